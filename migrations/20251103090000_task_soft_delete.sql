@@ -0,0 +1,6 @@
+-- Soft delete: deleted tasks stay in the table (with their dependency edges)
+-- until they are purged after the trash retention window
+ALTER TABLE tasks ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Only trashed rows are indexed; trash listing and the purge job are the only readers
+CREATE INDEX idx_tasks_deleted_at ON tasks(deleted_at) WHERE deleted_at IS NOT NULL;
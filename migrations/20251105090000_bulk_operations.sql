@@ -0,0 +1,7 @@
+-- Free-form labels on tasks
+ALTER TABLE tasks ADD COLUMN labels TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX idx_tasks_labels ON tasks USING GIN (labels);
+
+-- Audit entries written by one bulk request share a batch ID
+ALTER TABLE audit_logs ADD COLUMN batch_id UUID;
+CREATE INDEX idx_audit_logs_batch ON audit_logs(batch_id) WHERE batch_id IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Stored responses for requests sent with an Idempotency-Key header
+CREATE TABLE idempotency_keys (
+        -- The user ID, or 'anonymous' for public routes
+        scope TEXT NOT NULL,
+        idempotency_key VARCHAR(255) NOT NULL,
+        request_hash TEXT NOT NULL,
+        -- NULL while the original request is still being processed
+        response_status SMALLINT,
+        response_headers JSONB,
+        response_body BYTEA,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        expires_at TIMESTAMPTZ NOT NULL,
+        PRIMARY KEY (scope, idempotency_key)
+        );
+
+CREATE INDEX idx_idempotency_keys_expires ON idempotency_keys(expires_at);
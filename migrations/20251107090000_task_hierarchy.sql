@@ -0,0 +1,6 @@
+-- Parent/child decomposition of tasks
+ALTER TABLE tasks
+    ADD COLUMN parent_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    ADD CONSTRAINT no_self_parent CHECK (parent_id != id);
+
+CREATE INDEX idx_tasks_parent ON tasks(parent_id);
@@ -0,0 +1,42 @@
+-- Typed dependency links. `task_id` is the successor, `depends_on` the predecessor.
+--   finish_to_start   task cannot start until depends_on is completed
+--   start_to_start    task cannot start until depends_on has started
+--   finish_to_finish  task cannot be completed until depends_on is completed
+--   relates_to, duplicates  informational, never block
+-- The lag delays the blocking kinds by that many minutes after the predecessor event.
+ALTER TABLE task_dependencies
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'finish_to_start',
+    ADD COLUMN lag_minutes INTEGER NOT NULL DEFAULT 0,
+    ADD CONSTRAINT valid_dependency_kind CHECK (
+        kind IN ('finish_to_start', 'start_to_start', 'finish_to_finish', 'relates_to', 'duplicates')
+    ),
+    ADD CONSTRAINT valid_dependency_lag CHECK (
+        lag_minutes >= 0
+        AND (lag_minutes = 0 OR kind IN ('finish_to_start', 'start_to_start', 'finish_to_finish'))
+    );
+
+-- When work on a task started, needed for start-to-start links
+ALTER TABLE tasks ADD COLUMN started_at TIMESTAMPTZ;
+
+-- Best guess for tasks that were already started
+UPDATE tasks
+SET started_at = COALESCE(completed_at, updated_at, created_at)
+WHERE status != 'pending';
+
+-- Set started_at on the first move out of pending, clear it when a task goes back
+CREATE OR REPLACE FUNCTION track_task_start()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status = 'pending' THEN
+        NEW.started_at := NULL;
+    ELSIF NEW.started_at IS NULL THEN
+        NEW.started_at := NOW();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_track_start
+    BEFORE INSERT OR UPDATE OF status ON tasks
+    FOR EACH ROW
+    EXECUTE FUNCTION track_task_start();
@@ -0,0 +1,4 @@
+-- Estimated duration of a task, used for schedule (critical path) computation
+ALTER TABLE tasks
+    ADD COLUMN estimated_minutes INTEGER,
+    ADD CONSTRAINT non_negative_estimate CHECK (estimated_minutes >= 0);
@@ -0,0 +1,18 @@
+-- Status changes made automatically because of another change point at the audit
+-- entry of that change
+ALTER TABLE audit_logs ADD COLUMN caused_by UUID;
+CREATE INDEX idx_audit_logs_caused_by ON audit_logs(caused_by) WHERE caused_by IS NOT NULL;
+
+-- `blocked` and `cancelled` neither start a task nor clear its start: only moving to
+-- in_progress or completed sets started_at, only moving back to pending clears it
+CREATE OR REPLACE FUNCTION track_task_start()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status = 'pending' THEN
+        NEW.started_at := NULL;
+    ELSIF NEW.started_at IS NULL AND NEW.status IN ('in_progress', 'completed') THEN
+        NEW.started_at := NOW();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
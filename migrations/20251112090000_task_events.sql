@@ -0,0 +1,97 @@
+-- Durable log of task and dependency changes, streamed to clients over SSE.
+-- Rows are written by triggers, so every code path (bulk, propagation, revert, ...)
+-- is covered; the id doubles as the SSE event id clients resume from.
+CREATE TABLE task_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type VARCHAR(50) NOT NULL,
+    -- No foreign keys: events outlive purged tasks
+    task_id UUID NOT NULL,
+    project_id UUID,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_task_events_created ON task_events(created_at);
+
+-- Wake up every API instance; they read the event itself from the table
+CREATE OR REPLACE FUNCTION notify_task_event()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('task_events', NEW.id::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_events_notify
+    AFTER INSERT ON task_events
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_task_event();
+
+CREATE OR REPLACE FUNCTION record_task_event()
+RETURNS TRIGGER AS $$
+DECLARE
+    kind TEXT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        kind := 'task.created';
+    ELSIF OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN
+        kind := 'task.deleted';
+    ELSIF OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN
+        kind := 'task.restored';
+    ELSIF NEW.deleted_at IS NOT NULL THEN
+        -- Changes to trashed tasks are not visible anywhere
+        RETURN NULL;
+    ELSE
+        kind := 'task.updated';
+    END IF;
+
+    INSERT INTO task_events (event_type, task_id, project_id, payload)
+    VALUES (kind, NEW.id, NEW.project_id, to_jsonb(NEW));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_record_event
+    AFTER INSERT OR UPDATE ON tasks
+    FOR EACH ROW
+    EXECUTE FUNCTION record_task_event();
+
+CREATE OR REPLACE FUNCTION record_dependency_event()
+RETURNS TRIGGER AS $$
+DECLARE
+    link task_dependencies;
+    task_project UUID;
+    task_live BOOLEAN;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        link := OLD;
+    ELSE
+        link := NEW;
+    END IF;
+
+    -- Links of trashed or purged tasks are not reported
+    SELECT project_id, deleted_at IS NULL INTO task_project, task_live
+    FROM tasks WHERE id = link.task_id;
+    IF NOT FOUND OR NOT task_live THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO task_events (event_type, task_id, project_id, payload)
+    VALUES (
+        CASE TG_OP
+            WHEN 'INSERT' THEN 'dependency.added'
+            WHEN 'UPDATE' THEN 'dependency.updated'
+            ELSE 'dependency.removed'
+        END,
+        link.task_id,
+        task_project,
+        to_jsonb(link)
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_dependencies_record_event
+    AFTER INSERT OR UPDATE OR DELETE ON task_dependencies
+    FOR EACH ROW
+    EXECUTE FUNCTION record_dependency_event();
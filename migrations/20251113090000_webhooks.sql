@@ -0,0 +1,87 @@
+-- Outgoing webhooks: subscriptions, a durable delivery queue and its attempt log
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- Empty: every event type
+    event_types TEXT[] NOT NULL DEFAULT '{}',
+    -- NULL: every project the owner can see, and tasks outside any project
+    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    disabled_at TIMESTAMPTZ,
+    disabled_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhooks_owner ON webhooks(owner_id);
+
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    webhook_id UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    -- No foreign key: task events are purged before the delivery log
+    event_id BIGINT,
+    event_type VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMPTZ,
+    response_status INTEGER,
+    error TEXT,
+    redelivery_of UUID REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, created_at DESC);
+CREATE INDEX idx_webhook_deliveries_created ON webhook_deliveries(created_at);
+
+CREATE TABLE webhook_delivery_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    delivery_id UUID NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+    attempt INTEGER NOT NULL,
+    -- NULL when no response arrived (connection refused, timeout, ...)
+    response_status INTEGER,
+    response_body TEXT,
+    error TEXT,
+    duration_ms INTEGER NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_delivery_attempts_delivery ON webhook_delivery_attempts(delivery_id);
+
+-- Queue a delivery for every active webhook that wants the event, in the same
+-- transaction as the change itself. Project events only go to webhooks whose owner
+-- can see the project.
+CREATE OR REPLACE FUNCTION enqueue_webhook_deliveries()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO webhook_deliveries (webhook_id, event_id, event_type, payload)
+    SELECT w.id, NEW.id, NEW.event_type, to_jsonb(NEW)
+    FROM webhooks w
+    JOIN users u ON u.id = w.owner_id
+    WHERE w.active
+      AND (cardinality(w.event_types) = 0 OR NEW.event_type = ANY(w.event_types))
+      AND (w.project_id IS NULL OR w.project_id = NEW.project_id)
+      AND (
+          NEW.project_id IS NULL
+          OR u.role = 'admin'
+          OR EXISTS (
+              SELECT 1 FROM project_members pm
+              WHERE pm.project_id = NEW.project_id AND pm.user_id = w.owner_id
+          )
+      );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_events_enqueue_webhooks
+    AFTER INSERT ON task_events
+    FOR EACH ROW
+    EXECUTE FUNCTION enqueue_webhook_deliveries();
@@ -0,0 +1,62 @@
+-- Background jobs: a queue shared by every API instance, claimed with
+-- FOR UPDATE SKIP LOCKED
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL DEFAULT 'null',
+    -- queued -> running -> succeeded, or back to queued for a retry, or dead once
+    -- the attempts are used up
+    status VARCHAR(20) NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'succeeded', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    timeout_secs INTEGER NOT NULL DEFAULT 300,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- The schedule that queued the job; at most one of its jobs is pending at a time
+    schedule_name VARCHAR(100),
+    locked_by TEXT,
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_jobs_due ON jobs(run_at) WHERE status = 'queued';
+CREATE INDEX idx_jobs_lease ON jobs(locked_until) WHERE status = 'running';
+CREATE INDEX idx_jobs_kind_status ON jobs(kind, status);
+CREATE INDEX idx_jobs_finished ON jobs(finished_at) WHERE finished_at IS NOT NULL;
+CREATE UNIQUE INDEX idx_jobs_schedule_pending ON jobs(schedule_name)
+    WHERE schedule_name IS NOT NULL AND status IN ('queued', 'running');
+
+-- Recurring jobs. The definitions live in the code; this table holds when each
+-- one runs next, so that only one instance queues it.
+CREATE TABLE job_schedules (
+    name VARCHAR(100) PRIMARY KEY,
+    kind VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL DEFAULT 'null',
+    -- Exactly one of the two
+    cron VARCHAR(100),
+    interval_secs BIGINT,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_run_at TIMESTAMPTZ,
+    last_job_id UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((cron IS NULL) <> (interval_secs IS NULL))
+);
+
+-- Wake up idle workers when something becomes runnable
+CREATE OR REPLACE FUNCTION notify_job_queued()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status = 'queued' AND NEW.run_at <= NOW() THEN
+        PERFORM pg_notify('jobs_queued', NEW.kind);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER jobs_notify_queued
+    AFTER INSERT OR UPDATE OF status, run_at ON jobs
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_job_queued();
@@ -0,0 +1,192 @@
+-- In-app notifications. Task changes notify through a trigger on audit_logs, which
+-- knows who made the change; due-soon notifications come from a scheduled job.
+--   assigned        the task was assigned to you
+--   mentioned       you were mentioned (@email) in the task's title or description
+--   status_changed  the status of a task you created or are assigned to changed
+--   unblocked       the last dependency holding back your task was satisfied
+--   due_soon        your task is due within NOTIFY_DUE_SOON_HOURS
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(30) NOT NULL
+        CHECK (kind IN ('assigned', 'mentioned', 'status_changed', 'unblocked', 'due_soon')),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    -- The title when the notification was created
+    task_title VARCHAR(255) NOT NULL,
+    -- NULL for notifications nobody caused (due_soon)
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    -- Notifications with a key are only created once per user
+    dedup_key TEXT,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, dedup_key)
+);
+
+CREATE INDEX idx_notifications_user ON notifications(user_id, created_at DESC);
+CREATE INDEX idx_notifications_unread ON notifications(user_id) WHERE read_at IS NULL;
+CREATE INDEX idx_notifications_task ON notifications(task_id);
+CREATE INDEX idx_notifications_created ON notifications(created_at);
+
+-- Kinds a user turned off (or back on); a kind without a row is on
+CREATE TABLE notification_preferences (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(30) NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, kind)
+);
+
+-- Notify `recipient` about `task` (a task row as JSON) unless they caused the change,
+-- turned the kind off or cannot see the task. Returns whether a notification was
+-- created.
+CREATE OR REPLACE FUNCTION create_notification(
+    recipient UUID,
+    notification_kind TEXT,
+    task JSONB,
+    actor UUID,
+    details JSONB,
+    key TEXT DEFAULT NULL
+)
+RETURNS BOOLEAN AS $$
+BEGIN
+    IF recipient IS NULL OR recipient = actor THEN
+        RETURN FALSE;
+    END IF;
+
+    IF EXISTS (
+        SELECT 1 FROM notification_preferences
+        WHERE user_id = recipient AND kind = notification_kind AND NOT enabled
+    ) THEN
+        RETURN FALSE;
+    END IF;
+
+    IF task->>'project_id' IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM users u
+        WHERE u.id = recipient
+          AND (
+              u.role = 'admin'
+              OR EXISTS (
+                  SELECT 1 FROM project_members pm
+                  WHERE pm.project_id = (task->>'project_id')::UUID AND pm.user_id = recipient
+              )
+          )
+    ) THEN
+        RETURN FALSE;
+    END IF;
+
+    INSERT INTO notifications (user_id, kind, task_id, task_title, actor_id, details, dedup_key)
+    VALUES (recipient, notification_kind, (task->>'id')::UUID, task->>'title', actor, details, key)
+    ON CONFLICT (user_id, dedup_key) DO NOTHING;
+    RETURN FOUND;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Users mentioned as @email in a task's title or description
+CREATE OR REPLACE FUNCTION mentioned_users(task JSONB)
+RETURNS SETOF UUID AS $$
+    SELECT u.id
+    FROM users u
+    WHERE lower(u.email) IN (
+        SELECT lower(m[1])
+        FROM regexp_matches(
+            COALESCE(task->>'title', '') || ' ' || COALESCE(task->>'description', ''),
+            '@([A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,})',
+            'g'
+        ) AS m
+    );
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION notify_task_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    old_task JSONB := COALESCE(NEW.old_values, '{}');
+    new_task JSONB := NEW.new_values;
+    recipient UUID;
+    dependent tasks;
+BEGIN
+    -- Deletions have no new state, and a trashed task notifies nobody
+    IF NEW.resource_type <> 'task' OR new_task IS NULL OR new_task->>'deleted_at' IS NOT NULL THEN
+        RETURN NULL;
+    END IF;
+
+    IF new_task->>'assigned_to' IS DISTINCT FROM old_task->>'assigned_to' THEN
+        PERFORM create_notification(
+            (new_task->>'assigned_to')::UUID, 'assigned', new_task, NEW.user_id,
+            jsonb_build_object('previous_assignee', old_task->'assigned_to')
+        );
+    END IF;
+
+    -- Only new mentions: editing a description does not notify everyone in it again
+    FOR recipient IN
+        SELECT * FROM mentioned_users(new_task)
+        EXCEPT
+        SELECT * FROM mentioned_users(old_task)
+    LOOP
+        PERFORM create_notification(recipient, 'mentioned', new_task, NEW.user_id, '{}');
+    END LOOP;
+
+    IF old_task ? 'status' AND new_task->>'status' IS DISTINCT FROM old_task->>'status' THEN
+        FOR recipient IN
+            SELECT DISTINCT r FROM unnest(ARRAY[
+                (new_task->>'created_by')::UUID,
+                (new_task->>'assigned_to')::UUID
+            ]) AS r
+        LOOP
+            PERFORM create_notification(
+                recipient, 'status_changed', new_task, NEW.user_id,
+                jsonb_build_object(
+                    'from', old_task->'status',
+                    'to', new_task->'status',
+                    'caused_by', NEW.caused_by
+                )
+            );
+        END LOOP;
+
+        -- Dependents held back only by this task can go ahead now. Lag is ignored,
+        -- like in status propagation.
+        FOR dependent IN
+            SELECT d.*
+            FROM task_dependencies td
+            JOIN tasks d ON d.id = td.task_id
+            WHERE td.depends_on = NEW.resource_id
+              AND d.deleted_at IS NULL
+              AND d.status IN ('pending', 'blocked')
+              AND (
+                  (td.kind = 'finish_to_start'
+                      AND new_task->>'status' = 'completed'
+                      AND old_task->>'status' <> 'completed')
+                  OR (td.kind = 'start_to_start'
+                      AND new_task->>'started_at' IS NOT NULL
+                      AND old_task->>'started_at' IS NULL)
+              )
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM task_dependencies other
+                  JOIN tasks t ON t.id = other.depends_on
+                  WHERE other.task_id = d.id
+                    AND NOT CASE other.kind
+                        WHEN 'finish_to_start' THEN t.status = 'completed'
+                        WHEN 'start_to_start' THEN t.started_at IS NOT NULL
+                        ELSE TRUE
+                    END
+              )
+        LOOP
+            PERFORM create_notification(
+                COALESCE(dependent.assigned_to, dependent.created_by), 'unblocked',
+                to_jsonb(dependent), NEW.user_id,
+                jsonb_build_object(
+                    'unblocked_by', jsonb_build_object('id', new_task->'id', 'title', new_task->'title')
+                )
+            );
+        END LOOP;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_logs_notify
+    AFTER INSERT ON audit_logs
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_task_change();
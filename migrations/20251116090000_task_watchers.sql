@@ -0,0 +1,166 @@
+-- People following a task. The creator and every new assignee watch it
+-- automatically; anyone who can see the task can watch or unwatch it.
+CREATE TABLE task_watchers (
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (task_id, user_id)
+);
+
+CREATE INDEX idx_task_watchers_user ON task_watchers(user_id);
+
+INSERT INTO task_watchers (task_id, user_id)
+SELECT id, created_by FROM tasks WHERE deleted_at IS NULL
+UNION
+SELECT id, assigned_to FROM tasks WHERE deleted_at IS NULL AND assigned_to IS NOT NULL
+ON CONFLICT DO NOTHING;
+
+CREATE OR REPLACE FUNCTION auto_watch_task()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO task_watchers (task_id, user_id)
+        VALUES (NEW.id, NEW.created_by)
+        ON CONFLICT DO NOTHING;
+    END IF;
+
+    IF NEW.assigned_to IS NOT NULL
+       AND (TG_OP = 'INSERT' OR NEW.assigned_to IS DISTINCT FROM OLD.assigned_to) THEN
+        INSERT INTO task_watchers (task_id, user_id)
+        VALUES (NEW.id, NEW.assigned_to)
+        ON CONFLICT DO NOTHING;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_auto_watch
+    AFTER INSERT OR UPDATE OF assigned_to ON tasks
+    FOR EACH ROW
+    EXECUTE FUNCTION auto_watch_task();
+
+-- Watching and unwatching are task events too, so live streams that follow a
+-- user's watched tasks can keep up
+CREATE OR REPLACE FUNCTION record_watcher_event()
+RETURNS TRIGGER AS $$
+DECLARE
+    watcher task_watchers;
+    task_project UUID;
+    task_live BOOLEAN;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        watcher := OLD;
+    ELSE
+        watcher := NEW;
+    END IF;
+
+    -- Watchers of trashed or purged tasks are not reported
+    SELECT project_id, deleted_at IS NULL INTO task_project, task_live
+    FROM tasks WHERE id = watcher.task_id;
+    IF NOT FOUND OR NOT task_live THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO task_events (event_type, task_id, project_id, payload)
+    VALUES (
+        CASE TG_OP WHEN 'INSERT' THEN 'watcher.added' ELSE 'watcher.removed' END,
+        watcher.task_id,
+        task_project,
+        to_jsonb(watcher)
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_watchers_record_event
+    AFTER INSERT OR DELETE ON task_watchers
+    FOR EACH ROW
+    EXECUTE FUNCTION record_watcher_event();
+
+-- Status changes now notify the task's watchers rather than only its creator and
+-- assignee
+CREATE OR REPLACE FUNCTION notify_task_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    old_task JSONB := COALESCE(NEW.old_values, '{}');
+    new_task JSONB := NEW.new_values;
+    recipient UUID;
+    dependent tasks;
+BEGIN
+    -- Deletions have no new state, and a trashed task notifies nobody
+    IF NEW.resource_type <> 'task' OR new_task IS NULL OR new_task->>'deleted_at' IS NOT NULL THEN
+        RETURN NULL;
+    END IF;
+
+    IF new_task->>'assigned_to' IS DISTINCT FROM old_task->>'assigned_to' THEN
+        PERFORM create_notification(
+            (new_task->>'assigned_to')::UUID, 'assigned', new_task, NEW.user_id,
+            jsonb_build_object('previous_assignee', old_task->'assigned_to')
+        );
+    END IF;
+
+    -- Only new mentions: editing a description does not notify everyone in it again
+    FOR recipient IN
+        SELECT * FROM mentioned_users(new_task)
+        EXCEPT
+        SELECT * FROM mentioned_users(old_task)
+    LOOP
+        PERFORM create_notification(recipient, 'mentioned', new_task, NEW.user_id, '{}');
+    END LOOP;
+
+    IF old_task ? 'status' AND new_task->>'status' IS DISTINCT FROM old_task->>'status' THEN
+        FOR recipient IN
+            SELECT user_id FROM task_watchers WHERE task_id = NEW.resource_id
+        LOOP
+            PERFORM create_notification(
+                recipient, 'status_changed', new_task, NEW.user_id,
+                jsonb_build_object(
+                    'from', old_task->'status',
+                    'to', new_task->'status',
+                    'caused_by', NEW.caused_by
+                )
+            );
+        END LOOP;
+
+        -- Dependents held back only by this task can go ahead now. Lag is ignored,
+        -- like in status propagation.
+        FOR dependent IN
+            SELECT d.*
+            FROM task_dependencies td
+            JOIN tasks d ON d.id = td.task_id
+            WHERE td.depends_on = NEW.resource_id
+              AND d.deleted_at IS NULL
+              AND d.status IN ('pending', 'blocked')
+              AND (
+                  (td.kind = 'finish_to_start'
+                      AND new_task->>'status' = 'completed'
+                      AND old_task->>'status' <> 'completed')
+                  OR (td.kind = 'start_to_start'
+                      AND new_task->>'started_at' IS NOT NULL
+                      AND old_task->>'started_at' IS NULL)
+              )
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM task_dependencies other
+                  JOIN tasks t ON t.id = other.depends_on
+                  WHERE other.task_id = d.id
+                    AND NOT CASE other.kind
+                        WHEN 'finish_to_start' THEN t.status = 'completed'
+                        WHEN 'start_to_start' THEN t.started_at IS NOT NULL
+                        ELSE TRUE
+                    END
+              )
+        LOOP
+            PERFORM create_notification(
+                COALESCE(dependent.assigned_to, dependent.created_by), 'unblocked',
+                to_jsonb(dependent), NEW.user_id,
+                jsonb_build_object(
+                    'unblocked_by', jsonb_build_object('id', new_task->'id', 'title', new_task->'title')
+                )
+            );
+        END LOOP;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
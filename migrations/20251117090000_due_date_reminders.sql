@@ -0,0 +1,28 @@
+-- Due-date reminders and overdue tracking
+--   overdue    your task passed its due date
+--   escalated  a high or urgent task of your project has been overdue for
+--              ESCALATE_OVERDUE_AFTER_HOURS
+ALTER TABLE notifications DROP CONSTRAINT notifications_kind_check;
+ALTER TABLE notifications ADD CONSTRAINT notifications_kind_check CHECK (
+    kind IN ('assigned', 'mentioned', 'status_changed', 'unblocked', 'due_soon', 'overdue', 'escalated')
+);
+
+-- How long before a due date a user wants the due_soon reminder; users without a
+-- row get NOTIFY_DUE_SOON_HOURS
+CREATE TABLE reminder_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    hours_before_due INTEGER NOT NULL CHECK (hours_before_due BETWEEN 1 AND 720),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Open tasks the overdue scan found past their due date. A row is removed once the
+-- task is closed, trashed or given a new due date, so a missed new due date is
+-- reported again.
+CREATE TABLE overdue_tasks (
+    task_id UUID PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+    -- The due date that was missed
+    due_date TIMESTAMPTZ NOT NULL,
+    marked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- When the project owner was told
+    escalated_at TIMESTAMPTZ
+);
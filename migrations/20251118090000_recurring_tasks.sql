@@ -0,0 +1,75 @@
+-- Recurring tasks. A series holds a recurrence rule (an RRULE subset, see
+-- src/utils/rrule.rs) and a template; each occurrence is an ordinary task.
+CREATE TABLE task_series (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rrule TEXT NOT NULL,
+    -- Due date of the first occurrence; the rule counts from here
+    dtstart TIMESTAMPTZ NOT NULL,
+    -- on_complete: the next occurrence is created once the current one is closed
+    -- on_schedule: each occurrence is created lead_time_hours before it is due
+    generate VARCHAR(20) NOT NULL DEFAULT 'on_complete'
+        CHECK (generate IN ('on_complete', 'on_schedule')),
+    lead_time_hours INTEGER NOT NULL DEFAULT 24 CHECK (lead_time_hours BETWEEN 1 AND 8760),
+    -- Template for new occurrences
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    priority VARCHAR(20) NOT NULL DEFAULT 'medium',
+    labels TEXT[] NOT NULL DEFAULT '{}',
+    assigned_to UUID REFERENCES users(id) ON DELETE SET NULL,
+    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
+    estimated_minutes INTEGER,
+    copy_labels BOOLEAN NOT NULL DEFAULT TRUE,
+    copy_assignee BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Give each occurrence the dependencies of the one before
+    copy_dependencies BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Number (1-based) and due date of the next occurrence to create; null once the
+    -- rule has no more
+    next_occurrence INTEGER NOT NULL DEFAULT 2,
+    next_due_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Set when the series is stopped, runs out, or is split by a "this and future"
+    -- edit; no more occurrences are created
+    ended_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_task_series_active ON task_series(next_due_at) WHERE ended_at IS NULL;
+CREATE INDEX idx_task_series_project ON task_series(project_id);
+
+-- The tasks of a series. Occurrences skipped because they were already past due
+-- when their turn came keep their number, so COUNT still holds.
+CREATE TABLE task_occurrences (
+    task_id UUID PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+    series_id UUID NOT NULL REFERENCES task_series(id) ON DELETE CASCADE,
+    occurrence INTEGER NOT NULL,
+    -- The due date the rule gave it, whatever the task's due date is now
+    scheduled_for TIMESTAMPTZ NOT NULL,
+    UNIQUE (series_id, occurrence)
+);
+
+-- Closing an occurrence of an on_complete series queues a recurrence scan right
+-- away instead of waiting for the next scheduled one
+CREATE OR REPLACE FUNCTION queue_recurrence_scan() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status IN ('completed', 'cancelled')
+       AND OLD.status NOT IN ('completed', 'cancelled')
+       AND EXISTS (
+           SELECT 1 FROM task_occurrences o
+           JOIN task_series s ON s.id = o.series_id
+           WHERE o.task_id = NEW.id AND s.ended_at IS NULL AND s.generate = 'on_complete'
+       )
+       AND NOT EXISTS (
+           SELECT 1 FROM jobs
+           WHERE kind = 'recurrence_scan' AND status = 'queued' AND schedule_name IS NULL
+       )
+    THEN
+        INSERT INTO jobs (kind, payload) VALUES ('recurrence_scan', 'null');
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_queue_recurrence_scan
+    AFTER UPDATE OF status ON tasks
+    FOR EACH ROW EXECUTE FUNCTION queue_recurrence_scan();
@@ -0,0 +1,8 @@
+-- Per-user iCalendar feeds. Calendar apps cannot send an Authorization header, so
+-- the feed URL carries a secret token; only its SHA-256 is stored.
+CREATE TABLE calendar_feeds (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ
+);
@@ -0,0 +1,20 @@
+-- Time spent on tasks, per user: timers (started and stopped) and manual entries.
+-- Compared with tasks.estimated_minutes in the time rollups.
+CREATE TABLE time_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    -- Null while the timer runs
+    ended_at TIMESTAMPTZ,
+    note TEXT,
+    source VARCHAR(10) NOT NULL CHECK (source IN ('timer', 'manual')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT time_entry_ends_after_start CHECK (ended_at IS NULL OR ended_at >= started_at)
+);
+
+-- One running timer per user
+CREATE UNIQUE INDEX idx_time_entries_running ON time_entries(user_id) WHERE ended_at IS NULL;
+CREATE INDEX idx_time_entries_task ON time_entries(task_id);
+CREATE INDEX idx_time_entries_user_started ON time_entries(user_id, started_at);
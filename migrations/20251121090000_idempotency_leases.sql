@@ -0,0 +1,13 @@
+-- An in-flight request holds its key for a short lease. When the request never
+-- finishes (client gone, crash, restart) the key can be claimed again once the
+-- lease is over, instead of answering 409 until the key expires.
+ALTER TABLE idempotency_keys
+    ADD COLUMN lease_id UUID,
+    ADD COLUMN locked_until TIMESTAMPTZ;
+
+-- Unfinished reservations from before leases existed can be claimed right away
+UPDATE idempotency_keys SET locked_until = NOW() WHERE response_status IS NULL;
+
+-- Public routes no longer take idempotency keys; their stored responses (login
+-- and register responses carry tokens) are dropped
+DELETE FROM idempotency_keys WHERE scope = 'anonymous';
@@ -0,0 +1,3 @@
+-- Receivers' response bodies are no longer kept: a webhook URL pointing into the
+-- server's network would otherwise let its owner read the answers
+ALTER TABLE webhook_delivery_attempts DROP COLUMN response_body;
@@ -0,0 +1,37 @@
+-- Rows outside every monthly partition (a skewed clock, or a month the retention job
+-- has not created yet) land in a default partition instead of failing the insert.
+-- The retention job moves them into monthly partitions on its next run.
+CREATE TABLE IF NOT EXISTS audit_logs_default PARTITION OF audit_logs DEFAULT;
+
+-- Creates the partition holding the month that contains `month_start` (no-op if it
+-- exists). A partition cannot be attached over rows the default partition holds for
+-- its range, so those are moved into the new table before it is attached.
+CREATE OR REPLACE FUNCTION create_audit_log_partition(month_start DATE)
+RETURNS TEXT AS $$
+DECLARE
+    lower_bound DATE := date_trunc('month', month_start)::DATE;
+    upper_bound DATE := (date_trunc('month', month_start) + INTERVAL '1 month')::DATE;
+    partition_name TEXT := 'audit_logs_y' || to_char(lower_bound, 'YYYY') || 'm' || to_char(lower_bound, 'MM');
+BEGIN
+    IF to_regclass(quote_ident(partition_name)) IS NOT NULL THEN
+        RETURN partition_name;
+    END IF;
+
+    EXECUTE format(
+        'CREATE TABLE %I (LIKE audit_logs INCLUDING DEFAULTS INCLUDING CONSTRAINTS)',
+        partition_name
+    );
+    EXECUTE format(
+        'WITH moved AS (
+            DELETE FROM audit_logs_default WHERE created_at >= %L AND created_at < %L RETURNING *
+        )
+        INSERT INTO %I SELECT * FROM moved',
+        lower_bound, upper_bound, partition_name
+    );
+    EXECUTE format(
+        'ALTER TABLE audit_logs ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+        partition_name, lower_bound, upper_bound
+    );
+    RETURN partition_name;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,6 @@
+-- Time entries record work that was done: purging a trashed task must not take them
+-- with it. The trash purge leaves tasks with time entries in the trash.
+ALTER TABLE time_entries
+    DROP CONSTRAINT time_entries_task_id_fkey,
+    ADD CONSTRAINT time_entries_task_id_fkey
+        FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE RESTRICT;
@@ -1,6 +1,10 @@
+use sqlx::{PgConnection, PgPool};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+const BLOCKING_KINDS: [&str; 3] = ["finish_to_start", "start_to_start", "finish_to_finish"];
+
 fn has_cycle_dfs(
     graph: &HashMap<Uuid, Vec<Uuid>>,
     node: Uuid,
@@ -38,7 +42,16 @@ fn has_cycle_dfs(
     false
 }
 
-fn main() {
+#[tokio::main]
+async fn main() {
+    // `cargo run --bin test_cycle_detection -- bench [edges]` benchmarks instead
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let edges = args.get(2).and_then(|n| n.parse().ok()).unwrap_or(100_000);
+        bench(edges).await;
+        return;
+    }
+
     println!("##### Test Cycle Detection Algorithm! #####");
     let a = Uuid::new_v4();
     let b = Uuid::new_v4();
@@ -88,3 +101,168 @@ fn main() {
     let has_cycle = has_cycle_dfs(&graph3, a, &mut visited, &mut rec_stack, 0);
     println!("Result: {}\n", if has_cycle { "CYCLE ❌" } else { "NO CYCLE ✅" });
 }
+
+// The previous DependencyRepository::would_create_cycle: load every edge, then DFS
+async fn would_create_cycle_dfs(conn: &mut PgConnection, from: Uuid, to: Uuid) -> bool {
+    let all_deps = sqlx::query_as::<_, (Uuid, Uuid)>(
+        "SELECT task_id, depends_on FROM task_dependencies WHERE kind = ANY($1)",
+    )
+    .bind(&BLOCKING_KINDS[..])
+    .fetch_all(conn)
+    .await
+    .unwrap();
+
+    let mut graph: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (task_id, depends_on) in all_deps {
+        graph.entry(task_id).or_default().push(depends_on);
+    }
+    graph.entry(from).or_default().push(to);
+
+    let mut visited = HashSet::new();
+    let mut rec_stack = HashSet::new();
+    has_cycle_quiet(&graph, from, &mut visited, &mut rec_stack)
+}
+
+fn has_cycle_quiet(
+    graph: &HashMap<Uuid, Vec<Uuid>>,
+    node: Uuid,
+    visited: &mut HashSet<Uuid>,
+    rec_stack: &mut HashSet<Uuid>,
+) -> bool {
+    visited.insert(node);
+    rec_stack.insert(node);
+
+    if let Some(neighbors) = graph.get(&node) {
+        for &neighbor in neighbors {
+            if !visited.contains(&neighbor) {
+                if has_cycle_quiet(graph, neighbor, visited, rec_stack) {
+                    return true;
+                }
+            } else if rec_stack.contains(&neighbor) {
+                return true;
+            }
+        }
+    }
+
+    rec_stack.remove(&node);
+    false
+}
+
+// The current DependencyRepository::would_create_cycle: does `to` already reach `from`?
+async fn would_create_cycle_cte(conn: &mut PgConnection, from: Uuid, to: Uuid) -> bool {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        WITH RECURSIVE reachable(id) AS (
+            SELECT $1::uuid
+            UNION
+            SELECT td.depends_on
+            FROM task_dependencies td
+            JOIN reachable r ON td.task_id = r.id
+            WHERE td.kind = ANY($3)
+        )
+        SELECT EXISTS(SELECT 1 FROM reachable WHERE id = $2)
+        "#,
+    )
+    .bind(to)
+    .bind(from)
+    .bind(&BLOCKING_KINDS[..])
+    .fetch_one(conn)
+    .await
+    .unwrap()
+}
+
+// Small xorshift generator, so runs are repeatable without extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// Generate a random acyclic graph inside a transaction that is rolled back at the
+// end, then time both checks on the same candidate edges
+async fn bench(edges: usize) {
+    println!("##### Cycle Detection Benchmark ({} edges) #####", edges);
+    dotenv::dotenv().ok();
+
+    let database_url = std::env::var("DATABASE_URL").unwrap();
+    let pool = PgPool::connect(&database_url).await.unwrap();
+    let mut tx = pool.begin().await.unwrap();
+
+    let user_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO users (email, password_hash) VALUES ($1, '') RETURNING id",
+    )
+    .bind(format!("bench-{}@example.com", Uuid::new_v4()))
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    // Five edges per task on average; edges always point to an older task, so the
+    // generated graph has no cycles
+    let task_count = (edges / 5).max(2);
+    let ids: Vec<Uuid> = (0..task_count).map(|_| Uuid::new_v4()).collect();
+    sqlx::query("INSERT INTO tasks (id, title, created_by) SELECT unnest($1::uuid[]), 'bench', $2")
+        .bind(&ids)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut from = Vec::with_capacity(edges);
+    let mut to = Vec::with_capacity(edges);
+    for _ in 0..edges {
+        let a = 1 + rng.below(task_count - 1);
+        from.push(ids[a]);
+        to.push(ids[rng.below(a)]);
+    }
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO task_dependencies (task_id, depends_on)
+        SELECT * FROM unnest($1::uuid[], $2::uuid[])
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&from)
+    .bind(&to)
+    .execute(&mut *tx)
+    .await
+    .unwrap()
+    .rows_affected();
+    sqlx::query("ANALYZE task_dependencies").execute(&mut *tx).await.unwrap();
+    println!("Generated {} tasks and {} edges\n", task_count, inserted);
+
+    // Candidate edges in both directions: old → new never closes a cycle here,
+    // new → old often does
+    let candidates: Vec<(Uuid, Uuid)> = (0..50)
+        .map(|_| (ids[rng.below(task_count)], ids[rng.below(task_count)]))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    let mut dfs_time = Duration::ZERO;
+    let mut cte_time = Duration::ZERO;
+    let mut cycles = 0;
+    for &(a, b) in &candidates {
+        let start = Instant::now();
+        let dfs = would_create_cycle_dfs(&mut tx, a, b).await;
+        dfs_time += start.elapsed();
+
+        let start = Instant::now();
+        let cte = would_create_cycle_cte(&mut tx, a, b).await;
+        cte_time += start.elapsed();
+
+        assert_eq!(dfs, cte, "DFS and CTE disagree on {} -> {}", a, b);
+        cycles += cte as usize;
+    }
+
+    let n = candidates.len() as u32;
+    println!("Checked {} candidate edges ({} would close a cycle)", n, cycles);
+    println!("DFS (load all edges): {:?} per check", dfs_time / n);
+    println!("Recursive CTE:        {:?} per check", cte_time / n);
+
+    tx.rollback().await.unwrap();
+}
@@ -1,51 +1,200 @@
+// Render the task dependency graph from the database.
+//
+//   visualize_deps [--root <task id>] [--depth <n>] [--format text|dot|mermaid|json]
+//                  [--output <file>]
+//
+// Without --root the whole graph is rendered. `dot -Tsvg` turns the dot output into
+// an image; the mermaid output can be pasted into any Mermaid renderer.
+use anyhow::{Context, Result, anyhow, bail};
 use sqlx::PgPool;
 use std::collections::HashMap;
 use uuid::Uuid;
 
-#[tokio::main]
-async fn main() {
-    println!("##### Visualize the Dependency Graph! #####");
-    dotenv::dotenv().ok();
+#[path = "../utils/graph_export.rs"]
+mod graph_export;
+
+use graph_export::{GraphEdge, GraphNode};
+
+const USAGE: &str = "usage: visualize_deps [--root <task id>] [--depth <n>] \
+                     [--format text|dot|mermaid|json] [--output <file>]";
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Dot,
+    Mermaid,
+    Json,
+}
+
+struct Args {
+    root: Option<Uuid>,
+    depth: i32,
+    format: Format,
+    output: Option<String>,
+}
 
-    let database_url = std::env::var("DATABASE_URL").unwrap();
-    let pool = PgPool::connect(&database_url).await.unwrap();
-
-    // Get all tasks
-    let tasks = sqlx::query_as::<_, (Uuid, String)>("SELECT id, title FROM tasks ORDER BY title")
-        .fetch_all(&pool)
-        .await
-        .unwrap();
-
-    // Get all dependencies
-    let deps =
-        sqlx::query_as::<_, (Uuid, Uuid)>("SELECT task_id, depends_on FROM task_dependencies")
-            .fetch_all(&pool)
-            .await
-            .unwrap();
-
-    // Build adjacency list
-    let mut graph: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
-    for (task_id, depends_on) in deps {
-        graph.entry(task_id).or_insert_with(Vec::new).push(depends_on);
+fn parse_args() -> Result<Args> {
+    let mut args = Args { root: None, depth: 10, format: Format::Text, output: None };
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(flag) = argv.next() {
+        if flag == "-h" || flag == "--help" {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+        let value = argv.next().ok_or_else(|| anyhow!("{} needs a value\n{}", flag, USAGE))?;
+        match flag.as_str() {
+            "--root" => {
+                args.root = Some(value.parse().with_context(|| format!("invalid task id {value}"))?)
+            }
+            "--depth" => {
+                args.depth = value.parse().with_context(|| format!("invalid depth {value}"))?;
+                if args.depth < 1 {
+                    bail!("--depth must be at least 1");
+                }
+            }
+            "--format" => {
+                args.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "dot" => Format::Dot,
+                    "mermaid" => Format::Mermaid,
+                    "json" => Format::Json,
+                    other => bail!("unknown format {other}\n{USAGE}"),
+                }
+            }
+            "--output" | "-o" => args.output = Some(value),
+            other => bail!("unknown argument {other}\n{USAGE}"),
+        }
     }
 
-    // Task ID to a title map
-    let task_map: HashMap<Uuid, String> = tasks.into_iter().collect();
+    Ok(args)
+}
 
-    println!("📊 Task Dependency Graph:\n");
+// Live tasks (all of them, or those within `depth` links of the root either way)
+async fn load_nodes(pool: &PgPool, root: Option<Uuid>, depth: i32) -> Result<Vec<GraphNode>> {
+    let nodes = match root {
+        None => {
+            sqlx::query_as::<_, GraphNode>(
+                "SELECT id, title, status, priority FROM tasks \
+                 WHERE deleted_at IS NULL ORDER BY title",
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        Some(root) => {
+            let nodes = sqlx::query_as::<_, GraphNode>(
+                r#"
+                WITH RECURSIVE upstream(id, depth) AS (
+                    SELECT $1::uuid, 0
+                    UNION
+                    SELECT td.depends_on, u.depth + 1
+                    FROM task_dependencies td JOIN upstream u ON td.task_id = u.id
+                    WHERE u.depth < $2
+                ),
+                downstream(id, depth) AS (
+                    SELECT $1::uuid, 0
+                    UNION
+                    SELECT td.task_id, d.depth + 1
+                    FROM task_dependencies td JOIN downstream d ON td.depends_on = d.id
+                    WHERE d.depth < $2
+                )
+                SELECT id, title, status, priority FROM tasks
+                WHERE deleted_at IS NULL
+                  AND id IN (SELECT id FROM upstream UNION SELECT id FROM downstream)
+                ORDER BY title
+                "#,
+            )
+            .bind(root)
+            .bind(depth)
+            .fetch_all(pool)
+            .await?;
 
-    for (task_id, title) in task_map.iter() {
-        println!("Task: {}", title);
+            if !nodes.iter().any(|node| node.id == root) {
+                bail!("task {} not found", root);
+            }
+            nodes
+        }
+    };
 
-        if let Some(dependencies) = graph.get(task_id) {
-            for dep_id in dependencies {
-                if let Some(dep_title) = task_map.get(dep_id) {
-                    println!("  └─ depends on: {}", dep_title);
+    Ok(nodes)
+}
+
+async fn load_edges(pool: &PgPool, nodes: &[GraphNode]) -> Result<Vec<GraphEdge>> {
+    let ids: Vec<Uuid> = nodes.iter().map(|node| node.id).collect();
+    let edges = sqlx::query_as::<_, GraphEdge>(
+        "SELECT task_id, depends_on, kind, lag_minutes FROM task_dependencies \
+         WHERE task_id = ANY($1) AND depends_on = ANY($1) ORDER BY created_at",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(edges)
+}
+
+// The original plain listing: every task with what it depends on
+fn to_text(nodes: &[GraphNode], edges: &[GraphEdge]) -> String {
+    let titles: HashMap<Uuid, &str> =
+        nodes.iter().map(|node| (node.id, node.title.as_str())).collect();
+    let mut depends_on: HashMap<Uuid, Vec<&GraphEdge>> = HashMap::new();
+    for edge in edges {
+        depends_on.entry(edge.task_id).or_default().push(edge);
+    }
+
+    let mut out = String::from("📊 Task Dependency Graph:\n\n");
+    for node in nodes {
+        out.push_str(&format!("Task: {} [{}, {}]\n", node.title, node.status, node.priority));
+        match depends_on.get(&node.id) {
+            Some(links) => {
+                for link in links {
+                    let lag = if link.lag_minutes > 0 {
+                        format!(" +{}m", link.lag_minutes)
+                    } else {
+                        String::new()
+                    };
+                    out.push_str(&format!(
+                        "  └─ {}{}: {}\n",
+                        link.kind, lag, titles[&link.depends_on]
+                    ));
                 }
             }
-        } else {
-            println!("  └─ (no dependencies)");
+            None => out.push_str("  └─ (no dependencies)\n"),
         }
-        println!();
+        out.push('\n');
     }
+    out
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let args = parse_args()?;
+
+    let database_url = std::env::var("DATABASE_URL").context("DATABASE_URL is not set")?;
+    let pool = PgPool::connect(&database_url).await.context("cannot connect to the database")?;
+
+    let nodes = load_nodes(&pool, args.root, args.depth).await?;
+    let edges = load_edges(&pool, &nodes).await?;
+
+    let rendered = match args.format {
+        Format::Text => to_text(&nodes, &edges),
+        Format::Dot => graph_export::to_dot(&nodes, &edges),
+        Format::Mermaid => graph_export::to_mermaid(&nodes, &edges),
+        Format::Json => {
+            let mut json =
+                serde_json::to_string_pretty(&graph_export::to_json_graph(&nodes, &edges))?;
+            json.push('\n');
+            json
+        }
+    };
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, rendered).with_context(|| format!("cannot write {path}"))?;
+            eprintln!("Wrote {} tasks and {} links to {}", nodes.len(), edges.len(), path);
+        }
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
 }
@@ -6,6 +6,59 @@ pub struct Config {
     pub server_port: u16,
     pub jwt_secret: String,
     pub jwt_expiration_hours: i64,
+    pub audit_retention_months: u32,
+    pub audit_archive_dir: String,
+    pub audit_retention_interval_secs: u64,
+    pub trash_retention_days: i64,
+    pub trash_purge_interval_secs: u64,
+    pub require_if_match: bool,
+    pub idempotency_ttl_hours: i64,
+    pub max_task_depth: i32,
+    pub propagation: PropagationRules,
+    pub event_retention_hours: i64,
+    pub webhooks: WebhookSettings,
+    pub job_concurrency: usize,
+    pub job_retention_days: i64,
+    pub notifications: NotificationSettings,
+    // How far ahead calendar feeds list occurrences of recurring tasks
+    pub calendar_horizon_days: i64,
+}
+
+// What happens to dependent tasks when a task's status changes
+#[derive(Clone, Copy, Debug)]
+pub struct PropagationRules {
+    // Move started dependents to `blocked` when a dependency stops allowing them to run
+    pub block_on_reopen: bool,
+    // Move `blocked` dependents back to `pending` once their dependencies allow it again
+    pub unblock_on_complete: bool,
+    // Cancel every open task that transitively depends on a cancelled one
+    pub cascade_cancellation: bool,
+}
+
+// Delivery of outgoing webhooks
+#[derive(Clone, Copy, Debug)]
+pub struct WebhookSettings {
+    // Attempts per delivery before it is marked failed
+    pub max_attempts: i32,
+    // Wait before the first retry; doubles with every further attempt
+    pub retry_base_secs: i64,
+    // Failed attempts in a row after which a webhook is disabled
+    pub disable_after: i32,
+    pub timeout_secs: u64,
+    pub delivery_retention_days: i64,
+    // Let webhooks target loopback and private addresses (local development only)
+    pub allow_private_targets: bool,
+}
+
+// In-app notifications
+#[derive(Clone, Copy, Debug)]
+pub struct NotificationSettings {
+    // How long before its due date a task counts as due soon, for users who did not
+    // choose
+    pub due_soon_hours: i64,
+    // How long a high or urgent task is overdue before its project owner is told
+    pub escalate_overdue_after_hours: i64,
+    pub retention_days: i64,
 }
 
 impl Config {
@@ -22,6 +75,106 @@ impl Config {
                 .unwrap_or_else(|_| "24".to_string())
                 .parse()
                 .expect("JWT_EXPIRATION_HOURS must be a number"),
+            audit_retention_months: env::var("AUDIT_RETENTION_MONTHS")
+                .unwrap_or_else(|_| "6".to_string())
+                .parse()
+                .expect("AUDIT_RETENTION_MONTHS must be a number"),
+            audit_archive_dir: env::var("AUDIT_ARCHIVE_DIR")
+                .unwrap_or_else(|_| "./archive/audit_logs".to_string()),
+            audit_retention_interval_secs: env::var("AUDIT_RETENTION_INTERVAL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .expect("AUDIT_RETENTION_INTERVAL_SECS must be a number"),
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("TRASH_RETENTION_DAYS must be a number"),
+            trash_purge_interval_secs: env::var("TRASH_PURGE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .expect("TRASH_PURGE_INTERVAL_SECS must be a number"),
+            require_if_match: env::var("REQUIRE_IF_MATCH")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("REQUIRE_IF_MATCH must be true or false"),
+            idempotency_ttl_hours: env::var("IDEMPOTENCY_TTL_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .expect("IDEMPOTENCY_TTL_HOURS must be a number"),
+            max_task_depth: env::var("MAX_TASK_DEPTH")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("MAX_TASK_DEPTH must be a number"),
+            propagation: PropagationRules {
+                block_on_reopen: env::var("PROPAGATE_BLOCK_ON_REOPEN")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .expect("PROPAGATE_BLOCK_ON_REOPEN must be true or false"),
+                unblock_on_complete: env::var("PROPAGATE_UNBLOCK_ON_COMPLETE")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .expect("PROPAGATE_UNBLOCK_ON_COMPLETE must be true or false"),
+                cascade_cancellation: env::var("PROPAGATE_CANCELLATION")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .expect("PROPAGATE_CANCELLATION must be true or false"),
+            },
+            event_retention_hours: env::var("EVENT_RETENTION_HOURS")
+                .unwrap_or_else(|_| "72".to_string())
+                .parse()
+                .expect("EVENT_RETENTION_HOURS must be a number"),
+            webhooks: WebhookSettings {
+                max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+                    .unwrap_or_else(|_| "8".to_string())
+                    .parse()
+                    .expect("WEBHOOK_MAX_ATTEMPTS must be a number"),
+                retry_base_secs: env::var("WEBHOOK_RETRY_BASE_SECS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .expect("WEBHOOK_RETRY_BASE_SECS must be a number"),
+                disable_after: env::var("WEBHOOK_DISABLE_AFTER")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .expect("WEBHOOK_DISABLE_AFTER must be a number"),
+                timeout_secs: env::var("WEBHOOK_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .expect("WEBHOOK_TIMEOUT_SECS must be a number"),
+                delivery_retention_days: env::var("WEBHOOK_DELIVERY_RETENTION_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .expect("WEBHOOK_DELIVERY_RETENTION_DAYS must be a number"),
+                allow_private_targets: env::var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .expect("WEBHOOK_ALLOW_PRIVATE_TARGETS must be true or false"),
+            },
+            job_concurrency: env::var("JOB_CONCURRENCY")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()
+                .expect("JOB_CONCURRENCY must be a number"),
+            job_retention_days: env::var("JOB_RETENTION_DAYS")
+                .unwrap_or_else(|_| "7".to_string())
+                .parse()
+                .expect("JOB_RETENTION_DAYS must be a number"),
+            notifications: NotificationSettings {
+                due_soon_hours: env::var("NOTIFY_DUE_SOON_HOURS")
+                    .unwrap_or_else(|_| "24".to_string())
+                    .parse()
+                    .expect("NOTIFY_DUE_SOON_HOURS must be a number"),
+                escalate_overdue_after_hours: env::var("ESCALATE_OVERDUE_AFTER_HOURS")
+                    .unwrap_or_else(|_| "24".to_string())
+                    .parse()
+                    .expect("ESCALATE_OVERDUE_AFTER_HOURS must be a number"),
+                retention_days: env::var("NOTIFICATION_RETENTION_DAYS")
+                    .unwrap_or_else(|_| "90".to_string())
+                    .parse()
+                    .expect("NOTIFICATION_RETENTION_DAYS must be a number"),
+            },
+            calendar_horizon_days: env::var("CALENDAR_HORIZON_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .expect("CALENDAR_HORIZON_DAYS must be a number"),
         }
     }
 }
@@ -1,9 +1,12 @@
-use sqlx::{PgPool, types::JsonValue};
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use futures::TryStreamExt;
+use sqlx::{PgConnection, PgPool, types::JsonValue};
+use std::io::Write;
 use uuid::Uuid;
 
 use crate::{
-    error::Result,
-    models::{AuditLog, AuditLogWithUser},
+    error::{AppError, Result},
+    models::{AuditArchive, AuditLog, AuditLogWithUser, AuditPartition},
 };
 
 pub struct AuditRepository {
@@ -16,6 +19,7 @@ impl AuditRepository {
     }
 
     // Log an action
+    #[allow(clippy::too_many_arguments)]
     pub async fn log_action(
         &self,
         user_id: Uuid,
@@ -49,6 +53,105 @@ impl AuditRepository {
         Ok(log)
     }
 
+    // Log an action inside the transaction that makes it
+    pub async fn log_action_in(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        action: &str,
+        resource_type: &str,
+        resource_id: Uuid,
+        old_values: Option<JsonValue>,
+        new_values: Option<JsonValue>,
+    ) -> Result<AuditLog> {
+        let log = sqlx::query_as::<_, AuditLog>(
+            r#"
+            INSERT INTO audit_logs
+            (user_id, action, resource_type, resource_id, old_values, new_values)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(action)
+        .bind(resource_type)
+        .bind(resource_id)
+        .bind(old_values)
+        .bind(new_values)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(log)
+    }
+
+    // Log one item of a bulk request, inside the request's transaction
+    #[allow(clippy::too_many_arguments)]
+    pub async fn log_batch_action(
+        conn: &mut PgConnection,
+        batch_id: Uuid,
+        user_id: Uuid,
+        action: &str,
+        resource_type: &str,
+        resource_id: Uuid,
+        old_values: Option<JsonValue>,
+        new_values: Option<JsonValue>,
+    ) -> Result<AuditLog> {
+        let log = sqlx::query_as::<_, AuditLog>(
+            r#"
+            INSERT INTO audit_logs
+            (user_id, action, resource_type, resource_id, old_values, new_values, batch_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(action)
+        .bind(resource_type)
+        .bind(resource_id)
+        .bind(old_values)
+        .bind(new_values)
+        .bind(batch_id)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(log)
+    }
+
+    // Log a change made automatically because of the change recorded as `caused_by`,
+    // inside the transaction that makes it
+    #[allow(clippy::too_many_arguments)]
+    pub async fn log_caused_action(
+        conn: &mut PgConnection,
+        caused_by: Uuid,
+        batch_id: Option<Uuid>,
+        user_id: Uuid,
+        action: &str,
+        resource_type: &str,
+        resource_id: Uuid,
+        old_values: Option<JsonValue>,
+        new_values: Option<JsonValue>,
+    ) -> Result<AuditLog> {
+        let log = sqlx::query_as::<_, AuditLog>(
+            r#"
+            INSERT INTO audit_logs
+            (user_id, action, resource_type, resource_id, old_values, new_values, batch_id, caused_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(action)
+        .bind(resource_type)
+        .bind(resource_id)
+        .bind(old_values)
+        .bind(new_values)
+        .bind(batch_id)
+        .bind(caused_by)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(log)
+    }
+
     // Get audit logs for a specific resource
     pub async fn get_resource_history(
         &self,
@@ -65,11 +168,13 @@ impl AuditRepository {
                 al.resource_id,
                 al.old_values,
                 al.new_values,
-                al.created_at
+                al.created_at,
+                al.batch_id,
+                al.caused_by
             FROM audit_logs al
             JOIN users u ON al.user_id = u.id
             WHERE al.resource_type = $1 AND al.resource_id = $2
-            ORDER BY al.created_at DESC
+            ORDER BY al.created_at DESC, al.id DESC
             "#,
         )
         .bind(resource_type)
@@ -80,6 +185,46 @@ impl AuditRepository {
         Ok(logs)
     }
 
+    // Get a single audit entry
+    pub async fn get_entry(&self, id: Uuid) -> Result<Option<AuditLog>> {
+        let log = sqlx::query_as::<_, AuditLog>("SELECT * FROM audit_logs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(log)
+    }
+
+    // Rebuild the state of a resource by replaying its audit entries in order.
+    // Entries at exactly `at` are included only when `inclusive` is set, which lets
+    // callers ask for the state right before a given change.
+    // Returns None if the resource did not exist (or was deleted) at that point.
+    pub async fn reconstruct_state(
+        &self,
+        resource_type: &str,
+        resource_id: Uuid,
+        at: DateTime<Utc>,
+        inclusive: bool,
+    ) -> Result<Option<JsonValue>> {
+        let entries = sqlx::query_as::<_, AuditLog>(
+            r#"
+            SELECT * FROM audit_logs
+            WHERE resource_type = $1
+              AND resource_id = $2
+              AND (created_at < $3 OR ($4 AND created_at = $3))
+            ORDER BY created_at ASC, id ASC
+            "#,
+        )
+        .bind(resource_type)
+        .bind(resource_id)
+        .bind(at)
+        .bind(inclusive)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(replay(&entries))
+    }
+
     // Get user's activity
     pub async fn get_user_activity(
         &self,
@@ -96,11 +241,13 @@ impl AuditRepository {
                 al.resource_id,
                 al.old_values,
                 al.new_values,
-                al.created_at
+                al.created_at,
+                al.batch_id,
+                al.caused_by
             FROM audit_logs al
             JOIN users u ON al.user_id = u.id
             WHERE al.user_id = $1
-            ORDER BY al.created_at DESC
+            ORDER BY al.created_at DESC, al.id DESC
             LIMIT $2
             "#,
         )
@@ -124,10 +271,12 @@ impl AuditRepository {
                 al.resource_id,
                 al.old_values,
                 al.new_values,
-                al.created_at
+                al.created_at,
+                al.batch_id,
+                al.caused_by
             FROM audit_logs al
             JOIN users u ON al.user_id = u.id
-            ORDER BY al.created_at DESC
+            ORDER BY al.created_at DESC, al.id DESC
             LIMIT $1
             "#,
         )
@@ -137,4 +286,160 @@ impl AuditRepository {
 
         Ok(logs)
     }
+
+    // Make sure partitions exist for the month of `from` and the `months_ahead` months after it
+    pub async fn ensure_partitions(&self, from: NaiveDate, months_ahead: u32) -> Result<()> {
+        for offset in 0..=months_ahead {
+            let month = from + Months::new(offset);
+            sqlx::query("SELECT create_audit_log_partition($1)")
+                .bind(month)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Give the rows that landed in the default partition monthly partitions of their
+    // own, so they are archived with the rest of their month
+    pub async fn drain_default_partition(&self) -> Result<()> {
+        let months = sqlx::query_scalar::<_, NaiveDate>(
+            "SELECT DISTINCT date_trunc('month', created_at)::DATE FROM audit_logs_default",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for month in months {
+            sqlx::query("SELECT create_audit_log_partition($1)")
+                .bind(month)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // List the monthly partitions still attached to audit_logs, oldest first
+    pub async fn list_partitions(&self) -> Result<Vec<AuditPartition>> {
+        let partitions = sqlx::query_as::<_, AuditPartition>(
+            r#"
+            SELECT
+                c.relname::TEXT as partition_name,
+                to_date(right(c.relname, 7), 'YYYY"m"MM') as range_start,
+                (to_date(right(c.relname, 7), 'YYYY"m"MM') + INTERVAL '1 month')::DATE as range_end,
+                GREATEST(c.reltuples, 0)::BIGINT as estimated_rows,
+                pg_total_relation_size(c.oid) as size_bytes
+            FROM pg_inherits i
+            JOIN pg_class c ON c.oid = i.inhrelid
+            WHERE i.inhparent = 'audit_logs'::regclass
+              AND c.relname != 'audit_logs_default'
+            ORDER BY range_start
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(partitions)
+    }
+
+    // List partitions that have already been archived and dropped
+    pub async fn list_archives(&self) -> Result<Vec<AuditArchive>> {
+        let archives = sqlx::query_as::<_, AuditArchive>(
+            "SELECT * FROM audit_log_archives ORDER BY range_start",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(archives)
+    }
+
+    // Stream every row of a partition into `writer` as newline-delimited JSON.
+    // Returns the number of rows written.
+    pub async fn export_partition<W: Write>(
+        &self,
+        partition: &AuditPartition,
+        writer: &mut W,
+    ) -> Result<i64> {
+        // Partition names come from pg_inherits, but quote them anyway
+        let query = format!(
+            "SELECT row_to_json(p)::TEXT FROM {} p ORDER BY created_at, id",
+            quote_identifier(&partition.partition_name)
+        );
+
+        let mut rows = sqlx::query_scalar::<_, String>(&query).fetch(&self.pool);
+        let mut count = 0;
+
+        while let Some(row) = rows.try_next().await? {
+            writeln!(writer, "{}", row).map_err(|e| {
+                AppError::InternalError(format!("Failed to write audit archive: {}", e))
+            })?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    // Record the archive file and drop the partition in one transaction
+    pub async fn drop_archived_partition(
+        &self,
+        partition: &AuditPartition,
+        row_count: i64,
+        file_path: &str,
+        file_size_bytes: i64,
+    ) -> Result<AuditArchive> {
+        let mut tx = self.pool.begin().await?;
+
+        let archive = sqlx::query_as::<_, AuditArchive>(
+            r#"
+            INSERT INTO audit_log_archives
+            (partition_name, range_start, range_end, row_count, file_path, file_size_bytes)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(&partition.partition_name)
+        .bind(partition.range_start)
+        .bind(partition.range_end)
+        .bind(row_count)
+        .bind(file_path)
+        .bind(file_size_bytes)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!("DROP TABLE {}", quote_identifier(&partition.partition_name)))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(archive)
+    }
+}
+
+// Entries written before full snapshots were logged only carry the changed fields,
+// so each entry is merged over the previous state rather than replacing it.
+fn replay(entries: &[AuditLog]) -> Option<JsonValue> {
+    let mut state: Option<JsonValue> = None;
+
+    for entry in entries {
+        if entry.action == "DELETE" {
+            state = None;
+            continue;
+        }
+
+        if let Some(JsonValue::Object(changes)) = &entry.new_value {
+            let current = state.get_or_insert_with(|| JsonValue::Object(Default::default()));
+            if let JsonValue::Object(fields) = current {
+                for (key, value) in changes {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    state
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
 }
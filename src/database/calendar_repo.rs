@@ -0,0 +1,145 @@
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    database::project_repo::visible_to,
+    error::Result,
+    models::{CalendarFeed, CalendarOwner, CalendarQuery, Task, TaskSeries},
+};
+
+pub struct CalendarRepository {
+    pool: PgPool,
+}
+
+impl CalendarRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Give the user a feed with this token, replacing any previous one
+    pub async fn set_token(&self, user_id: Uuid, token: &str) -> Result<CalendarFeed> {
+        let feed = sqlx::query_as::<_, CalendarFeed>(
+            r#"
+            INSERT INTO calendar_feeds (user_id, token_hash)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET token_hash = EXCLUDED.token_hash, created_at = NOW(), last_used_at = NULL
+            RETURNING created_at, last_used_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(token))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(feed)
+    }
+
+    pub async fn get(&self, user_id: Uuid) -> Result<Option<CalendarFeed>> {
+        let feed = sqlx::query_as::<_, CalendarFeed>(
+            "SELECT created_at, last_used_at FROM calendar_feeds WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(feed)
+    }
+
+    // Returns whether there was a feed
+    pub async fn delete(&self, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM calendar_feeds WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // The owner of the feed with this token, recording the fetch
+    pub async fn open(&self, token: &str) -> Result<Option<CalendarOwner>> {
+        let owner = sqlx::query_as::<_, CalendarOwner>(
+            r#"
+            UPDATE calendar_feeds f
+            SET last_used_at = NOW()
+            FROM users u
+            WHERE f.token_hash = $1 AND u.id = f.user_id
+            RETURNING u.id AS user_id, u.email, u.role
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(owner)
+    }
+
+    // Tasks assigned to the user that have a due date: the open ones, and those
+    // closed within the last `closed_days` days
+    pub async fn tasks(
+        &self,
+        owner: &CalendarOwner,
+        query: &CalendarQuery,
+        closed_days: i64,
+    ) -> Result<Vec<Task>> {
+        let tasks = sqlx::query_as::<_, Task>(&format!(
+            r#"
+            SELECT t.* FROM tasks t
+            WHERE t.assigned_to = $1
+              AND t.deleted_at IS NULL
+              AND t.due_date IS NOT NULL
+              AND (
+                  t.status NOT IN ('completed', 'cancelled')
+                  OR t.updated_at > NOW() - make_interval(days => $5::INT)
+              )
+              AND {}
+              AND ($3::UUID IS NULL OR t.project_id = $3)
+              AND ($4::TEXT IS NULL OR $4 = ANY(t.labels))
+            ORDER BY t.due_date
+            "#,
+            visible_to("t", "$1", "$2")
+        ))
+        .bind(owner.user_id)
+        .bind(owner.role == "admin")
+        .bind(query.project_id)
+        .bind(&query.label)
+        .bind(closed_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    // Active series whose future occurrences will be assigned to the user
+    pub async fn series(
+        &self,
+        owner: &CalendarOwner,
+        query: &CalendarQuery,
+    ) -> Result<Vec<TaskSeries>> {
+        let series = sqlx::query_as::<_, TaskSeries>(&format!(
+            r#"
+            SELECT s.* FROM task_series s
+            WHERE s.ended_at IS NULL
+              AND s.copy_assignee
+              AND s.assigned_to = $1
+              AND {}
+              AND ($3::UUID IS NULL OR s.project_id = $3)
+              AND ($4::TEXT IS NULL OR (s.copy_labels AND $4 = ANY(s.labels)))
+            "#,
+            visible_to("s", "$1", "$2")
+        ))
+        .bind(owner.user_id)
+        .bind(owner.role == "admin")
+        .bind(query.project_id)
+        .bind(&query.label)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(series)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
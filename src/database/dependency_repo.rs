@@ -1,12 +1,51 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::{
+    database::project_repo::visible_to,
     error::{AppError, Result},
-    models::{DependencyInfo, TaskDependency},
+    models::{
+        DependencyInfo, DependencyKind, DependencyNode, DependencyTree, GraphQuery, ReadyTask,
+        ScheduleLink, ScheduleTaskRow, Task, TaskDependency,
+    },
+    utils::graph_export::{GraphEdge, GraphNode},
 };
 
+// Arbitrary key for the advisory lock taken while a blocking link is checked and inserted
+const DEPENDENCY_GRAPH_LOCK_KEY: i64 = 0x6465_705f_6772_6170;
+
+// SQL condition: the link `td` to predecessor `t` does not allow its task to start yet,
+// or to complete when `completing` (an SQL boolean) is true. Lag counts from the
+// predecessor's start or completion.
+fn link_unmet(completing: &str) -> String {
+    format!(
+        r#"NOT CASE td.kind
+            WHEN 'finish_to_start' THEN
+                t.status = 'completed'
+                AND COALESCE(t.completed_at, t.updated_at) + td.lag_minutes * INTERVAL '1 minute' <= NOW()
+            WHEN 'start_to_start' THEN
+                t.started_at IS NOT NULL
+                AND t.started_at + td.lag_minutes * INTERVAL '1 minute' <= NOW()
+            WHEN 'finish_to_finish' THEN
+                NOT {completing} OR (
+                    t.status = 'completed'
+                    AND COALESCE(t.completed_at, t.updated_at) + td.lag_minutes * INTERVAL '1 minute' <= NOW()
+                )
+            ELSE TRUE
+        END"#
+    )
+}
+
+// Which way to walk the dependency graph from a task
+#[derive(Debug, Clone, Copy)]
+pub enum TreeDirection {
+    // Everything the task depends on
+    Upstream,
+    // Everything that depends on the task
+    Downstream,
+}
+
 pub struct DependencyRepository {
     pool: PgPool,
 }
@@ -16,44 +55,78 @@ impl DependencyRepository {
         Self { pool }
     }
 
-    // Add a dependency
-    pub async fn add_dependency(&self, task_id: Uuid, depends_on: Uuid) -> Result<TaskDependency> {
-        // Check if both tasks exist
-        let task_exists =
-            sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM tasks WHERE id = $1)")
-                .bind(task_id)
-                .fetch_one(&self.pool)
-                .await?;
+    // Add a dependency (adding an existing link again changes its kind and lag)
+    pub async fn add_dependency(
+        &self,
+        task_id: Uuid,
+        depends_on: Uuid,
+        kind: DependencyKind,
+        lag_minutes: i32,
+    ) -> Result<TaskDependency> {
+        if lag_minutes < 0 {
+            return Err(AppError::BadRequest("lag_minutes cannot be negative".to_string()));
+        }
+        if lag_minutes > 0 && !kind.is_blocking() {
+            return Err(AppError::BadRequest(format!(
+                "A {} link cannot have a lag",
+                kind.as_str()
+            )));
+        }
 
-        let dependency_exists =
-            sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM tasks WHERE id = $1)")
-                .bind(depends_on)
-                .fetch_one(&self.pool)
+        let mut tx = self.pool.begin().await?;
+
+        // Serialize blocking inserts: two concurrent checks could each pass and
+        // together close a cycle. The lock is released on commit or rollback.
+        if kind.is_blocking() {
+            sqlx::query("SELECT pg_advisory_xact_lock($1)")
+                .bind(DEPENDENCY_GRAPH_LOCK_KEY)
+                .execute(&mut *tx)
                 .await?;
+        }
+
+        // Check if both tasks exist
+        let task_exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(task_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let dependency_exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(depends_on)
+        .fetch_one(&mut *tx)
+        .await?;
 
         if !task_exists || !dependency_exists {
             return Err(AppError::NotFound);
         }
 
-        // Check for circular dependency BEFORE inserting
-        if self.would_create_cycle(task_id, depends_on).await? {
+        // Check for circular dependency BEFORE inserting. Informational links may form cycles.
+        if kind.is_blocking() && Self::would_create_cycle(&mut tx, task_id, depends_on).await? {
             return Err(AppError::ValidationError(validator::ValidationErrors::new()));
         }
 
         // Insert dependency
         let dependency = sqlx::query_as::<_, TaskDependency>(
             r#"
-            INSERT INTO task_dependencies (task_id, depends_on)
-            VALUES ($1, $2)
-            ON CONFLICT (task_id, depends_on) DO NOTHING
+            INSERT INTO task_dependencies (task_id, depends_on, kind, lag_minutes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (task_id, depends_on)
+            DO UPDATE SET kind = EXCLUDED.kind, lag_minutes = EXCLUDED.lag_minutes
             RETURNING *
             "#,
         )
         .bind(task_id)
         .bind(depends_on)
-        .fetch_one(&self.pool)
+        .bind(kind.as_str())
+        .bind(lag_minutes)
+        .fetch_one(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+
         Ok(dependency)
     }
 
@@ -75,114 +148,610 @@ impl DependencyRepository {
     }
 
     // Get all dependencies for a task
-    pub async fn get_dependencies(&self, task_id: Uuid) -> Result<Vec<DependencyInfo>> {
-        let dependencies = sqlx::query_as::<_, DependencyInfo>(
+    pub async fn get_dependencies(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<DependencyInfo>> {
+        let dependencies = sqlx::query_as::<_, DependencyInfo>(&format!(
             r#"
             SELECT
                 td.task_id,
                 t1.title as task_title,
                 td.depends_on,
                 t2.title as depends_on_title,
-                t2.status as dependency_status
+                t2.status as dependency_status,
+                td.kind,
+                td.lag_minutes
             FROM task_dependencies td
             JOIN tasks t1 ON td.task_id = t1.id
             JOIN tasks t2 ON td.depends_on = t2.id
-            WHERE td.task_id = $1
+            WHERE td.task_id = $1 AND t1.deleted_at IS NULL AND t2.deleted_at IS NULL
+              AND {}
             "#,
-        )
+            visible_to("t2", "$2", "$3")
+        ))
         .bind(task_id)
+        .bind(user_id)
+        .bind(is_admin)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(dependencies)
     }
 
-    // Get all tasks that depend on this task (reverse dependencies)
-    pub async fn get_blocked_tasks(&self, task_id: Uuid) -> Result<Vec<Uuid>> {
-        let blocked = sqlx::query_scalar::<_, Uuid>(
-            "SELECT task_id FROM task_dependencies WHERE depends_on = $1",
-        )
+    // Get all tasks that depend on this task (reverse dependencies, blocking kinds only)
+    pub async fn get_blocked_tasks(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<Uuid>> {
+        let blocked = sqlx::query_scalar::<_, Uuid>(&format!(
+            r#"
+            SELECT td.task_id
+            FROM task_dependencies td
+            JOIN tasks t ON td.task_id = t.id
+            WHERE td.depends_on = $1 AND t.deleted_at IS NULL AND td.kind = ANY($2) AND {}
+            "#,
+            visible_to("t", "$3", "$4")
+        ))
         .bind(task_id)
+        .bind(&DependencyKind::BLOCKING[..])
+        .bind(user_id)
+        .bind(is_admin)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(blocked)
     }
 
-    // Check if a task can be completed (all dependencies must be completed)
-    pub async fn can_complete_task(&self, task_id: Uuid) -> Result<bool> {
-        let incomplete_deps = sqlx::query_scalar::<_, i64>(
+    // Every task the user can see that is reachable from `task_id` over blocking links,
+    // up to `max_depth` links away, as a tree. One recursive query returns each link
+    // between reached tasks. The walk does not go through tasks the user cannot see.
+    pub async fn get_dependency_tree(
+        &self,
+        task_id: Uuid,
+        direction: TreeDirection,
+        max_depth: i32,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<DependencyTree> {
+        // (column walked from, column walked to)
+        let (from, to) = match direction {
+            TreeDirection::Upstream => ("task_id", "depends_on"),
+            TreeDirection::Downstream => ("depends_on", "task_id"),
+        };
+
+        // `reach` keeps distinct (task, depth) pairs, so shared tasks are not walked once
+        // per path and the walk is bounded even if trashed links ever form a cycle
+        let query = format!(
+            r#"
+            WITH RECURSIVE reach(id, depth) AS (
+                SELECT $1::uuid, 0
+                UNION
+                SELECT td.{to}, r.depth + 1
+                FROM task_dependencies td
+                JOIN reach r ON td.{from} = r.id
+                JOIN tasks t ON t.id = td.{to}
+                WHERE td.kind = ANY($3) AND t.deleted_at IS NULL AND r.depth < $2
+                  AND {visible_t}
+            ),
+            nodes AS (
+                SELECT id, MIN(depth) AS depth FROM reach GROUP BY id
+            ),
+            links AS (
+                SELECT NULL::uuid AS parent_id, n.id, NULL::varchar AS kind, n.depth
+                FROM nodes n
+                WHERE n.depth = 0
+                UNION ALL
+                SELECT td.{from}, td.{to}, td.kind, n.depth
+                FROM task_dependencies td
+                JOIN nodes n ON n.id = td.{to}
+                JOIN nodes p ON p.id = td.{from} AND p.depth < $2
+                WHERE td.kind = ANY($3)
+            )
+            SELECT l.parent_id, n.id AS task_id, n.title, n.status, l.kind, l.depth,
+                   ARRAY(
+                       SELECT td.depends_on
+                       FROM task_dependencies td
+                       JOIN tasks t ON t.id = td.depends_on
+                       WHERE td.task_id = n.id AND td.kind = ANY($3)
+                         AND t.deleted_at IS NULL AND {unmet} AND {visible_t}
+                   ) AS blocked_by
+            FROM links l
+            JOIN tasks n ON n.id = l.id
+            WHERE n.deleted_at IS NULL AND {visible_n}
+            ORDER BY l.depth, n.title
+            "#,
+            unmet = link_unmet("TRUE"),
+            visible_t = visible_to("t", "$4", "$5"),
+            visible_n = visible_to("n", "$4", "$5")
+        );
+
+        let rows = sqlx::query_as::<_, DependencyNode>(&query)
+            .bind(task_id)
+            .bind(max_depth)
+            .bind(&DependencyKind::BLOCKING[..])
+            .bind(user_id)
+            .bind(is_admin)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut root = None;
+        let mut children: HashMap<Uuid, Vec<DependencyNode>> = HashMap::new();
+        let mut incoming: HashMap<Uuid, usize> = HashMap::new();
+        for row in rows {
+            match row.parent_id {
+                None => root = Some(row),
+                Some(parent_id) => {
+                    *incoming.entry(row.task_id).or_default() += 1;
+                    children.entry(parent_id).or_default().push(row);
+                }
+            }
+        }
+        let root = root.ok_or(AppError::NotFound)?;
+
+        let mut expanded = HashSet::from([root.task_id]);
+        Ok(build_tree(root, &mut children, &incoming, &mut expanded))
+    }
+
+    // The task plus everything it transitively waits for, and the blocking links between
+    // them. The walk stops at tasks the user cannot see, so they are not scheduled.
+    pub async fn get_schedule_graph(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<(Vec<ScheduleTaskRow>, Vec<ScheduleLink>)> {
+        let tasks = sqlx::query_as::<_, ScheduleTaskRow>(&format!(
+            r#"
+            WITH RECURSIVE upstream(id) AS (
+                SELECT $1::uuid
+                UNION
+                SELECT td.depends_on
+                FROM task_dependencies td
+                JOIN upstream u ON td.task_id = u.id
+                JOIN tasks t ON t.id = td.depends_on
+                WHERE td.kind = ANY($2) AND t.deleted_at IS NULL AND {visible}
+            )
+            SELECT t.id, t.title, t.status, t.started_at, t.completed_at, t.due_date,
+                   t.estimated_minutes
+            FROM upstream u
+            JOIN tasks t ON t.id = u.id
+            WHERE t.deleted_at IS NULL AND {visible}
+            "#,
+            visible = visible_to("t", "$3", "$4")
+        ))
+        .bind(task_id)
+        .bind(&DependencyKind::BLOCKING[..])
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if !tasks.iter().any(|task| task.id == task_id) {
+            return Err(AppError::NotFound);
+        }
+
+        let ids: Vec<Uuid> = tasks.iter().map(|task| task.id).collect();
+        let links = sqlx::query_as::<_, ScheduleLink>(
+            r#"
+            SELECT task_id, depends_on, kind, lag_minutes
+            FROM task_dependencies
+            WHERE task_id = ANY($1) AND depends_on = ANY($1) AND kind = ANY($2)
+            "#,
+        )
+        .bind(&ids)
+        .bind(&DependencyKind::BLOCKING[..])
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((tasks, links))
+    }
+
+    // Tasks matching the query that the user can see, and every link (of any kind)
+    // between them. With a root, only tasks up to `max_depth` links upstream or
+    // downstream of it are considered. Fails instead of truncating when more than
+    // `max_nodes` tasks match.
+    pub async fn get_graph(
+        &self,
+        params: &GraphQuery,
+        user_id: Uuid,
+        is_admin: bool,
+        max_depth: i32,
+        max_nodes: i64,
+    ) -> Result<(Vec<GraphNode>, Vec<GraphEdge>)> {
+        let mut query = QueryBuilder::<Postgres>::new("WITH RECURSIVE actor AS (SELECT ");
+        query.push_bind(user_id).push("::uuid AS user_id, ");
+        query.push_bind(is_admin).push("::bool AS is_admin)");
+        if let Some(root) = params.root {
+            query
+                .push(", upstream(id, depth) AS (SELECT ")
+                .push_bind(root)
+                .push(
+                    "::uuid, 0 UNION SELECT td.depends_on, u.depth + 1 \
+                     FROM task_dependencies td JOIN upstream u ON td.task_id = u.id \
+                     JOIN tasks t ON t.id = td.depends_on \
+                     WHERE t.deleted_at IS NULL AND u.depth < ",
+                )
+                .push_bind(max_depth)
+                .push("), downstream(id, depth) AS (SELECT ")
+                .push_bind(root)
+                .push(
+                    "::uuid, 0 UNION SELECT td.task_id, d.depth + 1 \
+                     FROM task_dependencies td JOIN downstream d ON td.depends_on = d.id \
+                     JOIN tasks t ON t.id = td.task_id \
+                     WHERE t.deleted_at IS NULL AND d.depth < ",
+                )
+                .push_bind(max_depth)
+                .push(") ");
+        }
+        query.push(
+            " SELECT id, title, status, priority FROM tasks, actor WHERE deleted_at IS NULL AND ",
+        );
+        query.push(visible_to("tasks", "actor.user_id", "actor.is_admin"));
+        if params.root.is_some() {
+            query.push(" AND id IN (SELECT id FROM upstream UNION SELECT id FROM downstream)");
+        }
+        if let Some(project_id) = params.project_id {
+            query.push(" AND project_id = ").push_bind(project_id);
+        }
+        if let Some(status) = &params.status {
+            query.push(" AND status = ").push_bind(status);
+        }
+        if let Some(priority) = &params.priority {
+            query.push(" AND priority = ").push_bind(priority);
+        }
+        if let Some(assigned_to) = params.assigned_to {
+            query.push(" AND assigned_to = ").push_bind(assigned_to);
+        }
+        if let Some(label) = &params.label {
+            query.push(" AND ").push_bind(label).push(" = ANY(labels)");
+        }
+        query.push(" ORDER BY created_at LIMIT ").push_bind(max_nodes + 1);
+
+        let nodes = query.build_query_as::<GraphNode>().fetch_all(&self.pool).await?;
+
+        if let Some(root) = params.root
+            && !nodes.iter().any(|node| node.id == root)
+        {
+            return Err(AppError::NotFound);
+        }
+        if nodes.len() as i64 > max_nodes {
+            return Err(AppError::BadRequest(format!(
+                "The graph has more than {} tasks, narrow it down with filters or a root task",
+                max_nodes
+            )));
+        }
+
+        let ids: Vec<Uuid> = nodes.iter().map(|node| node.id).collect();
+        let edges = sqlx::query_as::<_, GraphEdge>(
+            r#"
+            SELECT task_id, depends_on, kind, lag_minutes
+            FROM task_dependencies
+            WHERE task_id = ANY($1) AND depends_on = ANY($1)
+            ORDER BY created_at
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((nodes, edges))
+    }
+
+    // Check if the task's dependency links allow it to move into `status`: starting
+    // (in_progress) and completing are gated, any other status is not. Completing also
+    // starts a pending task, so the start conditions apply to it as well. Runs on the
+    // given connection, so it sees the caller's uncommitted changes.
+    pub async fn allows_status_in(
+        conn: &mut PgConnection,
+        task_id: Uuid,
+        status: &str,
+    ) -> Result<bool> {
+        let completing = match status {
+            "in_progress" => false,
+            "completed" => true,
+            _ => return Ok(true),
+        };
+        Ok(Self::unmet_dependencies_in(conn, task_id, completing).await? == 0)
+    }
+
+    // Number of links that do not allow the task to start (or complete) yet, lag included
+    async fn unmet_dependencies_in(
+        conn: &mut PgConnection,
+        task_id: Uuid,
+        completing: bool,
+    ) -> Result<i64> {
+        let query = format!(
             r#"
             SELECT COUNT(*)
             FROM task_dependencies td
             JOIN tasks t ON td.depends_on = t.id
-            WHERE td.task_id = $1 AND t.status != 'completed'
+            WHERE td.task_id = $1 AND t.deleted_at IS NULL AND {}
             "#,
-        )
-        .bind(task_id)
-        .fetch_one(&self.pool)
-        .await?;
+            link_unmet("$2")
+        );
+
+        let unmet = sqlx::query_scalar::<_, i64>(&query)
+            .bind(task_id)
+            .bind(completing)
+            .fetch_one(conn)
+            .await?;
 
-        Ok(incomplete_deps == 0)
+        Ok(unmet)
     }
 
-    // CORE ALGORITHM: Detect circular dependencies using DFS
-    async fn would_create_cycle(&self, from: Uuid, to: Uuid) -> Result<bool> {
-        // If adding edge from→to creates a cycle, return true
+    // Open tasks assigned to the user, or unassigned in one of the user's projects, that
+    // have nothing left to wait for, ranked best first by priority weighted by how much
+    // work each one holds up downstream. Each comes with the ready tasks upstream of it,
+    // which `utils::ready::order` puts first.
+    pub async fn get_ready_tasks(
+        &self,
+        user_id: Uuid,
+        project_id: Option<Uuid>,
+    ) -> Result<Vec<ReadyTask>> {
+        let query = format!(
+            r#"
+            WITH RECURSIVE ready AS (
+                SELECT c.*
+                FROM tasks c
+                WHERE c.deleted_at IS NULL
+                  AND c.status IN ('pending', 'in_progress')
+                  AND ($2::uuid IS NULL OR c.project_id = $2)
+                  AND (c.assigned_to = $1 OR (c.assigned_to IS NULL AND c.project_id IN (
+                      SELECT project_id FROM project_members WHERE user_id = $1
+                  )))
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM task_dependencies td
+                      JOIN tasks t ON td.depends_on = t.id
+                      WHERE td.task_id = c.id AND t.deleted_at IS NULL AND {}
+                  )
+            ),
+            downstream(root, id) AS (
+                SELECT r.id, td.task_id
+                FROM ready r
+                JOIN task_dependencies td ON td.depends_on = r.id
+                WHERE td.kind = ANY($3)
+                UNION
+                SELECT d.root, td.task_id
+                FROM downstream d
+                JOIN task_dependencies td ON td.depends_on = d.id
+                WHERE td.kind = ANY($3)
+            ),
+            waits AS (
+                SELECT d.id, array_agg(d.root) AS waits_for
+                FROM downstream d
+                JOIN ready r ON r.id = d.id
+                GROUP BY d.id
+            ),
+            weighted AS (
+                SELECT r.*,
+                       COALESCE(w.waits_for, ARRAY[]::uuid[]) AS waits_for,
+                       (SELECT COUNT(*)
+                        FROM downstream d
+                        JOIN tasks t ON t.id = d.id
+                        WHERE d.root = r.id AND t.deleted_at IS NULL AND t.status != 'completed'
+                       ) AS blocks_count,
+                       CASE r.priority
+                           WHEN 'urgent' THEN 4 WHEN 'high' THEN 3 WHEN 'medium' THEN 2 ELSE 1
+                       END AS priority_weight
+                FROM ready r
+                LEFT JOIN waits w ON w.id = r.id
+            )
+            SELECT *, priority_weight * (1 + blocks_count) AS score
+            FROM weighted
+            ORDER BY score DESC, due_date ASC NULLS LAST, created_at, id
+            "#,
+            link_unmet("TRUE")
+        );
 
-        // Get all existing dependencies
-        let all_deps =
-            sqlx::query_as::<_, (Uuid, Uuid)>("SELECT task_id, depends_on FROM task_dependencies")
-                .fetch_all(&self.pool)
-                .await?;
+        let tasks = sqlx::query_as::<_, ReadyTask>(&query)
+            .bind(user_id)
+            .bind(project_id)
+            .bind(&DependencyKind::BLOCKING[..])
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
+
+    // Pending tasks that this one currently holds up and that wait for nothing else,
+    // i.e. the tasks that become ready once this one is completed (lag aside). Tasks
+    // already ready or in progress are not unblocked by it. Tasks of projects the user
+    // is not in are left out unless `is_admin`.
+    pub async fn get_unblocked_by(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<Task>> {
+        let query = format!(
+            r#"
+            SELECT b.*
+            FROM tasks b
+            WHERE b.deleted_at IS NULL AND b.status = 'pending'
+              AND {visible}
+              AND EXISTS (
+                  SELECT 1
+                  FROM task_dependencies td
+                  JOIN tasks t ON td.depends_on = t.id
+                  WHERE td.task_id = b.id AND td.depends_on = $1 AND td.kind = ANY($2)
+                    AND {unmet}
+              )
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM task_dependencies td
+                  JOIN tasks t ON td.depends_on = t.id
+                  WHERE td.task_id = b.id AND td.depends_on != $1
+                    AND t.deleted_at IS NULL AND {unmet}
+              )
+            ORDER BY b.created_at
+            "#,
+            unmet = link_unmet("TRUE"),
+            visible = visible_to("b", "$3", "$4")
+        );
+
+        let tasks = sqlx::query_as::<_, Task>(&query)
+            .bind(task_id)
+            .bind(&DependencyKind::BLOCKING[..])
+            .bind(user_id)
+            .bind(is_admin)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tasks)
+    }
 
-        // Build adjacency list (graph representation)
-        let mut graph: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    // CORE ALGORITHM: adding the edge from→to closes a cycle exactly when `to`
+    // already reaches `from` over blocking links. The walk runs in the database, and
+    // UNION (not UNION ALL) drops revisited tasks so it terminates on any graph.
+    // Edges of trashed tasks are included on purpose: they come back on restore, so
+    // they must not be closed into a cycle meanwhile.
+    async fn would_create_cycle(conn: &mut PgConnection, from: Uuid, to: Uuid) -> Result<bool> {
+        let reachable = sqlx::query_scalar::<_, bool>(
+            r#"
+            WITH RECURSIVE reachable(id) AS (
+                SELECT $1::uuid
+                UNION
+                SELECT td.depends_on
+                FROM task_dependencies td
+                JOIN reachable r ON td.task_id = r.id
+                WHERE td.kind = ANY($3)
+            )
+            SELECT EXISTS(SELECT 1 FROM reachable WHERE id = $2)
+            "#,
+        )
+        .bind(to)
+        .bind(from)
+        .bind(&DependencyKind::BLOCKING[..])
+        .fetch_one(conn)
+        .await?;
 
-        for (task_id, depends_on) in all_deps {
-            graph.entry(task_id).or_insert_with(Vec::new).push(depends_on);
+        Ok(reachable)
+    }
+}
+
+// Turn the walked links into a tree. Rows come ordered by depth, so the first
+// occurrence of a shared task is on one of its shortest paths; only that one is expanded.
+fn build_tree(
+    node: DependencyNode,
+    children: &mut HashMap<Uuid, Vec<DependencyNode>>,
+    incoming: &HashMap<Uuid, usize>,
+    expanded: &mut HashSet<Uuid>,
+) -> DependencyTree {
+    let mut dependencies = Vec::new();
+    for child in children.remove(&node.task_id).unwrap_or_default() {
+        if child.depth == node.depth + 1 && expanded.insert(child.task_id) {
+            dependencies.push(build_tree(child, children, incoming, expanded));
+        } else {
+            dependencies.push(DependencyTree {
+                task_id: child.task_id,
+                title: child.title,
+                status: child.status,
+                kind: child.kind,
+                depth: child.depth,
+                dependencies: Vec::new(),
+                blocked_by: child.blocked_by,
+                shared: true,
+            });
         }
+    }
 
-        // Add the proposed new edge
-        graph.entry(from).or_insert_with(Vec::new).push(to);
+    DependencyTree {
+        shared: incoming.get(&node.task_id).is_some_and(|&n| n > 1),
+        task_id: node.task_id,
+        title: node.title,
+        status: node.status,
+        kind: node.kind,
+        depth: node.depth,
+        dependencies,
+        blocked_by: node.blocked_by,
+    }
+}
 
-        // Run DFS to detect cycle
-        let mut visited = HashSet::new();
-        let mut rec_stack = HashSet::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        self.has_cycle_dfs(&graph, from, &mut visited, &mut rec_stack)
+    fn graph_query(project_id: Option<Uuid>, root: Option<Uuid>) -> GraphQuery {
+        GraphQuery {
+            format: Default::default(),
+            project_id,
+            root,
+            max_depth: None,
+            status: None,
+            priority: None,
+            assigned_to: None,
+            label: None,
+        }
     }
 
-    // DFS with a recursion stack to detect cycles
-    fn has_cycle_dfs(
-        &self,
-        graph: &HashMap<Uuid, Vec<Uuid>>,
-        node: Uuid,
-        visited: &mut HashSet<Uuid>,
-        rec_stack: &mut HashSet<Uuid>,
-    ) -> Result<bool> {
-        // Mark the current node as being visited
-        visited.insert(node);
-        rec_stack.insert(node);
-
-        // Visit all neighbors
-        if let Some(neighbors) = graph.get(&node) {
-            for &neighbor in neighbors {
-                // If a neighbor not visited, recurse
-                if !visited.contains(&neighbor) {
-                    if self.has_cycle_dfs(graph, neighbor, visited, rec_stack)? {
-                        return Ok(true);
-                    }
-                }
-                // If neighbor is in recursion stack, we found a cycle!
-                else if rec_stack.contains(&neighbor) {
-                    return Ok(true);
-                }
-            }
+    async fn user(pool: &PgPool, email: &str) -> Uuid {
+        sqlx::query_scalar("INSERT INTO users (email, password_hash) VALUES ($1, '') RETURNING id")
+            .bind(email)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn graph_leaves_out_tasks_of_other_projects(pool: PgPool) {
+        let alice = user(&pool, "alice@example.com").await;
+        let bob = user(&pool, "bob@example.com").await;
+        let project: Uuid = sqlx::query_scalar(
+            "INSERT INTO projects (name, owner_id) VALUES ('secret', $1) RETURNING id",
+        )
+        .bind(alice)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO project_members (project_id, user_id) VALUES ($1, $2)")
+            .bind(project)
+            .bind(alice)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        for title in ["first", "second"] {
+            let id: Uuid = sqlx::query_scalar(
+                "INSERT INTO tasks (title, created_by, project_id) VALUES ($1, $2, $3) RETURNING id",
+            )
+            .bind(title)
+            .bind(alice)
+            .bind(project)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
         }
+        sqlx::query("INSERT INTO task_dependencies (task_id, depends_on) VALUES ($1, $2)")
+            .bind(ids[1])
+            .bind(ids[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let repo = DependencyRepository::new(pool);
+
+        let (nodes, edges) =
+            repo.get_graph(&graph_query(None, None), bob, false, 10, 100).await.unwrap();
+        assert!(nodes.is_empty());
+        assert!(edges.is_empty());
+
+        let rooted = repo.get_graph(&graph_query(None, Some(ids[1])), bob, false, 10, 100).await;
+        assert!(matches!(rooted, Err(AppError::NotFound)));
+
+        let (nodes, edges) =
+            repo.get_graph(&graph_query(None, Some(ids[1])), alice, false, 10, 100).await.unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(edges.len(), 1);
 
-        // Remove from the recursion stack before returning
-        rec_stack.remove(&node);
-        Ok(false)
+        let (nodes, _) =
+            repo.get_graph(&graph_query(None, None), bob, true, 10, 100).await.unwrap();
+        assert_eq!(nodes.len(), 2);
     }
 }
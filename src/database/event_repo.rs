@@ -0,0 +1,55 @@
+use sqlx::PgPool;
+
+use crate::{error::Result, models::TaskEvent};
+
+pub struct EventRepository {
+    pool: PgPool,
+}
+
+impl EventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Events recorded after `after_id`, oldest first
+    pub async fn list_after(&self, after_id: i64, limit: i64) -> Result<Vec<TaskEvent>> {
+        let events = sqlx::query_as::<_, TaskEvent>(
+            "SELECT * FROM task_events WHERE id > $1 ORDER BY id LIMIT $2",
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<TaskEvent>> {
+        let event = sqlx::query_as::<_, TaskEvent>("SELECT * FROM task_events WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(event)
+    }
+
+    pub async fn latest_id(&self) -> Result<i64> {
+        let id = sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(id), 0) FROM task_events")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(id)
+    }
+
+    // Clients can only resume from events that are still stored
+    pub async fn purge_older_than(&self, hours: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM task_events WHERE created_at < NOW() - make_interval(hours => $1::INT)",
+        )
+        .bind(hours)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,238 @@
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    database::project_repo::visible_to,
+    error::{AppError, Result},
+    models::{SubtaskNode, SubtaskProgress, SubtaskRow, Task},
+};
+
+// Arbitrary key for the advisory lock taken while a parent link is checked and written
+const HIERARCHY_LOCK_KEY: i64 = 0x7061_7265_6e74_7321;
+
+pub struct HierarchyRepository {
+    pool: PgPool,
+}
+
+impl HierarchyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Direct, live children of a task that the user can see
+    pub async fn get_children(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<Task>> {
+        let children = sqlx::query_as::<_, Task>(&format!(
+            r#"
+            SELECT t.* FROM tasks t
+            WHERE t.parent_id = $1 AND t.deleted_at IS NULL AND {}
+            ORDER BY t.created_at
+            "#,
+            visible_to("t", "$2", "$3")
+        ))
+        .bind(task_id)
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(children)
+    }
+
+    // All live descendants of a task that the user can see, with their depth below it
+    // (children are depth 1). Subtrees under a trashed or hidden task are left out.
+    pub async fn get_descendants(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<SubtaskRow>> {
+        let visible = visible_to("t", "$2", "$3");
+        let rows = sqlx::query_as::<_, SubtaskRow>(&format!(
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT t.id, t.parent_id, t.title, t.status, t.priority, t.assigned_to,
+                       t.due_date, 1 AS depth
+                FROM tasks t
+                WHERE t.parent_id = $1 AND t.deleted_at IS NULL AND {visible}
+                UNION ALL
+                SELECT t.id, t.parent_id, t.title, t.status, t.priority, t.assigned_to,
+                       t.due_date, s.depth + 1
+                FROM tasks t
+                JOIN subtree s ON t.parent_id = s.id
+                WHERE t.deleted_at IS NULL AND {visible} AND s.depth < 100
+            )
+            SELECT * FROM subtree ORDER BY depth, title
+            "#
+        ))
+        .bind(task_id)
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // The task's descendants as a nested tree, with progress rolled up at every level
+    pub async fn get_subtree(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<(SubtaskProgress, Vec<SubtaskNode>)> {
+        let rows = self.get_descendants(task_id, user_id, is_admin).await?;
+
+        let mut by_parent: HashMap<Uuid, Vec<SubtaskRow>> = HashMap::new();
+        for row in rows {
+            if let Some(parent_id) = row.parent_id {
+                by_parent.entry(parent_id).or_default().push(row);
+            }
+        }
+
+        Ok(build_nodes(task_id, &mut by_parent))
+    }
+
+    // Can `task_id` (None for a new task) be placed under `parent_id`?
+    // The parent must be a live, open task outside the task's own subtree, and the
+    // resulting tree must not be deeper than `max_depth` levels.
+    // Runs in the caller's transaction, which must also write the parent link: the lock
+    // keeps concurrent moves from passing these checks together and then forming a
+    // cycle or too deep a tree. It is released on commit or rollback.
+    pub async fn validate_parent_in(
+        conn: &mut PgConnection,
+        task_id: Option<Uuid>,
+        parent_id: Uuid,
+        max_depth: i32,
+    ) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(HIERARCHY_LOCK_KEY)
+            .execute(&mut *conn)
+            .await?;
+
+        // Levels from the root down to and including the parent (a root task is level 1)
+        let (parent_level, parent_status) = sqlx::query_as::<_, (i32, String)>(
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id, status AS parent_status, 1 AS level
+                FROM tasks
+                WHERE id = $1 AND deleted_at IS NULL
+                UNION ALL
+                SELECT t.id, t.parent_id, a.parent_status, a.level + 1
+                FROM tasks t
+                JOIN ancestors a ON t.id = a.parent_id
+                WHERE a.level <= $2
+            )
+            SELECT MAX(level), MAX(parent_status) FROM ancestors HAVING COUNT(*) > 0
+            "#,
+        )
+        .bind(parent_id)
+        .bind(max_depth)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AppError::BadRequest("Parent task not found".to_string()))?;
+
+        // Otherwise a completed task would end up with open subtasks
+        if parent_status == "completed" {
+            return Err(AppError::BadRequest(
+                "Cannot add subtasks to a completed task, reopen it first".to_string(),
+            ));
+        }
+
+        // Height of the subtree being moved (1 for a task without children)
+        let height = match task_id {
+            None => 1,
+            Some(task_id) => {
+                let (height, contains_parent) = sqlx::query_as::<_, (i32, bool)>(
+                    r#"
+                    WITH RECURSIVE subtree AS (
+                        SELECT id, 1 AS level FROM tasks WHERE id = $1
+                        UNION ALL
+                        SELECT t.id, s.level + 1
+                        FROM tasks t
+                        JOIN subtree s ON t.parent_id = s.id
+                        WHERE s.level <= $3
+                    )
+                    SELECT MAX(level), bool_or(id = $2) FROM subtree
+                    "#,
+                )
+                .bind(task_id)
+                .bind(parent_id)
+                .bind(max_depth)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                if contains_parent {
+                    return Err(AppError::BadRequest(
+                        "A task cannot be moved under one of its own subtasks".to_string(),
+                    ));
+                }
+                height
+            }
+        };
+
+        if parent_level + height > max_depth {
+            return Err(AppError::BadRequest(format!(
+                "Task hierarchy cannot be deeper than {} levels",
+                max_depth
+            )));
+        }
+
+        Ok(())
+    }
+
+    // A parent cannot be completed while any live child is still open
+    pub async fn has_open_children_in(conn: &mut PgConnection, task_id: Uuid) -> Result<bool> {
+        let open = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM tasks
+                WHERE parent_id = $1 AND status NOT IN ('completed', 'cancelled')
+                  AND deleted_at IS NULL
+            )
+            "#,
+        )
+        .bind(task_id)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(open)
+    }
+}
+
+// Build the nodes under `parent_id` and return them with the parent's rolled-up progress
+fn build_nodes(
+    parent_id: Uuid,
+    by_parent: &mut HashMap<Uuid, Vec<SubtaskRow>>,
+) -> (SubtaskProgress, Vec<SubtaskNode>) {
+    let rows = by_parent.remove(&parent_id).unwrap_or_default();
+    let mut total = 0;
+    let mut completed = 0;
+    let mut nodes = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let (progress, children) = build_nodes(row.id, by_parent);
+
+        total += 1 + progress.total_descendants;
+        completed += progress.completed_descendants + i64::from(row.status == "completed");
+
+        nodes.push(SubtaskNode {
+            id: row.id,
+            title: row.title,
+            status: row.status,
+            priority: row.priority,
+            assigned_to: row.assigned_to,
+            due_date: row.due_date,
+            depth: row.depth,
+            progress,
+            children,
+        });
+    }
+
+    (SubtaskProgress::new(total, completed), nodes)
+}
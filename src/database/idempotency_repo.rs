@@ -0,0 +1,163 @@
+use sqlx::{PgPool, types::JsonValue};
+use uuid::Uuid;
+
+use crate::{error::Result, models::IdempotencyRecord};
+
+pub struct IdempotencyRepository {
+    pool: PgPool,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Claim a key for a new request, holding it for `lease_secs` while the request
+    // runs. Returns false if the key is already taken (by a finished request, or an
+    // in-flight one whose lease is not over); expired claims are replaced.
+    pub async fn reserve(
+        &self,
+        scope: &str,
+        key: &str,
+        request_hash: &str,
+        lease_id: Uuid,
+        lease_secs: i64,
+        ttl_hours: i64,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE scope = $1 AND idempotency_key = $2
+              AND (expires_at < NOW() OR (response_status IS NULL AND locked_until < NOW()))
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .execute(&mut *tx)
+        .await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys
+                (scope, idempotency_key, request_hash, lease_id, locked_until, expires_at)
+            VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5::INT),
+                    NOW() + make_interval(hours => $6::INT))
+            ON CONFLICT (scope, idempotency_key) DO NOTHING
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(request_hash)
+        .bind(lease_id)
+        .bind(lease_secs)
+        .bind(ttl_hours)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(inserted.rows_affected() == 1)
+    }
+
+    // Extend the lease of a request that is still running. Returns false if the lease
+    // is no longer held.
+    pub async fn renew(
+        &self,
+        scope: &str,
+        key: &str,
+        lease_id: Uuid,
+        lease_secs: i64,
+    ) -> Result<bool> {
+        let renewed = sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET locked_until = NOW() + make_interval(secs => $4::INT)
+            WHERE scope = $1 AND idempotency_key = $2 AND lease_id = $3
+              AND response_status IS NULL
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(lease_id)
+        .bind(lease_secs)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(renewed.rows_affected() == 1)
+    }
+
+    pub async fn get(&self, scope: &str, key: &str) -> Result<Option<IdempotencyRecord>> {
+        let record = sqlx::query_as::<_, IdempotencyRecord>(
+            r#"
+            SELECT request_hash, response_status, response_headers, response_body
+            FROM idempotency_keys
+            WHERE scope = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    // Store the response of the original request so retries can replay it. Does
+    // nothing if the lease was lost to a retry in the meantime.
+    pub async fn complete(
+        &self,
+        scope: &str,
+        key: &str,
+        lease_id: Uuid,
+        status: u16,
+        headers: JsonValue,
+        body: &[u8],
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET response_status = $4, response_headers = $5, response_body = $6,
+                lease_id = NULL, locked_until = NULL
+            WHERE scope = $1 AND idempotency_key = $2 AND lease_id = $3
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(lease_id)
+        .bind(status as i16)
+        .bind(headers)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Give a key back, e.g. when the original request failed with a server error
+    pub async fn release(&self, scope: &str, key: &str, lease_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE scope = $1 AND idempotency_key = $2 AND lease_id = $3
+              AND response_status IS NULL
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(lease_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,354 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, types::JsonValue};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::{JobQuery, JobRecord, JobSchedule, JobStats, NewJob},
+};
+
+// Extra time on top of a job's timeout before another instance may take it over
+const LEASE_GRACE_SECS: i32 = 30;
+
+pub struct JobRepository {
+    pool: PgPool,
+}
+
+impl JobRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Queue a job inside the caller's transaction, so it only exists if the work
+    // that asked for it is committed. Returns None for a scheduled job whose
+    // previous run is still pending.
+    pub async fn enqueue_in(conn: &mut PgConnection, job: &NewJob) -> Result<Option<JobRecord>> {
+        let record = sqlx::query_as::<_, JobRecord>(
+            r#"
+            INSERT INTO jobs (kind, payload, max_attempts, timeout_secs, run_at, schedule_name)
+            VALUES ($1, $2, $3, $4, COALESCE($5, NOW()), $6)
+            ON CONFLICT (schedule_name)
+                WHERE schedule_name IS NOT NULL AND status IN ('queued', 'running')
+                DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(job.kind)
+        .bind(&job.payload)
+        .bind(job.max_attempts)
+        .bind(job.timeout_secs)
+        .bind(job.run_at)
+        .bind(job.schedule_name)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    // Take the next due job of one of `kinds` for `worker`
+    pub async fn claim(&self, kinds: &[&str], worker: &str) -> Result<Option<JobRecord>> {
+        let job = sqlx::query_as::<_, JobRecord>(
+            r#"
+            UPDATE jobs
+            SET status = 'running',
+                attempts = attempts + 1,
+                locked_by = $2,
+                locked_until = NOW() + make_interval(secs => timeout_secs + $3),
+                started_at = NOW()
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE status = 'queued' AND run_at <= NOW() AND kind = ANY($1)
+                ORDER BY run_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(kinds)
+        .bind(worker)
+        .bind(LEASE_GRACE_SECS)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    // Jobs whose worker disappeared (crash, kill -9) count as a failed attempt
+    pub async fn reap_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'queued' END,
+                run_at = NOW(),
+                locked_by = NULL,
+                locked_until = NULL,
+                last_error = 'The worker running the job stopped responding',
+                finished_at = CASE WHEN attempts >= max_attempts THEN NOW() END
+            WHERE status = 'running' AND locked_until < NOW()
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Only the worker holding the job can finish it; false when it lost the lease
+    pub async fn complete(&self, id: Uuid, worker: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'succeeded', locked_by = NULL, locked_until = NULL, finished_at = NOW()
+            WHERE id = $1 AND locked_by = $2 AND status = 'running'
+            "#,
+        )
+        .bind(id)
+        .bind(worker)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Queue the job again after `retry_delay_secs`, or make it dead once it has used
+    // up its attempts. Returns the new status (None when the lease was lost).
+    pub async fn fail(
+        &self,
+        id: Uuid,
+        worker: &str,
+        error: &str,
+        retry_delay_secs: i64,
+    ) -> Result<Option<String>> {
+        let status = sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE jobs
+            SET status = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'queued' END,
+                run_at = NOW() + make_interval(secs => $4),
+                locked_by = NULL,
+                locked_until = NULL,
+                last_error = $3,
+                finished_at = CASE WHEN attempts >= max_attempts THEN NOW() END
+            WHERE id = $1 AND locked_by = $2 AND status = 'running'
+            RETURNING status
+            "#,
+        )
+        .bind(id)
+        .bind(worker)
+        .bind(error)
+        .bind(retry_delay_secs as f64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(status)
+    }
+
+    // Hand back the jobs a stopping worker could not finish; the attempt is not
+    // counted
+    pub async fn release(&self, worker: &str) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'queued',
+                attempts = GREATEST(attempts - 1, 0),
+                run_at = NOW(),
+                locked_by = NULL,
+                locked_until = NULL
+            WHERE locked_by = $1 AND status = 'running'
+            "#,
+        )
+        .bind(worker)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Newest first
+    pub async fn list(&self, query: &JobQuery, limit: i64) -> Result<Vec<JobRecord>> {
+        let jobs = sqlx::query_as::<_, JobRecord>(
+            r#"
+            SELECT * FROM jobs
+            WHERE ($1::TEXT IS NULL OR status = $1) AND ($2::TEXT IS NULL OR kind = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(&query.status)
+        .bind(&query.kind)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<JobRecord>> {
+        let job = sqlx::query_as::<_, JobRecord>("SELECT * FROM jobs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(job)
+    }
+
+    // Run a dead job again with a fresh set of attempts, or a queued one right away
+    pub async fn retry(&self, id: Uuid) -> Result<JobRecord> {
+        let job = self.get(id).await?.ok_or(AppError::NotFound)?;
+        if job.status != "dead" && job.status != "queued" {
+            return Err(AppError::Conflict(format!(
+                "Only dead or queued jobs can be retried; this one is {}",
+                job.status
+            )));
+        }
+
+        let retried = sqlx::query_as::<_, JobRecord>(
+            r#"
+            UPDATE jobs
+            SET status = 'queued',
+                attempts = CASE WHEN status = 'dead' THEN 0 ELSE attempts END,
+                run_at = NOW(),
+                finished_at = NULL
+            WHERE id = $1 AND status IN ('dead', 'queued')
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(
+                "A newer run of this job's schedule is already pending".to_string(),
+            ),
+            _ => AppError::from(e),
+        })?
+        .ok_or_else(|| AppError::Conflict("The job changed state; try again".to_string()))?;
+
+        Ok(retried)
+    }
+
+    pub async fn stats(&self) -> Result<Vec<JobStats>> {
+        let stats = sqlx::query_as::<_, JobStats>(
+            r#"
+            SELECT kind, status, COUNT(*) AS count, MIN(run_at) AS oldest_run_at
+            FROM jobs
+            GROUP BY kind, status
+            ORDER BY kind, status
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    // Succeeded and dead jobs are kept for JOB_RETENTION_DAYS
+    pub async fn purge_finished_older_than(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM jobs
+            WHERE status IN ('succeeded', 'dead')
+              AND finished_at < NOW() - make_interval(days => $1::INT)
+            "#,
+        )
+        .bind(days)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Register a schedule defined in the code. A changed definition starts over from
+    // `first_run_at`; an unchanged one keeps its next run.
+    pub async fn upsert_schedule(
+        &self,
+        name: &str,
+        kind: &str,
+        payload: &JsonValue,
+        cron: Option<&str>,
+        interval_secs: Option<i64>,
+        first_run_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO job_schedules (name, kind, payload, cron, interval_secs, next_run_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO UPDATE
+            SET kind = EXCLUDED.kind,
+                payload = EXCLUDED.payload,
+                cron = EXCLUDED.cron,
+                interval_secs = EXCLUDED.interval_secs,
+                next_run_at = CASE
+                    WHEN job_schedules.cron IS DISTINCT FROM EXCLUDED.cron
+                      OR job_schedules.interval_secs IS DISTINCT FROM EXCLUDED.interval_secs
+                    THEN EXCLUDED.next_run_at
+                    ELSE job_schedules.next_run_at
+                END,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(name)
+        .bind(kind)
+        .bind(payload)
+        .bind(cron)
+        .bind(interval_secs)
+        .bind(first_run_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Due schedules among `names`, locked so that only one instance queues them
+    pub async fn lock_due_schedules_in(
+        conn: &mut PgConnection,
+        names: &[&str],
+    ) -> Result<Vec<JobSchedule>> {
+        let schedules = sqlx::query_as::<_, JobSchedule>(
+            r#"
+            SELECT * FROM job_schedules
+            WHERE name = ANY($1) AND next_run_at <= NOW()
+            ORDER BY next_run_at
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(names)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(schedules)
+    }
+
+    pub async fn advance_schedule_in(
+        conn: &mut PgConnection,
+        name: &str,
+        next_run_at: DateTime<Utc>,
+        job_id: Option<Uuid>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE job_schedules
+            SET next_run_at = $2,
+                last_run_at = NOW(),
+                last_job_id = COALESCE($3, last_job_id)
+            WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .bind(next_run_at)
+        .bind(job_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_schedules(&self) -> Result<Vec<JobSchedule>> {
+        let schedules =
+            sqlx::query_as::<_, JobSchedule>("SELECT * FROM job_schedules ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(schedules)
+    }
+}
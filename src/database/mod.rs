@@ -1,5 +1,18 @@
 pub mod audit_repo;
+pub mod calendar_repo;
 pub mod dependency_repo;
+pub mod event_repo;
+pub mod hierarchy_repo;
+pub mod idempotency_repo;
+pub mod job_repo;
+pub mod notification_repo;
+pub mod overdue_repo;
+pub mod project_repo;
+pub mod propagation_repo;
+pub mod recurrence_repo;
+pub mod time_repo;
+pub mod watcher_repo;
+pub mod webhook_repo;
 
 use sqlx::{PgPool, postgres::PgPoolOptions};
 
@@ -0,0 +1,228 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::{
+        NOTIFICATION_KINDS, Notification, NotificationPreferences, NotificationQuery,
+        ReminderSettings,
+    },
+};
+
+// Columns of `Notification`, for `notifications n`
+const NOTIFICATION_COLUMNS: &str = r#"
+    n.id, n.kind, n.task_id, n.task_title, n.actor_id, u.email AS actor_email,
+    n.details, n.read_at, n.created_at
+"#;
+
+pub struct NotificationRepository {
+    pool: PgPool,
+}
+
+impl NotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // A user's notifications, newest first
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        query: &NotificationQuery,
+        limit: i64,
+    ) -> Result<Vec<Notification>> {
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
+            r#"
+            SELECT {NOTIFICATION_COLUMNS}
+            FROM notifications n
+            LEFT JOIN users u ON u.id = n.actor_id
+            WHERE n.user_id = $1
+              AND (NOT $2 OR n.read_at IS NULL)
+              AND ($3::TEXT IS NULL OR n.kind = $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR n.created_at < $4)
+            ORDER BY n.created_at DESC
+            LIMIT $5
+            "#
+        ))
+        .bind(user_id)
+        .bind(query.unread)
+        .bind(&query.kind)
+        .bind(query.before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    pub async fn unread_count(&self, user_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // None when the notification does not exist or belongs to someone else. Marking
+    // a read notification again keeps its original read time.
+    pub async fn mark_read(&self, user_id: Uuid, id: Uuid) -> Result<Option<Notification>> {
+        let notification = sqlx::query_as::<_, Notification>(&format!(
+            r#"
+            WITH marked AS (
+                UPDATE notifications
+                SET read_at = COALESCE(read_at, NOW())
+                WHERE id = $1 AND user_id = $2
+                RETURNING *
+            )
+            SELECT {NOTIFICATION_COLUMNS}
+            FROM marked n
+            LEFT JOIN users u ON u.id = n.actor_id
+            "#
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(notification)
+    }
+
+    // Returns how many notifications were unread
+    pub async fn mark_all_read(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Every kind with its setting; kinds the user never changed are on
+    pub async fn get_preferences(&self, user_id: Uuid) -> Result<NotificationPreferences> {
+        let stored = sqlx::query_as::<_, (String, bool)>(
+            "SELECT kind, enabled FROM notification_preferences WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut preferences: NotificationPreferences =
+            NOTIFICATION_KINDS.iter().map(|kind| (kind.to_string(), true)).collect();
+        preferences.extend(stored);
+
+        Ok(preferences)
+    }
+
+    pub async fn set_preferences(
+        &self,
+        user_id: Uuid,
+        changes: &NotificationPreferences,
+    ) -> Result<NotificationPreferences> {
+        let (kinds, enabled): (Vec<&str>, Vec<bool>) =
+            changes.iter().map(|(kind, enabled)| (kind.as_str(), *enabled)).unzip();
+
+        sqlx::query(
+            r#"
+            INSERT INTO notification_preferences (user_id, kind, enabled)
+            SELECT $1, kind, enabled FROM UNNEST($2::TEXT[], $3::BOOLEAN[]) AS p(kind, enabled)
+            ON CONFLICT (user_id, kind) DO UPDATE
+            SET enabled = EXCLUDED.enabled, updated_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(&kinds)
+        .bind(&enabled)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_preferences(user_id).await
+    }
+
+    // Users without settings get `default_hours`
+    pub async fn get_reminder_settings(
+        &self,
+        user_id: Uuid,
+        default_hours: i64,
+    ) -> Result<ReminderSettings> {
+        let hours = sqlx::query_scalar::<_, i32>(
+            "SELECT hours_before_due FROM reminder_settings WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(ReminderSettings { hours_before_due: hours.unwrap_or(default_hours as i32) })
+    }
+
+    pub async fn set_reminder_settings(
+        &self,
+        user_id: Uuid,
+        settings: &ReminderSettings,
+    ) -> Result<ReminderSettings> {
+        sqlx::query(
+            r#"
+            INSERT INTO reminder_settings (user_id, hours_before_due)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET hours_before_due = EXCLUDED.hours_before_due, updated_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(settings.hours_before_due)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(ReminderSettings { hours_before_due: settings.hours_before_due })
+    }
+
+    // Remind the assignee (the creator of unassigned tasks) of every open task due
+    // within their reminder window (`default_hours` unless they set one). Each due
+    // date is only reminded once, so restarts and repeated scans do not send it
+    // again, but a moved due date does.
+    pub async fn notify_due_soon(&self, default_hours: i64) -> Result<u64> {
+        let created = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*) FILTER (
+                WHERE create_notification(
+                    r.user_id,
+                    'due_soon',
+                    to_jsonb(t),
+                    NULL,
+                    jsonb_build_object('due_date', t.due_date),
+                    'due_soon:' || t.id || ':' || t.due_date
+                )
+            )
+            FROM tasks t
+            CROSS JOIN LATERAL (SELECT COALESCE(t.assigned_to, t.created_by) AS user_id) r
+            LEFT JOIN reminder_settings rs ON rs.user_id = r.user_id
+            WHERE t.deleted_at IS NULL
+              AND t.status NOT IN ('completed', 'cancelled')
+              AND t.due_date > NOW()
+              AND t.due_date <= NOW() + make_interval(
+                  hours => COALESCE(rs.hours_before_due, $1::INT)
+              )
+            "#,
+        )
+        .bind(default_hours)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(created as u64)
+    }
+
+    pub async fn purge_older_than(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM notifications WHERE created_at < NOW() - make_interval(days => $1::INT)",
+        )
+        .bind(days)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
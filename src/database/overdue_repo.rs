@@ -0,0 +1,176 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    database::project_repo::visible_to,
+    error::Result,
+    models::{OverdueQuery, OverdueTask},
+};
+
+// SQL condition: task `t` is open and past its due date
+const OVERDUE: &str = r#"
+    t.deleted_at IS NULL
+    AND t.status NOT IN ('completed', 'cancelled')
+    AND t.due_date < NOW()
+"#;
+
+// What one overdue scan did
+#[derive(Debug, Default)]
+pub struct OverdueScan {
+    pub marked: u64,
+    pub cleared: u64,
+    pub escalated: u64,
+}
+
+pub struct OverdueRepository {
+    pool: PgPool,
+}
+
+impl OverdueRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Overdue tasks the user can see, most overdue first
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        is_admin: bool,
+        query: &OverdueQuery,
+        limit: i64,
+    ) -> Result<Vec<OverdueTask>> {
+        let visible = visible_to("t", "$1", "$2");
+        let tasks = sqlx::query_as::<_, OverdueTask>(&format!(
+            r#"
+            SELECT t.*,
+                   (EXTRACT(EPOCH FROM NOW() - t.due_date) / 60)::BIGINT AS overdue_minutes,
+                   o.marked_at,
+                   o.escalated_at
+            FROM tasks t
+            LEFT JOIN overdue_tasks o ON o.task_id = t.id AND o.due_date = t.due_date
+            WHERE {OVERDUE}
+              AND {visible}
+              AND ($3::UUID IS NULL OR t.project_id = $3)
+              AND ($4::UUID IS NULL OR t.assigned_to = $4)
+              AND ($5::TEXT IS NULL OR t.priority = $5)
+            ORDER BY t.due_date
+            LIMIT $6
+            "#
+        ))
+        .bind(user_id)
+        .bind(is_admin)
+        .bind(query.project_id)
+        .bind(query.assigned_to)
+        .bind(&query.priority)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    // Mark tasks that went overdue since the last scan, with a `task.overdue` event
+    // and an `overdue` notification, forget the ones that were dealt with, and
+    // escalate high and urgent project tasks overdue for `escalate_after_hours` to
+    // the project owner. Each missed due date is reported and escalated once.
+    pub async fn scan(&self, escalate_after_hours: i64) -> Result<OverdueScan> {
+        let mut tx = self.pool.begin().await?;
+
+        let cleared = sqlx::query(&format!(
+            r#"
+            DELETE FROM overdue_tasks o
+            WHERE NOT EXISTS (
+                SELECT 1 FROM tasks t
+                WHERE t.id = o.task_id AND t.due_date = o.due_date AND {OVERDUE}
+            )
+            "#
+        ))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let marked = sqlx::query_scalar::<_, Uuid>(&format!(
+            r#"
+            INSERT INTO overdue_tasks (task_id, due_date)
+            SELECT t.id, t.due_date FROM tasks t
+            WHERE {OVERDUE}
+            ON CONFLICT (task_id) DO NOTHING
+            RETURNING task_id
+            "#
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !marked.is_empty() {
+            sqlx::query(
+                r#"
+                INSERT INTO task_events (event_type, task_id, project_id, payload)
+                SELECT 'task.overdue', t.id, t.project_id, to_jsonb(t)
+                FROM tasks t
+                WHERE t.id = ANY($1)
+                "#,
+            )
+            .bind(&marked)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                SELECT create_notification(
+                    COALESCE(t.assigned_to, t.created_by),
+                    'overdue',
+                    to_jsonb(t),
+                    NULL,
+                    jsonb_build_object('due_date', t.due_date),
+                    'overdue:' || t.id || ':' || t.due_date
+                )
+                FROM tasks t
+                WHERE t.id = ANY($1)
+                "#,
+            )
+            .bind(&marked)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let escalated = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH escalated AS (
+                UPDATE overdue_tasks o
+                SET escalated_at = NOW()
+                FROM tasks t
+                WHERE t.id = o.task_id
+                  AND o.escalated_at IS NULL
+                  AND t.project_id IS NOT NULL
+                  AND t.priority IN ('high', 'urgent')
+                  AND o.due_date < NOW() - make_interval(hours => $1::INT)
+                RETURNING o.task_id
+            )
+            SELECT COUNT(*) FILTER (
+                WHERE create_notification(
+                    p.owner_id,
+                    'escalated',
+                    to_jsonb(t),
+                    NULL,
+                    jsonb_build_object(
+                        'due_date', t.due_date,
+                        'priority', t.priority,
+                        'assigned_to', t.assigned_to
+                    ),
+                    'escalated:' || t.id || ':' || t.due_date
+                )
+            )
+            FROM escalated e
+            JOIN tasks t ON t.id = e.task_id
+            JOIN projects p ON p.id = t.project_id
+            "#,
+        )
+        .bind(escalate_after_hours)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(OverdueScan { marked: marked.len() as u64, cleared, escalated: escalated as u64 })
+    }
+}
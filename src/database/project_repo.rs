@@ -0,0 +1,243 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::{Project, ProjectMember},
+};
+
+// SQL condition: the user bound as `{user}` may see `{alias}`, a task or series, when it
+// is outside any project or the user is a member of its project. `{admin}` is an SQL
+// boolean, whether the user is an admin (admins see everything).
+pub fn visible_to(alias: &str, user: &str, admin: &str) -> String {
+    format!(
+        r#"({alias}.project_id IS NULL
+            OR {admin}
+            OR EXISTS (
+                SELECT 1 FROM project_members pm
+                WHERE pm.project_id = {alias}.project_id AND pm.user_id = {user}
+            ))"#
+    )
+}
+
+pub struct ProjectRepository {
+    pool: PgPool,
+}
+
+impl ProjectRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Create a project; the owner is its first member
+    pub async fn create(
+        &self,
+        owner_id: Uuid,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Project> {
+        let mut tx = self.pool.begin().await?;
+
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+            INSERT INTO projects (name, description, owner_id)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(description)
+        .bind(owner_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO project_members (project_id, user_id) VALUES ($1, $2)")
+            .bind(project.id)
+            .bind(owner_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(project)
+    }
+
+    pub async fn get(&self, project_id: Uuid) -> Result<Option<Project>> {
+        let project = sqlx::query_as::<_, Project>("SELECT * FROM projects WHERE id = $1")
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(project)
+    }
+
+    // Projects the user is a member of
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<Project>> {
+        let projects = sqlx::query_as::<_, Project>(
+            r#"
+            SELECT p.*
+            FROM projects p
+            JOIN project_members pm ON pm.project_id = p.id
+            WHERE pm.user_id = $1
+            ORDER BY p.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects)
+    }
+
+    // IDs of the projects a user is a member of
+    pub async fn member_project_ids(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            "SELECT project_id FROM project_members WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    // Whether the user may see a task of `project_id` (None: outside any project)
+    pub async fn can_see_in(
+        conn: &mut PgConnection,
+        project_id: Option<Uuid>,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<bool> {
+        match project_id {
+            Some(project_id) if !is_admin => {
+                let member = sqlx::query_scalar::<_, bool>(
+                    "SELECT EXISTS(SELECT 1 FROM project_members WHERE project_id = $1 AND user_id = $2)",
+                )
+                .bind(project_id)
+                .bind(user_id)
+                .fetch_one(conn)
+                .await?;
+                Ok(member)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    pub async fn is_member(&self, project_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let member = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM project_members WHERE project_id = $1 AND user_id = $2)",
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(member)
+    }
+
+    pub async fn list_members(&self, project_id: Uuid) -> Result<Vec<ProjectMember>> {
+        let members = sqlx::query_as::<_, ProjectMember>(
+            r#"
+            SELECT pm.user_id, u.email, pm.added_at
+            FROM project_members pm
+            JOIN users u ON u.id = pm.user_id
+            WHERE pm.project_id = $1
+            ORDER BY pm.added_at
+            "#,
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(members)
+    }
+
+    pub async fn add_member(&self, project_id: Uuid, user_id: Uuid) -> Result<()> {
+        let user_exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if !user_exists {
+            return Err(AppError::NotFound);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO project_members (project_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // The owner cannot be removed
+    pub async fn remove_member(&self, project_id: Uuid, user_id: Uuid) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM project_members pm
+            USING projects p
+            WHERE pm.project_id = $1 AND pm.user_id = $2
+              AND p.id = pm.project_id AND p.owner_id != pm.user_id
+            "#,
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    // Tasks can only be put in a project the user belongs to (admins: any project)
+    pub async fn check_task_project_in(
+        conn: &mut PgConnection,
+        project_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<()> {
+        let allowed = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM projects p
+                WHERE p.id = $1 AND ($3 OR EXISTS(
+                    SELECT 1 FROM project_members pm
+                    WHERE pm.project_id = p.id AND pm.user_id = $2
+                ))
+            )
+            "#,
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_one(conn)
+        .await?;
+
+        if !allowed {
+            return Err(AppError::BadRequest(
+                "Project not found or you are not a member of it".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn check_task_project(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        Self::check_task_project_in(&mut conn, project_id, user_id, is_admin).await
+    }
+}
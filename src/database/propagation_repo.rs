@@ -0,0 +1,170 @@
+use sqlx::PgConnection;
+use std::collections::{HashSet, VecDeque};
+use uuid::Uuid;
+
+use crate::{
+    config::PropagationRules,
+    database::audit_repo::AuditRepository,
+    error::Result,
+    models::{AuditLog, DependencyKind, Task},
+};
+
+// SQL condition: the link `td` to predecessor `t` does not let its task run. Lag is
+// ignored here: it only delays a start, it does not take a started task back.
+const RUN_UNMET: &str = r#"NOT CASE td.kind
+        WHEN 'finish_to_start' THEN t.status = 'completed'
+        WHEN 'start_to_start' THEN t.started_at IS NOT NULL
+        ELSE TRUE
+    END"#;
+
+pub struct PropagationRepository;
+
+impl PropagationRepository {
+    // Apply the propagation rules after `task` moved from `previous_status` (None for
+    // a task that did not exist before), inside the caller's transaction: the change
+    // and its consequences commit together. Changed dependents propagate in turn (a
+    // dependent moved back to pending is no longer started, a cancelled one cancels
+    // its own dependents); each task is changed at most once per call. Returns the
+    // dependents that were changed.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn propagate_in(
+        conn: &mut PgConnection,
+        rules: PropagationRules,
+        user_id: Uuid,
+        batch_id: Option<Uuid>,
+        cause: &AuditLog,
+        previous_status: Option<&str>,
+        task: &Task,
+    ) -> Result<Vec<Task>> {
+        let mut changed = Vec::new();
+        let mut seen = HashSet::from([task.id]);
+        let mut queue =
+            VecDeque::from([(task.id, previous_status.map(str::to_string), task.status.clone())]);
+
+        while let Some((id, previous, status)) = queue.pop_front() {
+            if previous.as_deref() == Some(status.as_str()) {
+                continue;
+            }
+
+            for (dependent, new_status) in
+                Self::affected_dependents(conn, rules, id, &status).await?
+            {
+                if !seen.insert(dependent.id) {
+                    continue;
+                }
+
+                let updated = sqlx::query_as::<_, Task>(
+                    r#"
+                    UPDATE tasks
+                    SET status = $1, updated_at = NOW()
+                    WHERE id = $2
+                    RETURNING *
+                    "#,
+                )
+                .bind(new_status)
+                .bind(dependent.id)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                AuditRepository::log_caused_action(
+                    conn,
+                    cause.id,
+                    batch_id,
+                    user_id,
+                    "PROPAGATE",
+                    "task",
+                    updated.id,
+                    Some(dependent.snapshot()),
+                    Some(updated.snapshot()),
+                )
+                .await?;
+
+                queue.push_back((updated.id, Some(dependent.status), updated.status.clone()));
+                changed.push(updated);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    // Direct dependents of `task_id` that have to change now that it is `status`,
+    // locked, with the status they move to
+    async fn affected_dependents(
+        conn: &mut PgConnection,
+        rules: PropagationRules,
+        task_id: Uuid,
+        status: &str,
+    ) -> Result<Vec<(Task, &'static str)>> {
+        if status == "cancelled" && rules.cascade_cancellation {
+            let cancelled = sqlx::query_as::<_, Task>(
+                r#"
+                SELECT d.* FROM tasks d
+                WHERE d.deleted_at IS NULL AND d.status NOT IN ('completed', 'cancelled')
+                  AND d.id IN (
+                      SELECT task_id FROM task_dependencies
+                      WHERE depends_on = $1 AND kind = ANY($2)
+                  )
+                ORDER BY d.created_at
+                FOR UPDATE
+                "#,
+            )
+            .bind(task_id)
+            .bind(&DependencyKind::BLOCKING[..])
+            .fetch_all(&mut *conn)
+            .await?;
+
+            return Ok(cancelled.into_iter().map(|task| (task, "cancelled")).collect());
+        }
+
+        let mut affected = Vec::new();
+
+        if rules.block_on_reopen {
+            let query = format!(
+                r#"
+                SELECT d.* FROM tasks d
+                WHERE d.deleted_at IS NULL AND d.status = 'in_progress'
+                  AND EXISTS (
+                      SELECT 1
+                      FROM task_dependencies td
+                      JOIN tasks t ON t.id = td.depends_on
+                      WHERE td.task_id = d.id AND td.depends_on = $1 AND {RUN_UNMET}
+                  )
+                ORDER BY d.created_at
+                FOR UPDATE
+                "#
+            );
+            let blocked =
+                sqlx::query_as::<_, Task>(&query).bind(task_id).fetch_all(&mut *conn).await?;
+            affected.extend(blocked.into_iter().map(|task| (task, "blocked")));
+        }
+
+        if rules.unblock_on_complete {
+            let query = format!(
+                r#"
+                SELECT d.* FROM tasks d
+                WHERE d.deleted_at IS NULL AND d.status = 'blocked'
+                  AND d.id IN (
+                      SELECT task_id FROM task_dependencies
+                      WHERE depends_on = $1 AND kind = ANY($2)
+                  )
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM task_dependencies td
+                      JOIN tasks t ON t.id = td.depends_on
+                      WHERE td.task_id = d.id AND t.deleted_at IS NULL AND {RUN_UNMET}
+                  )
+                ORDER BY d.created_at
+                FOR UPDATE
+                "#
+            );
+            let unblocked = sqlx::query_as::<_, Task>(&query)
+                .bind(task_id)
+                .bind(&DependencyKind::BLOCKING[..])
+                .fetch_all(&mut *conn)
+                .await?;
+            affected.extend(unblocked.into_iter().map(|task| (task, "pending")));
+        }
+
+        Ok(affected)
+    }
+}
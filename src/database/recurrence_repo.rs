@@ -0,0 +1,722 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    database::audit_repo::AuditRepository,
+    error::{AppError, Result},
+    models::{
+        CreateRecurrenceRequest, SeriesOccurrence, SeriesQuery, Task, TaskSeries,
+        UpdateRecurrenceRequest, normalize_labels,
+    },
+    utils::rrule::RRule,
+};
+
+// Occurrences one scan creates for a series at most, so a long lead time on a
+// daily rule cannot flood the task list
+const MAX_PER_SCAN: usize = 10;
+
+// SQL condition: series `s` has an occurrence that is still open
+const HAS_OPEN_OCCURRENCE: &str = r#"
+    EXISTS (
+        SELECT 1 FROM task_occurrences o
+        JOIN tasks t ON t.id = o.task_id
+        WHERE o.series_id = s.id
+          AND t.deleted_at IS NULL
+          AND t.status NOT IN ('completed', 'cancelled')
+    )
+"#;
+
+pub struct RecurrenceRepository {
+    pool: PgPool,
+}
+
+impl RecurrenceRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Start a series from `task`, which becomes its first occurrence and its template
+    pub async fn create(
+        &self,
+        task: &Task,
+        dtstart: DateTime<Utc>,
+        rule: &RRule,
+        request: &CreateRecurrenceRequest,
+        created_by: Uuid,
+    ) -> Result<TaskSeries> {
+        let mut tx = self.pool.begin().await?;
+
+        let next_due_at = rule.nth(dtstart, 1);
+        let series = sqlx::query_as::<_, TaskSeries>(
+            r#"
+            INSERT INTO task_series
+            (rrule, dtstart, generate, lead_time_hours, title, description, priority, labels,
+             assigned_to, project_id, estimated_minutes, copy_labels, copy_assignee,
+             copy_dependencies, next_due_at, created_by, ended_at)
+            VALUES ($1, $2, COALESCE($3, 'on_complete'), COALESCE($4, 24), $5, $6, $7, $8, $9,
+                    $10, $11, COALESCE($12, TRUE), COALESCE($13, TRUE), COALESCE($14, FALSE),
+                    $15, $16, CASE WHEN $15::TIMESTAMPTZ IS NULL THEN NOW() END)
+            RETURNING *
+            "#,
+        )
+        .bind(rule.to_string())
+        .bind(dtstart)
+        .bind(&request.generate)
+        .bind(request.lead_time_hours)
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(&task.priority)
+        .bind(&task.labels)
+        .bind(task.assigned_to)
+        .bind(task.project_id)
+        .bind(task.estimated_minutes)
+        .bind(request.copy_labels)
+        .bind(request.copy_assignee)
+        .bind(request.copy_dependencies)
+        .bind(next_due_at)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO task_occurrences (task_id, series_id, occurrence, scheduled_for)
+            VALUES ($1, $2, 1, $3)
+            "#,
+        )
+        .bind(task.id)
+        .bind(series.id)
+        .bind(dtstart)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(series)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<TaskSeries>> {
+        let series = sqlx::query_as::<_, TaskSeries>("SELECT * FROM task_series WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(series)
+    }
+
+    // The series a task belongs to, with the task's occurrence number
+    pub async fn find_by_task(&self, task_id: Uuid) -> Result<Option<(TaskSeries, i32)>> {
+        let found = sqlx::query_as::<_, (Uuid, i32)>(
+            "SELECT series_id, occurrence FROM task_occurrences WHERE task_id = $1",
+        )
+        .bind(task_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((series_id, occurrence)) = found else {
+            return Ok(None);
+        };
+        Ok(self.get(series_id).await?.map(|series| (series, occurrence)))
+    }
+
+    // Series the user can see: their own, those assigned to them, and those of their
+    // projects (every series for admins)
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        is_admin: bool,
+        query: &SeriesQuery,
+    ) -> Result<Vec<TaskSeries>> {
+        let series = sqlx::query_as::<_, TaskSeries>(
+            r#"
+            SELECT s.* FROM task_series s
+            WHERE (
+                $2
+                OR s.created_by = $1
+                OR s.assigned_to = $1
+                OR EXISTS (
+                    SELECT 1 FROM project_members pm
+                    WHERE pm.project_id = s.project_id AND pm.user_id = $1
+                )
+            )
+              AND ($3::UUID IS NULL OR s.project_id = $3)
+              AND ($4 OR s.ended_at IS NULL)
+            ORDER BY s.created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(is_admin)
+        .bind(query.project_id)
+        .bind(query.include_ended)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(series)
+    }
+
+    pub async fn occurrences(&self, series_id: Uuid) -> Result<Vec<SeriesOccurrence>> {
+        let occurrences = sqlx::query_as::<_, SeriesOccurrence>(
+            r#"
+            SELECT o.task_id, o.occurrence, o.scheduled_for, t.title, t.status, t.due_date,
+                   t.deleted_at
+            FROM task_occurrences o
+            JOIN tasks t ON t.id = o.task_id
+            WHERE o.series_id = $1
+            ORDER BY o.occurrence
+            "#,
+        )
+        .bind(series_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(occurrences)
+    }
+
+    // Stop creating occurrences; the existing ones are left alone
+    pub async fn stop(&self, id: Uuid) -> Result<Option<TaskSeries>> {
+        let series = sqlx::query_as::<_, TaskSeries>(
+            r#"
+            UPDATE task_series
+            SET ended_at = COALESCE(ended_at, NOW()), next_due_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(series)
+    }
+
+    // Edit occurrence `occurrence` of `series` and every later one. From the first
+    // occurrence this updates the series in place; from a later one the series is
+    // split: it ends, and a new series takes over from this occurrence on, with the
+    // later occurrences renumbered into it. Template field changes are applied to
+    // the open occurrences of the new part, each with its own audit entry.
+    pub async fn update_from(
+        &self,
+        series_id: Uuid,
+        occurrence: i32,
+        rule: Option<&RRule>,
+        changes: &UpdateRecurrenceRequest,
+        user_id: Uuid,
+    ) -> Result<(TaskSeries, Vec<Task>)> {
+        let mut tx = self.pool.begin().await?;
+
+        let series =
+            sqlx::query_as::<_, TaskSeries>("SELECT * FROM task_series WHERE id = $1 FOR UPDATE")
+                .bind(series_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if series.ended_at.is_some() {
+            return Err(AppError::Conflict(
+                "The series has ended: it creates no more occurrences to edit".to_string(),
+            ));
+        }
+
+        // With no occurrences left (they were all purged meanwhile) the edit is one of
+        // the whole series
+        let first = sqlx::query_scalar::<_, Option<i32>>(
+            "SELECT MIN(occurrence) FROM task_occurrences WHERE series_id = $1",
+        )
+        .bind(series_id)
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(occurrence);
+
+        let labels = changes.labels.clone().map(normalize_labels);
+        let current_rule: RRule = series.rrule.parse()?;
+
+        let (target, from) = if occurrence <= first {
+            let rule = rule.unwrap_or(&current_rule);
+            let next_due_at = rule.nth(series.dtstart, series.next_occurrence as usize - 1);
+            let updated =
+                update_series(&mut tx, &series, rule, next_due_at, changes, &labels).await?;
+            (updated, first)
+        } else {
+            let scheduled_for = sqlx::query_scalar::<_, DateTime<Utc>>(
+                "SELECT scheduled_for FROM task_occurrences WHERE series_id = $1 AND occurrence = $2",
+            )
+            .bind(series_id)
+            .bind(occurrence)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            // An unchanged rule continues where the old series is; a new one starts
+            // counting from this occurrence
+            let taken = (occurrence - 1) as u32;
+            let rule = match rule {
+                Some(rule) => rule.clone(),
+                None => current_rule.skip_count(taken).unwrap_or(current_rule.clone()),
+            };
+            let next_occurrence = series.next_occurrence - taken as i32;
+            let next_due_at = rule.nth(scheduled_for, next_occurrence as usize - 1);
+
+            sqlx::query(
+                r#"
+                UPDATE task_series
+                SET ended_at = NOW(), next_due_at = NULL, updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(series_id)
+            .execute(&mut *tx)
+            .await?;
+
+            let split = sqlx::query_as::<_, TaskSeries>(
+                r#"
+                INSERT INTO task_series
+                (rrule, dtstart, generate, lead_time_hours, title, description, priority, labels,
+                 assigned_to, project_id, estimated_minutes, copy_labels, copy_assignee,
+                 copy_dependencies, next_occurrence, created_by)
+                SELECT rrule, $2, generate, lead_time_hours, title, description, priority, labels,
+                       assigned_to, project_id, estimated_minutes, copy_labels, copy_assignee,
+                       copy_dependencies, $3, created_by
+                FROM task_series WHERE id = $1
+                RETURNING *
+                "#,
+            )
+            .bind(series_id)
+            .bind(scheduled_for)
+            .bind(next_occurrence)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                UPDATE task_occurrences
+                SET series_id = $2, occurrence = occurrence - $3
+                WHERE series_id = $1 AND occurrence >= $4
+                "#,
+            )
+            .bind(series_id)
+            .bind(split.id)
+            .bind(taken as i32)
+            .bind(occurrence)
+            .execute(&mut *tx)
+            .await?;
+
+            let updated =
+                update_series(&mut tx, &split, &rule, next_due_at, changes, &labels).await?;
+            (updated, 1)
+        };
+
+        let mut tasks = Vec::new();
+        if changes.changes_tasks() {
+            let open = sqlx::query_as::<_, Task>(
+                r#"
+                SELECT t.* FROM task_occurrences o
+                JOIN tasks t ON t.id = o.task_id
+                WHERE o.series_id = $1
+                  AND o.occurrence >= $2
+                  AND t.deleted_at IS NULL
+                  AND t.status NOT IN ('completed', 'cancelled')
+                ORDER BY o.occurrence
+                FOR UPDATE OF t
+                "#,
+            )
+            .bind(target.id)
+            .bind(from)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            for old in open {
+                let task = sqlx::query_as::<_, Task>(
+                    r#"
+                    UPDATE tasks
+                    SET title = COALESCE($2, title),
+                        description = COALESCE($3, description),
+                        priority = COALESCE($4, priority),
+                        labels = COALESCE($5, labels),
+                        assigned_to = COALESCE($6, assigned_to),
+                        estimated_minutes = COALESCE($7, estimated_minutes),
+                        updated_at = NOW()
+                    WHERE id = $1
+                    RETURNING *
+                    "#,
+                )
+                .bind(old.id)
+                .bind(&changes.title)
+                .bind(&changes.description)
+                .bind(&changes.priority)
+                .bind(&labels)
+                .bind(changes.assigned_to)
+                .bind(changes.estimated_minutes)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                AuditRepository::log_action_in(
+                    &mut tx,
+                    user_id,
+                    "UPDATE",
+                    "task",
+                    task.id,
+                    Some(old.snapshot()),
+                    Some(task.snapshot()),
+                )
+                .await?;
+                tasks.push(task);
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok((target, tasks))
+    }
+
+    // Series with occurrences to create: on_complete series whose occurrences are
+    // all closed, and on_schedule series whose next one comes within their lead time
+    pub async fn due_series(&self) -> Result<Vec<Uuid>> {
+        let due = sqlx::query_scalar::<_, Uuid>(&format!(
+            r#"
+            SELECT s.id FROM task_series s
+            WHERE s.ended_at IS NULL
+              AND (
+                  (s.generate = 'on_complete' AND NOT {HAS_OPEN_OCCURRENCE})
+                  OR (
+                      s.generate = 'on_schedule'
+                      AND s.next_due_at - make_interval(hours => s.lead_time_hours) <= NOW()
+                  )
+              )
+            "#
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(due)
+    }
+
+    // Create the due occurrences of one series; occurrences already past due are
+    // skipped. Returns how many tasks were created.
+    pub async fn generate_for(&self, series_id: Uuid) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        // Another instance is already on it
+        let Some(mut series) = sqlx::query_as::<_, TaskSeries>(
+            "SELECT * FROM task_series WHERE id = $1 AND ended_at IS NULL FOR UPDATE SKIP LOCKED",
+        )
+        .bind(series_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(0);
+        };
+
+        // Nobody is left to create occurrences in the name of
+        let Some(owner) = series_owner(&mut tx, &series).await? else {
+            end_series(&mut tx, series.id).await?;
+            tx.commit().await?;
+            return Ok(0);
+        };
+
+        let rule: RRule = series.rrule.parse()?;
+        let now = Utc::now();
+        let mut created = 0;
+
+        while created < MAX_PER_SCAN {
+            let ready = match series.generate.as_str() {
+                "on_schedule" => series.next_due_at.is_some_and(|due| {
+                    due - chrono::Duration::hours(i64::from(series.lead_time_hours)) <= now
+                }),
+                _ => created == 0 && !has_open_occurrence(&mut tx, series.id).await?,
+            };
+            if !ready {
+                break;
+            }
+
+            let next = rule
+                .occurrences(series.dtstart)
+                .enumerate()
+                .skip(series.next_occurrence as usize - 1)
+                .find(|(_, at)| *at > now);
+            let Some((index, due)) = next else {
+                end_series(&mut tx, series.id).await?;
+                break;
+            };
+
+            create_occurrence(&mut tx, &series, owner, index as i32 + 1, due).await?;
+            created += 1;
+
+            let next_due_at = rule.nth(series.dtstart, index + 1);
+            series = sqlx::query_as::<_, TaskSeries>(
+                r#"
+                UPDATE task_series
+                SET next_occurrence = $2,
+                    next_due_at = $3,
+                    ended_at = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN NOW() END,
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#,
+            )
+            .bind(series.id)
+            .bind(index as i32 + 2)
+            .bind(next_due_at)
+            .fetch_one(&mut *tx)
+            .await?;
+            if series.ended_at.is_some() {
+                break;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(created as u64)
+    }
+}
+
+async fn has_open_occurrence(conn: &mut PgConnection, series_id: Uuid) -> Result<bool> {
+    let open = sqlx::query_scalar::<_, bool>(&format!(
+        "SELECT {HAS_OPEN_OCCURRENCE} FROM task_series s WHERE s.id = $1"
+    ))
+    .bind(series_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(open)
+}
+
+// Who new occurrences are created by: the series' creator, or once that user is gone,
+// its assignee, or else the owner of its project
+async fn series_owner(conn: &mut PgConnection, series: &TaskSeries) -> Result<Option<Uuid>> {
+    if let Some(created_by) = series.created_by {
+        return Ok(Some(created_by));
+    }
+
+    let owner = sqlx::query_scalar::<_, Option<Uuid>>(
+        r#"
+        SELECT COALESCE(s.assigned_to, p.owner_id)
+        FROM task_series s
+        LEFT JOIN projects p ON p.id = s.project_id
+        WHERE s.id = $1
+        "#,
+    )
+    .bind(series.id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(owner)
+}
+
+async fn end_series(conn: &mut PgConnection, series_id: Uuid) -> Result<()> {
+    sqlx::query(
+        "UPDATE task_series SET ended_at = NOW(), next_due_at = NULL, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(series_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// Apply an edit's rule and settings to a series
+async fn update_series(
+    conn: &mut PgConnection,
+    series: &TaskSeries,
+    rule: &RRule,
+    next_due_at: Option<DateTime<Utc>>,
+    changes: &UpdateRecurrenceRequest,
+    labels: &Option<Vec<String>>,
+) -> Result<TaskSeries> {
+    let series = sqlx::query_as::<_, TaskSeries>(
+        r#"
+        UPDATE task_series
+        SET rrule = $2,
+            next_due_at = $3,
+            ended_at = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN NOW() END,
+            generate = COALESCE($4, generate),
+            lead_time_hours = COALESCE($5, lead_time_hours),
+            copy_labels = COALESCE($6, copy_labels),
+            copy_assignee = COALESCE($7, copy_assignee),
+            copy_dependencies = COALESCE($8, copy_dependencies),
+            title = COALESCE($9, title),
+            description = COALESCE($10, description),
+            priority = COALESCE($11, priority),
+            labels = COALESCE($12, labels),
+            assigned_to = COALESCE($13, assigned_to),
+            estimated_minutes = COALESCE($14, estimated_minutes),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(series.id)
+    .bind(rule.to_string())
+    .bind(next_due_at)
+    .bind(&changes.generate)
+    .bind(changes.lead_time_hours)
+    .bind(changes.copy_labels)
+    .bind(changes.copy_assignee)
+    .bind(changes.copy_dependencies)
+    .bind(&changes.title)
+    .bind(&changes.description)
+    .bind(&changes.priority)
+    .bind(labels)
+    .bind(changes.assigned_to)
+    .bind(changes.estimated_minutes)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(series)
+}
+
+// Create occurrence number `occurrence` from the template, due at `due`, by `owner`
+async fn create_occurrence(
+    conn: &mut PgConnection,
+    series: &TaskSeries,
+    owner: Uuid,
+    occurrence: i32,
+    due: DateTime<Utc>,
+) -> Result<Task> {
+    let previous = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT task_id FROM task_occurrences
+        WHERE series_id = $1
+        ORDER BY occurrence DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(series.id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let labels = if series.copy_labels { series.labels.clone() } else { Vec::new() };
+    let assigned_to = if series.copy_assignee { series.assigned_to } else { None };
+
+    let task = sqlx::query_as::<_, Task>(
+        r#"
+        INSERT INTO tasks
+        (title, description, priority, assigned_to, created_by, due_date, labels,
+         estimated_minutes, project_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING *
+        "#,
+    )
+    .bind(&series.title)
+    .bind(&series.description)
+    .bind(&series.priority)
+    .bind(assigned_to)
+    .bind(owner)
+    .bind(due)
+    .bind(&labels)
+    .bind(series.estimated_minutes)
+    .bind(series.project_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO task_occurrences (task_id, series_id, occurrence, scheduled_for)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(task.id)
+    .bind(series.id)
+    .bind(occurrence)
+    .bind(due)
+    .execute(&mut *conn)
+    .await?;
+
+    // The new task has no dependents yet, so its links cannot close a cycle. Links
+    // to other occurrences of the series are not carried over.
+    if series.copy_dependencies
+        && let Some(previous) = previous
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO task_dependencies (task_id, depends_on, kind, lag_minutes)
+            SELECT $1, d.depends_on, d.kind, d.lag_minutes
+            FROM task_dependencies d
+            JOIN tasks t ON t.id = d.depends_on
+            WHERE d.task_id = $2
+              AND t.deleted_at IS NULL
+              AND d.depends_on NOT IN (
+                  SELECT task_id FROM task_occurrences WHERE series_id = $3
+              )
+            "#,
+        )
+        .bind(task.id)
+        .bind(previous)
+        .bind(series.id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    AuditRepository::log_action_in(
+        conn,
+        owner,
+        "CREATE",
+        "task",
+        task.id,
+        Some(json!({})),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn user(pool: &PgPool, email: &str) -> Uuid {
+        sqlx::query_scalar("INSERT INTO users (email, password_hash) VALUES ($1, '') RETURNING id")
+            .bind(email)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    // An on_schedule daily series whose second occurrence is due within its lead time
+    async fn series(pool: &PgPool, created_by: Option<Uuid>, assigned_to: Option<Uuid>) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO task_series
+            (rrule, dtstart, generate, title, assigned_to, created_by, next_due_at)
+            VALUES ('FREQ=DAILY', NOW() - INTERVAL '1 hour', 'on_schedule', 'daily', $1, $2,
+                    NOW() + INTERVAL '23 hours')
+            RETURNING id
+            "#,
+        )
+        .bind(assigned_to)
+        .bind(created_by)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn occurrences_of_a_series_without_creator_go_to_its_assignee(pool: PgPool) {
+        let bob = user(&pool, "bob@example.com").await;
+        let series_id = series(&pool, None, Some(bob)).await;
+
+        let repo = RecurrenceRepository::new(pool.clone());
+        assert_eq!(repo.generate_for(series_id).await.unwrap(), 1);
+
+        let created_by: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT t.created_by FROM task_occurrences o
+            JOIN tasks t ON t.id = o.task_id
+            WHERE o.series_id = $1
+            "#,
+        )
+        .bind(series_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(created_by, Some(bob));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn a_series_without_owner_ends(pool: PgPool) {
+        let series_id = series(&pool, None, None).await;
+
+        let repo = RecurrenceRepository::new(pool);
+        assert_eq!(repo.generate_for(series_id).await.unwrap(), 0);
+        assert!(repo.get(series_id).await.unwrap().unwrap().ended_at.is_some());
+    }
+}
@@ -0,0 +1,483 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    database::audit_repo::AuditRepository,
+    error::{AppError, Result},
+    models::{TaskTimeSummary, TaskTimeTotal, TimeEntry, UserTimeTotal},
+};
+
+// Columns of `TimeEntry`, for `time_entries {ENTRY_FROM}` (or `{ENTRY_FROM}` over a
+// CTE named `e` holding the rows just written)
+const ENTRY_COLUMNS: &str = r#"
+    e.id, e.task_id, t.title AS task_title, t.project_id, e.user_id, u.email AS user_email,
+    e.started_at, e.ended_at,
+    ROUND(EXTRACT(EPOCH FROM COALESCE(e.ended_at, NOW()) - e.started_at) / 60)::BIGINT AS minutes,
+    e.note, e.source, e.created_at, e.updated_at
+"#;
+
+const ENTRY_FROM: &str = r#"
+    e
+    JOIN tasks t ON t.id = e.task_id
+    JOIN users u ON u.id = e.user_id
+"#;
+
+// Minutes of entry `e`. Totals add up the rounded minutes of their entries, so they
+// match the rows of an export.
+const MINUTES: &str =
+    "ROUND(EXTRACT(EPOCH FROM COALESCE(e.ended_at, NOW()) - e.started_at) / 60)::BIGINT";
+
+// Which entries to report on; entries count on the day they started
+#[derive(Debug, Default)]
+pub struct TimeFilter {
+    pub user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+// SQL condition for a `TimeFilter` bound as $1 to $4
+const FILTER: &str = r#"
+    ($1::UUID IS NULL OR e.user_id = $1)
+    AND ($2::UUID IS NULL OR t.project_id = $2)
+    AND ($3::TIMESTAMPTZ IS NULL OR e.started_at >= $3)
+    AND ($4::TIMESTAMPTZ IS NULL OR e.started_at < $4)
+"#;
+
+pub struct TimeEntryRepository {
+    pool: PgPool,
+}
+
+impl TimeEntryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<TimeEntry>> {
+        get_in(&mut *self.pool.acquire().await?, id, false).await
+    }
+
+    // The user's running timer
+    pub async fn running(&self, user_id: Uuid) -> Result<Option<TimeEntry>> {
+        let entry = sqlx::query_as::<_, TimeEntry>(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM time_entries {ENTRY_FROM} WHERE e.user_id = $1 AND e.ended_at IS NULL"
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(entry)
+    }
+
+    // Start a timer on the task, stopping the user's running one first. Returns the
+    // stopped entry, if any, and the new one.
+    pub async fn start_timer(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        note: Option<&str>,
+    ) -> Result<(Option<TimeEntry>, TimeEntry)> {
+        let mut tx = self.pool.begin().await?;
+        lock_user_in(&mut tx, user_id).await?;
+
+        let stopped = stop_in(&mut tx, user_id).await?;
+        let started = insert_in(&mut tx, task_id, user_id, Utc::now(), None, note, "timer").await?;
+
+        tx.commit().await?;
+
+        Ok((stopped, started))
+    }
+
+    pub async fn stop_timer(&self, user_id: Uuid) -> Result<Option<TimeEntry>> {
+        let mut tx = self.pool.begin().await?;
+        let stopped = stop_in(&mut tx, user_id).await?;
+        tx.commit().await?;
+
+        Ok(stopped)
+    }
+
+    pub async fn create_manual(
+        &self,
+        task_id: Uuid,
+        user_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        note: Option<&str>,
+    ) -> Result<TimeEntry> {
+        let mut tx = self.pool.begin().await?;
+        lock_user_in(&mut tx, user_id).await?;
+        check_overlap_in(&mut tx, user_id, started_at, Some(ended_at), None).await?;
+        let entry =
+            insert_in(&mut tx, task_id, user_id, started_at, Some(ended_at), note, "manual")
+                .await?;
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    // `actor_id` is who made the change, for the audit log
+    pub async fn update(
+        &self,
+        id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+        note: Option<&str>,
+        actor_id: Uuid,
+    ) -> Result<Option<TimeEntry>> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(old) = get_in(&mut tx, id, true).await? else {
+            return Ok(None);
+        };
+        lock_user_in(&mut tx, old.user_id).await?;
+        check_overlap_in(&mut tx, old.user_id, started_at, ended_at, Some(id)).await?;
+        let entry = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            WITH e AS (
+                UPDATE time_entries
+                SET started_at = $2, ended_at = $3, note = $4, updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+            )
+            SELECT {ENTRY_COLUMNS} FROM {ENTRY_FROM}
+            "#
+        ))
+        .bind(id)
+        .bind(started_at)
+        .bind(ended_at)
+        .bind(note)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditRepository::log_action_in(
+            &mut tx,
+            actor_id,
+            "UPDATE",
+            "time_entry",
+            id,
+            Some(old.snapshot()),
+            Some(entry.snapshot()),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(entry))
+    }
+
+    // Returns whether the entry existed
+    pub async fn delete(&self, id: Uuid, actor_id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(old) = get_in(&mut tx, id, true).await? else {
+            return Ok(false);
+        };
+        sqlx::query("DELETE FROM time_entries WHERE id = $1").bind(id).execute(&mut *tx).await?;
+
+        AuditRepository::log_action_in(
+            &mut tx,
+            actor_id,
+            "DELETE",
+            "time_entry",
+            id,
+            Some(old.snapshot()),
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn list_for_task(&self, task_id: Uuid) -> Result<Vec<TimeEntry>> {
+        let entries = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            SELECT {ENTRY_COLUMNS} FROM time_entries {ENTRY_FROM}
+            WHERE e.task_id = $1
+            ORDER BY e.started_at DESC
+            "#
+        ))
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    // Entries matching the filter, oldest first
+    pub async fn list(&self, filter: &TimeFilter) -> Result<Vec<TimeEntry>> {
+        let entries = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            SELECT {ENTRY_COLUMNS} FROM time_entries {ENTRY_FROM}
+            WHERE {FILTER}
+            ORDER BY e.started_at
+            "#
+        ))
+        .bind(filter.user_id)
+        .bind(filter.project_id)
+        .bind(filter.from)
+        .bind(filter.until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    // Time per task among the matching entries, most first
+    pub async fn totals_by_task(&self, filter: &TimeFilter) -> Result<Vec<TaskTimeTotal>> {
+        let totals = sqlx::query_as::<_, TaskTimeTotal>(&format!(
+            r#"
+            SELECT t.id AS task_id, t.title, t.estimated_minutes,
+                   SUM({MINUTES})::BIGINT AS logged_minutes
+            FROM time_entries e
+            JOIN tasks t ON t.id = e.task_id
+            WHERE {FILTER}
+            GROUP BY t.id
+            ORDER BY logged_minutes DESC, t.title
+            "#
+        ))
+        .bind(filter.user_id)
+        .bind(filter.project_id)
+        .bind(filter.from)
+        .bind(filter.until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(totals)
+    }
+
+    // Time per user among the matching entries, most first
+    pub async fn totals_by_user(&self, filter: &TimeFilter) -> Result<Vec<UserTimeTotal>> {
+        let totals = sqlx::query_as::<_, UserTimeTotal>(&format!(
+            r#"
+            SELECT u.id AS user_id, u.email, SUM({MINUTES})::BIGINT AS minutes
+            FROM time_entries {ENTRY_FROM}
+            WHERE {FILTER}
+            GROUP BY u.id
+            ORDER BY minutes DESC, u.email
+            "#
+        ))
+        .bind(filter.user_id)
+        .bind(filter.project_id)
+        .bind(filter.from)
+        .bind(filter.until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(totals)
+    }
+
+    // Sum of the estimates of the project's live tasks
+    pub async fn project_estimate(&self, project_id: Uuid) -> Result<i64> {
+        let estimate = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(estimated_minutes), 0)::BIGINT FROM tasks
+            WHERE project_id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(project_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(estimate)
+    }
+
+    // The task's estimate and logged time, and those of its whole live subtree
+    pub async fn task_summary(&self, task_id: Uuid) -> Result<TaskTimeSummary> {
+        let subtree = r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id, estimated_minutes FROM tasks WHERE id = $1
+                UNION ALL
+                SELECT t.id, t.estimated_minutes
+                FROM tasks t
+                JOIN subtree s ON t.parent_id = s.id
+                WHERE t.deleted_at IS NULL
+            )
+        "#;
+
+        let (estimated_minutes, subtree_estimated_minutes, logged_minutes, subtree_logged_minutes) =
+            sqlx::query_as::<_, (Option<i32>, i64, i64, i64)>(&format!(
+                r#"
+                {subtree}
+                SELECT
+                    (SELECT estimated_minutes FROM subtree WHERE id = $1),
+                    (SELECT COALESCE(SUM(estimated_minutes), 0) FROM subtree)::BIGINT,
+                    COALESCE(SUM({MINUTES}) FILTER (WHERE e.task_id = $1), 0)::BIGINT,
+                    COALESCE(SUM({MINUTES}), 0)::BIGINT
+                FROM time_entries e
+                WHERE e.task_id IN (SELECT id FROM subtree)
+                "#
+            ))
+            .bind(task_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let users = sqlx::query_as::<_, UserTimeTotal>(&format!(
+            r#"
+            {subtree}
+            SELECT u.id AS user_id, u.email, SUM({MINUTES})::BIGINT AS minutes
+            FROM time_entries e
+            JOIN users u ON u.id = e.user_id
+            WHERE e.task_id IN (SELECT id FROM subtree)
+            GROUP BY u.id
+            ORDER BY minutes DESC, u.email
+            "#
+        ))
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(TaskTimeSummary {
+            task_id,
+            estimated_minutes,
+            logged_minutes,
+            subtree_estimated_minutes,
+            subtree_logged_minutes,
+            remaining_minutes: subtree_estimated_minutes - subtree_logged_minutes,
+            users,
+        })
+    }
+}
+
+async fn get_in(conn: &mut PgConnection, id: Uuid, for_update: bool) -> Result<Option<TimeEntry>> {
+    let lock = if for_update { "FOR UPDATE OF e" } else { "" };
+    let entry = sqlx::query_as::<_, TimeEntry>(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM time_entries {ENTRY_FROM} WHERE e.id = $1 {lock}"
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(entry)
+}
+
+// Serialize changes to one user's entries for the rest of the transaction, so the
+// running timer and overlap checks below see each other's writes
+async fn lock_user_in(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('time_entries:' || $1::text, 0))")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// A user's entries must not overlap; a running timer counts as open-ended. `except` is
+// the entry being changed.
+async fn check_overlap_in(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    except: Option<Uuid>,
+) -> Result<()> {
+    let overlaps = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM time_entries
+            WHERE user_id = $1 AND ($4::UUID IS NULL OR id != $4)
+              AND tstzrange(started_at, ended_at) && tstzrange($2, $3)
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(started_at)
+    .bind(ended_at)
+    .bind(except)
+    .fetch_one(conn)
+    .await?;
+
+    if overlaps {
+        return Err(AppError::Conflict(
+            "Overlaps another time entry of the user, or the running timer".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn stop_in(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<TimeEntry>> {
+    let Some(old) = sqlx::query_as::<_, TimeEntry>(&format!(
+        r#"
+        SELECT {ENTRY_COLUMNS} FROM time_entries {ENTRY_FROM}
+        WHERE e.user_id = $1 AND e.ended_at IS NULL
+        FOR UPDATE OF e
+        "#
+    ))
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let entry = sqlx::query_as::<_, TimeEntry>(&format!(
+        r#"
+        WITH e AS (
+            UPDATE time_entries
+            SET ended_at = GREATEST(NOW(), started_at), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+        )
+        SELECT {ENTRY_COLUMNS} FROM {ENTRY_FROM}
+        "#
+    ))
+    .bind(old.id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    AuditRepository::log_action_in(
+        conn,
+        user_id,
+        "UPDATE",
+        "time_entry",
+        entry.id,
+        Some(old.snapshot()),
+        Some(entry.snapshot()),
+    )
+    .await?;
+
+    Ok(Some(entry))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_in(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    note: Option<&str>,
+    source: &str,
+) -> Result<TimeEntry> {
+    let entry = sqlx::query_as::<_, TimeEntry>(&format!(
+        r#"
+        WITH e AS (
+            INSERT INTO time_entries (task_id, user_id, started_at, ended_at, note, source)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+        )
+        SELECT {ENTRY_COLUMNS} FROM {ENTRY_FROM}
+        "#
+    ))
+    .bind(task_id)
+    .bind(user_id)
+    .bind(started_at)
+    .bind(ended_at)
+    .bind(note)
+    .bind(source)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    AuditRepository::log_action_in(
+        conn,
+        user_id,
+        "CREATE",
+        "time_entry",
+        entry.id,
+        Some(serde_json::json!({})),
+        Some(entry.snapshot()),
+    )
+    .await?;
+
+    Ok(entry)
+}
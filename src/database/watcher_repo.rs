@@ -0,0 +1,90 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    database::project_repo::visible_to,
+    error::Result,
+    models::{Task, TaskWatcher},
+};
+
+pub struct WatcherRepository {
+    pool: PgPool,
+}
+
+impl WatcherRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self, task_id: Uuid) -> Result<Vec<TaskWatcher>> {
+        let watchers = sqlx::query_as::<_, TaskWatcher>(
+            r#"
+            SELECT w.user_id, u.email, w.created_at
+            FROM task_watchers w
+            JOIN users u ON u.id = w.user_id
+            WHERE w.task_id = $1
+            ORDER BY w.created_at
+            "#,
+        )
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(watchers)
+    }
+
+    // Watching a task twice is not an error
+    pub async fn watch(&self, task_id: Uuid, user_id: Uuid) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO task_watchers (task_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(task_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unwatch(&self, task_id: Uuid, user_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM task_watchers WHERE task_id = $1 AND user_id = $2")
+            .bind(task_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn watched_task_ids(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids =
+            sqlx::query_scalar::<_, Uuid>("SELECT task_id FROM task_watchers WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(ids)
+    }
+
+    // Live tasks the user watches and can still see, most recently updated first
+    pub async fn list_watched_tasks(&self, user_id: Uuid, is_admin: bool) -> Result<Vec<Task>> {
+        let tasks = sqlx::query_as::<_, Task>(&format!(
+            r#"
+            SELECT t.*
+            FROM task_watchers w
+            JOIN tasks t ON t.id = w.task_id
+            WHERE w.user_id = $1
+              AND t.deleted_at IS NULL
+              AND {}
+            ORDER BY t.updated_at DESC
+            "#,
+            visible_to("t", "$1", "$2")
+        ))
+        .bind(user_id)
+        .bind(is_admin)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+}
@@ -0,0 +1,375 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::WebhookSettings,
+    error::{AppError, Result},
+    models::{
+        AttemptOutcome, DueDelivery, UpdateWebhookRequest, Webhook, WebhookDelivery,
+        WebhookDeliveryAttempt,
+    },
+};
+
+// Retries never wait longer than this
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 3600;
+
+pub struct WebhookRepository {
+    pool: PgPool,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        owner_id: Uuid,
+        url: &str,
+        event_types: &[String],
+        project_id: Option<Uuid>,
+        secret: &str,
+    ) -> Result<Webhook> {
+        let webhook = sqlx::query_as::<_, Webhook>(
+            r#"
+            INSERT INTO webhooks (owner_id, url, event_types, project_id, secret)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(owner_id)
+        .bind(url)
+        .bind(event_types)
+        .bind(project_id)
+        .bind(secret)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn list_for_owner(&self, owner_id: Uuid) -> Result<Vec<Webhook>> {
+        let webhooks = sqlx::query_as::<_, Webhook>(
+            "SELECT * FROM webhooks WHERE owner_id = $1 ORDER BY created_at",
+        )
+        .bind(owner_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<Webhook>> {
+        let webhook = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(webhook)
+    }
+
+    // Fields left out stay as they are. Enabling a webhook clears its failure count
+    // and the reason it was disabled.
+    pub async fn update(&self, id: Uuid, changes: &UpdateWebhookRequest) -> Result<Webhook> {
+        let webhook = sqlx::query_as::<_, Webhook>(
+            r#"
+            UPDATE webhooks
+            SET url = COALESCE($2, url),
+                event_types = COALESCE($3, event_types),
+                secret = COALESCE($4, secret),
+                active = COALESCE($5, active),
+                consecutive_failures = CASE WHEN $5 THEN 0 ELSE consecutive_failures END,
+                disabled_at = CASE
+                    WHEN $5 THEN NULL
+                    WHEN NOT $5 THEN COALESCE(disabled_at, NOW())
+                    ELSE disabled_at
+                END,
+                disabled_reason = CASE
+                    WHEN $5 THEN NULL
+                    WHEN NOT $5 THEN COALESCE(disabled_reason, 'Disabled by its owner')
+                    ELSE disabled_reason
+                END,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(&changes.url)
+        .bind(&changes.event_types)
+        .bind(&changes.secret)
+        .bind(changes.active)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(webhook)
+    }
+
+    // Also drops its delivery log
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        let result =
+            sqlx::query("DELETE FROM webhooks WHERE id = $1").bind(id).execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    // Newest first
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        status: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1 AND ($2::TEXT IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn get_delivery(
+        &self,
+        webhook_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<Option<WebhookDelivery>> {
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT * FROM webhook_deliveries WHERE id = $1 AND webhook_id = $2",
+        )
+        .bind(delivery_id)
+        .bind(webhook_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    pub async fn list_attempts(&self, delivery_id: Uuid) -> Result<Vec<WebhookDeliveryAttempt>> {
+        let attempts = sqlx::query_as::<_, WebhookDeliveryAttempt>(
+            r#"
+            SELECT attempt, response_status, error, duration_ms, attempted_at
+            FROM webhook_delivery_attempts
+            WHERE delivery_id = $1
+            ORDER BY attempt
+            "#,
+        )
+        .bind(delivery_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(attempts)
+    }
+
+    // Queue the same payload again as a new delivery, with a fresh set of attempts
+    pub async fn redeliver(&self, original: &WebhookDelivery) -> Result<WebhookDelivery> {
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            INSERT INTO webhook_deliveries
+            (webhook_id, event_id, event_type, payload, redelivery_of)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(original.webhook_id)
+        .bind(original.event_id)
+        .bind(&original.event_type)
+        .bind(&original.payload)
+        .bind(original.id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    // Queue a `ping` delivery, to check a receiver without changing any task
+    pub async fn enqueue_ping(&self, webhook: &Webhook) -> Result<WebhookDelivery> {
+        let payload = serde_json::json!({
+            "event_type": "ping",
+            "webhook_id": webhook.id,
+            "event_types": webhook.event_types,
+            "project_id": webhook.project_id,
+        });
+
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event_type, payload)
+            VALUES ($1, 'ping', $2)
+            RETURNING *
+            "#,
+        )
+        .bind(webhook.id)
+        .bind(payload)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    // Claim due deliveries of active webhooks. Claimed rows are pushed `lease_secs`
+    // into the future, so other instances skip them and a crashed sender's
+    // deliveries are picked up again once the lease runs out.
+    pub async fn claim_due(&self, limit: i64, lease_secs: i64) -> Result<Vec<DueDelivery>> {
+        let deliveries = sqlx::query_as::<_, DueDelivery>(
+            r#"
+            WITH claimed AS (
+                UPDATE webhook_deliveries
+                SET next_attempt_at = NOW() + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT d.id
+                    FROM webhook_deliveries d
+                    JOIN webhooks w ON w.id = d.webhook_id
+                    WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.active
+                    ORDER BY d.next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE OF d SKIP LOCKED
+                )
+                RETURNING id, webhook_id, event_type, payload, attempts
+            )
+            SELECT c.id, c.webhook_id, c.event_type, c.payload, c.attempts, w.url, w.secret
+            FROM claimed c
+            JOIN webhooks w ON w.id = c.webhook_id
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    // Log an attempt and schedule what comes next: done, a retry with exponential
+    // backoff, or giving up after the last attempt. Failures count against the
+    // webhook; returns true when this one got it disabled.
+    pub async fn record_attempt(
+        &self,
+        settings: &WebhookSettings,
+        delivery: &DueDelivery,
+        outcome: &AttemptOutcome,
+    ) -> Result<bool> {
+        let attempt = delivery.attempts + 1;
+        let succeeded = outcome.succeeded();
+        let status = if succeeded {
+            "delivered"
+        } else if attempt >= settings.max_attempts {
+            "failed"
+        } else {
+            "pending"
+        };
+        let retry_delay = settings
+            .retry_base_secs
+            .saturating_mul(1i64 << (attempt - 1).clamp(0, 20))
+            .min(MAX_RETRY_DELAY_SECS);
+
+        let mut tx = self.pool.begin().await?;
+
+        // The webhook may have been deleted while the request was in flight
+        let updated = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2,
+                attempts = $3,
+                last_attempt_at = NOW(),
+                next_attempt_at = NOW() + make_interval(secs => $4),
+                response_status = $5,
+                error = $6,
+                delivered_at = CASE WHEN $2 = 'delivered' THEN NOW() END
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(status)
+        .bind(attempt)
+        .bind(retry_delay as f64)
+        .bind(outcome.response_status)
+        .bind(&outcome.error)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_delivery_attempts
+            (delivery_id, attempt, response_status, error, duration_ms)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(attempt)
+        .bind(outcome.response_status)
+        .bind(&outcome.error)
+        .bind(outcome.duration_ms)
+        .execute(&mut *tx)
+        .await?;
+
+        let disabled = if succeeded {
+            sqlx::query("UPDATE webhooks SET consecutive_failures = 0 WHERE id = $1")
+                .bind(delivery.webhook_id)
+                .execute(&mut *tx)
+                .await?;
+            false
+        } else {
+            sqlx::query_scalar::<_, bool>(
+                r#"
+                UPDATE webhooks
+                SET consecutive_failures = consecutive_failures + 1,
+                    active = active AND consecutive_failures + 1 < $2,
+                    disabled_at = CASE
+                        WHEN active AND consecutive_failures + 1 >= $2 THEN NOW()
+                        ELSE disabled_at
+                    END,
+                    disabled_reason = CASE
+                        WHEN active AND consecutive_failures + 1 >= $2
+                            THEN format('%s delivery attempts failed in a row', $2)
+                        ELSE disabled_reason
+                    END
+                WHERE id = $1
+                RETURNING consecutive_failures = $2
+                "#,
+            )
+            .bind(delivery.webhook_id)
+            .bind(settings.disable_after)
+            .fetch_optional(&mut *tx)
+            .await?
+            .unwrap_or(false)
+        };
+
+        tx.commit().await?;
+        Ok(disabled)
+    }
+
+    // The delivery log is kept for WEBHOOK_DELIVERY_RETENTION_DAYS. Pending deliveries
+    // are kept while their webhook is active; those of a disabled webhook go too, so
+    // they do not pile up (re-enabling it still sends the more recent ones).
+    pub async fn purge_deliveries_older_than(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM webhook_deliveries d
+            USING webhooks w
+            WHERE w.id = d.webhook_id
+              AND d.created_at < NOW() - make_interval(days => $1::INT)
+              AND (d.status != 'pending' OR NOT w.active)
+            "#,
+        )
+        .bind(days)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
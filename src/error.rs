@@ -11,7 +11,14 @@ pub enum AppError {
     DatabaseError(sqlx::Error),
     NotFound,
     ValidationError(ValidationErrors),
+    BadRequest(String),
+    UnprocessableEntity(String),
+    UnsupportedMediaType(String),
     Unauthorized(String),
+    Conflict(String),
+    // The If-Match precondition did not hold; carries the current representation
+    PreconditionFailed(Box<serde_json::Value>),
+    PreconditionRequired(String),
     InternalError(String),
 }
 
@@ -26,7 +33,19 @@ impl IntoResponse for AppError {
             AppError::ValidationError(e) => {
                 (StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
             }
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::UnprocessableEntity(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            AppError::UnsupportedMediaType(msg) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg),
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::PreconditionFailed(current) => {
+                let body = Json(json!({
+                    "error": "Resource has been modified",
+                    "current": current,
+                }));
+                return (StatusCode::PRECONDITION_FAILED, body).into_response();
+            }
+            AppError::PreconditionRequired(msg) => (StatusCode::PRECONDITION_REQUIRED, msg),
             AppError::InternalError(msg) => {
                 tracing::error!("Internal error: {:?}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, msg)
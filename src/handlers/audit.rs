@@ -1,7 +1,9 @@
 use crate::{
     database::audit_repo::AuditRepository,
     error::Result,
-    models::{AuditLogWithUser, AuditQuery},
+    handlers::tasks::ensure_history_visible,
+    jobs::audit_retention,
+    models::{AuditArchive, AuditLogWithUser, AuditPartitionReport, AuditQuery},
     state::AppState,
     utils::jwt::Claims,
 };
@@ -17,6 +19,8 @@ pub async fn get_task_history(
     State(state): State<AppState>,
     Path(task_id): Path<Uuid>,
 ) -> Result<Json<Vec<AuditLogWithUser>>> {
+    ensure_history_visible(&state, &claims, task_id).await?;
+
     let repo = AuditRepository::new(state.pool);
     let logs = repo.get_resource_history("task", task_id).await?;
 
@@ -54,3 +58,35 @@ pub async fn get_recent_activity(
 
     Ok(Json(logs))
 }
+
+// Admin: Report audit log partition sizes and past archives
+pub async fn get_audit_partitions(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<AuditPartitionReport>> {
+    if claims.role != "admin" {
+        return Err(crate::error::AppError::Unauthorized("Admin access required".to_string()));
+    }
+    let repo = AuditRepository::new(state.pool);
+    let partitions = repo.list_partitions().await?;
+    let archives = repo.list_archives().await?;
+
+    Ok(Json(AuditPartitionReport {
+        retention_months: state.config.audit_retention_months,
+        partitions,
+        archives,
+    }))
+}
+
+// Admin: Run the retention policy now instead of waiting for the next scheduled run
+pub async fn archive_audit_partitions(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AuditArchive>>> {
+    if claims.role != "admin" {
+        return Err(crate::error::AppError::Unauthorized("Admin access required".to_string()));
+    }
+    let archives = audit_retention::run(&state.pool, &state.config).await?;
+
+    Ok(Json(archives))
+}
@@ -94,7 +94,7 @@ pub async fn login(
 
 // Debug endpoint: decode your own token
 pub async fn whoami(
-    State(state): State<AppState>,
+    State(_state): State<AppState>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<serde_json::Value>> {
     Ok(Json(serde_json::json!({
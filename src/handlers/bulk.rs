@@ -0,0 +1,507 @@
+use crate::{
+    config::PropagationRules,
+    database::{
+        audit_repo::AuditRepository,
+        dependency_repo::DependencyRepository,
+        hierarchy_repo::HierarchyRepository,
+        project_repo::{ProjectRepository, visible_to},
+        propagation_repo::PropagationRepository,
+    },
+    error::{AppError, Result},
+    handlers::tasks::OPEN_SUBTASKS,
+    models::{
+        BulkFilter, BulkItemResult, BulkItemStatus, BulkOperation, BulkRequest, BulkResponse,
+        CreateTaskRequest, Task, TaskPatchDocument, normalize_labels,
+    },
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{Extension, Json, extract::State};
+use chrono::Utc;
+use serde_json::{Value, json};
+use sqlx::{Acquire, PgConnection, Postgres, QueryBuilder, error::ErrorKind};
+use uuid::Uuid;
+use validator::Validate;
+
+// Upper bound on the number of tasks a single bulk request may touch
+const MAX_BULK_ITEMS: usize = 500;
+
+// Why a single item failed. Database errors that are not about the item itself
+// (lost connection, ...) abort the whole request instead.
+enum ItemFailure {
+    Validation(String),
+    Forbidden,
+    // Its dependencies or its open subtasks hold the status change up
+    DependencyBlocked(&'static str),
+    NotFound,
+}
+
+type ItemResult = std::result::Result<Option<Task>, ItemFailure>;
+
+// Who is running the batch
+struct Actor {
+    user_id: Uuid,
+    is_admin: bool,
+    batch_id: Uuid,
+    max_task_depth: i32,
+    propagation: PropagationRules,
+}
+
+impl Actor {
+    fn can_edit(&self, task: &Task) -> bool {
+        task.editable_by(self.user_id, self.is_admin)
+    }
+
+    // Same rule as DELETE /api/tasks/{id}
+    fn can_delete(&self, task: &Task) -> bool {
+        self.is_admin || task.created_by == Some(self.user_id)
+    }
+}
+
+// Create, update, delete or relabel many tasks in one transaction.
+// Each item runs in its own savepoint, so one failing item does not undo the
+// others unless `atomic` is set. Every item gets its own audit entry, all
+// sharing the batch ID.
+pub async fn bulk_tasks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Json(payload): Json<BulkRequest>,
+) -> Result<Json<BulkResponse>> {
+    let actor = Actor {
+        user_id: claims.user_id()?,
+        is_admin: claims.role == "admin",
+        batch_id: Uuid::new_v4(),
+        max_task_depth: state.config.max_task_depth,
+        propagation: state.config.propagation,
+    };
+
+    let mut tx = state.pool.begin().await?;
+    let mut results = Vec::new();
+
+    match &payload.operation {
+        BulkOperation::Create { items } => {
+            if items.len() > MAX_BULK_ITEMS {
+                return Err(too_many_items());
+            }
+
+            for (index, item) in items.iter().enumerate() {
+                let mut savepoint = Acquire::begin(&mut tx).await?;
+                let outcome = catch_db(create_item(&mut savepoint, &actor, item).await)?;
+                results.push(finish_item(savepoint, index, None, outcome).await?);
+            }
+        }
+        operation => {
+            let ids = resolve_targets(&mut tx, &actor, &payload).await?;
+
+            for (index, id) in ids.into_iter().enumerate() {
+                let mut savepoint = Acquire::begin(&mut tx).await?;
+                let outcome = match operation {
+                    BulkOperation::Update { set } => {
+                        update_item(&mut savepoint, &actor, id, set).await
+                    }
+                    BulkOperation::Delete => delete_item(&mut savepoint, &actor, id).await,
+                    BulkOperation::Relabel { add, remove, set } => {
+                        relabel_item(&mut savepoint, &actor, id, add, remove, set.as_deref()).await
+                    }
+                    BulkOperation::Create { .. } => unreachable!(),
+                };
+                let outcome = catch_db(outcome)?;
+                results.push(finish_item(savepoint, index, Some(id), outcome).await?);
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.status != BulkItemStatus::Success).count();
+    let committed = !(payload.atomic && failed > 0);
+
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+    }
+
+    Ok(Json(BulkResponse {
+        batch_id: actor.batch_id,
+        committed,
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    }))
+}
+
+// Commit or roll back an item's savepoint and turn its outcome into a result
+async fn finish_item(
+    savepoint: sqlx::Transaction<'_, Postgres>,
+    index: usize,
+    task_id: Option<Uuid>,
+    outcome: ItemResult,
+) -> Result<BulkItemResult> {
+    let result = match outcome {
+        Ok(task) => {
+            savepoint.commit().await?;
+            BulkItemResult {
+                index,
+                task_id: task_id.or(task.as_ref().map(|t| t.id)),
+                status: BulkItemStatus::Success,
+                error: None,
+                task,
+            }
+        }
+        Err(failure) => {
+            savepoint.rollback().await?;
+            let (status, error) = match failure {
+                ItemFailure::Validation(msg) => (BulkItemStatus::ValidationError, msg),
+                ItemFailure::Forbidden => {
+                    (BulkItemStatus::Forbidden, "Not allowed to modify this task".to_string())
+                }
+                ItemFailure::DependencyBlocked(reason) => {
+                    (BulkItemStatus::DependencyBlocked, reason.to_string())
+                }
+                ItemFailure::NotFound => (BulkItemStatus::NotFound, "Task not found".to_string()),
+            };
+            BulkItemResult { index, task_id, status, error: Some(error), task: None }
+        }
+    };
+
+    Ok(result)
+}
+
+// Constraint violations (unknown assignee, ...) and rejected values (invalid parent, ...)
+// belong to the item; anything else means the database is in trouble and the request
+// should fail. The database's own message names tables and constraints, so items get
+// a fixed one instead.
+fn catch_db(outcome: std::result::Result<ItemResult, AppError>) -> Result<ItemResult> {
+    match outcome {
+        Ok(outcome) => Ok(outcome),
+        Err(AppError::BadRequest(msg)) => Ok(Err(ItemFailure::Validation(msg))),
+        Err(AppError::DatabaseError(e)) => {
+            let msg = match e.as_database_error().map(|db_err| db_err.kind()) {
+                Some(ErrorKind::ForeignKeyViolation) => {
+                    "Refers to a user or task that does not exist"
+                }
+                Some(ErrorKind::UniqueViolation) => "Conflicts with an existing record",
+                Some(ErrorKind::NotNullViolation) => "A required field is missing",
+                Some(ErrorKind::CheckViolation) => "A field has a value that is not allowed",
+                _ => return Err(AppError::DatabaseError(e)),
+            };
+            Ok(Err(ItemFailure::Validation(msg.to_string())))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn too_many_items() -> AppError {
+    AppError::BadRequest(format!("A bulk request may touch at most {} tasks", MAX_BULK_ITEMS))
+}
+
+// Turn `ids` or `filter` into the list of target task IDs
+async fn resolve_targets(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    payload: &BulkRequest,
+) -> Result<Vec<Uuid>> {
+    let ids = match (&payload.ids, &payload.filter) {
+        (Some(ids), None) => {
+            let mut unique = Vec::with_capacity(ids.len());
+            for id in ids {
+                if !unique.contains(id) {
+                    unique.push(*id);
+                }
+            }
+            unique
+        }
+        (None, Some(filter)) => select_by_filter(conn, actor, filter).await?,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Provide exactly one of `ids` or `filter`".to_string(),
+            ));
+        }
+    };
+
+    if ids.len() > MAX_BULK_ITEMS {
+        return Err(too_many_items());
+    }
+
+    Ok(ids)
+}
+
+// Only tasks the actor can see are selected
+async fn select_by_filter(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    filter: &BulkFilter,
+) -> Result<Vec<Uuid>> {
+    let mut query = QueryBuilder::<Postgres>::new("WITH actor AS (SELECT ");
+    query.push_bind(actor.user_id).push("::UUID AS user_id, ");
+    query.push_bind(actor.is_admin).push("::BOOL AS is_admin)");
+    query.push(" SELECT tasks.id FROM tasks, actor WHERE tasks.deleted_at IS NULL AND ");
+    query.push(visible_to("tasks", "actor.user_id", "actor.is_admin"));
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(priority) = &filter.priority {
+        query.push(" AND priority = ").push_bind(priority);
+    }
+    if let Some(assigned_to) = filter.assigned_to {
+        query.push(" AND assigned_to = ").push_bind(assigned_to);
+    }
+    if let Some(created_by) = filter.created_by {
+        query.push(" AND created_by = ").push_bind(created_by);
+    }
+    if let Some(label) = &filter.label {
+        query.push(" AND ").push_bind(label).push(" = ANY(labels)");
+    }
+    // One more than allowed, so an oversized filter is rejected rather than truncated
+    query.push(" ORDER BY created_at LIMIT ").push_bind((MAX_BULK_ITEMS + 1) as i64);
+
+    let ids = query.build_query_scalar::<Uuid>().fetch_all(conn).await?;
+
+    Ok(ids)
+}
+
+// A task the actor cannot see is not found
+async fn lock_task(conn: &mut PgConnection, actor: &Actor, id: Uuid) -> Result<Option<Task>> {
+    let task = sqlx::query_as::<_, Task>(&format!(
+        "SELECT t.* FROM tasks t WHERE t.id = $1 AND t.deleted_at IS NULL AND {} FOR UPDATE OF t",
+        visible_to("t", "$2", "$3")
+    ))
+    .bind(id)
+    .bind(actor.user_id)
+    .bind(actor.is_admin)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(task)
+}
+
+async fn create_item(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    item: &CreateTaskRequest,
+) -> Result<ItemResult> {
+    if let Err(e) = item.validate() {
+        return Ok(Err(ItemFailure::Validation(e.to_string())));
+    }
+    if let Some(parent_id) = item.parent_id {
+        HierarchyRepository::validate_parent_in(conn, None, parent_id, actor.max_task_depth)
+            .await?;
+    }
+    if let Some(project_id) = item.project_id {
+        ProjectRepository::check_task_project_in(conn, project_id, actor.user_id, actor.is_admin)
+            .await?;
+    }
+
+    let task = sqlx::query_as::<_, Task>(
+        r#"
+        INSERT INTO tasks
+        (title, description, priority, assigned_to, created_by, due_date, labels, parent_id,
+         estimated_minutes, project_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(&item.title)
+    .bind(&item.description)
+    .bind(item.priority.clone().unwrap_or_else(|| "medium".to_string()))
+    .bind(item.assigned_to)
+    .bind(actor.user_id)
+    .bind(item.due_date)
+    .bind(normalize_labels(item.labels.clone()))
+    .bind(item.parent_id)
+    .bind(item.estimated_minutes)
+    .bind(item.project_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    AuditRepository::log_batch_action(
+        conn,
+        actor.batch_id,
+        actor.user_id,
+        "CREATE",
+        "task",
+        task.id,
+        Some(json!({})),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    Ok(Ok(Some(task)))
+}
+
+async fn update_item(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    id: Uuid,
+    set: &Value,
+) -> Result<ItemResult> {
+    let Some(existing) = lock_task(conn, actor, id).await? else {
+        return Ok(Err(ItemFailure::NotFound));
+    };
+    if !actor.can_edit(&existing) {
+        return Ok(Err(ItemFailure::Forbidden));
+    }
+
+    let patched = match TaskPatchDocument::from(&existing).merged(set) {
+        Ok(patched) => patched,
+        Err(e) => return Ok(Err(ItemFailure::Validation(e.to_string()))),
+    };
+    if let Err(e) = patched.validate() {
+        return Ok(Err(ItemFailure::Validation(e.to_string())));
+    }
+
+    // Gated by the target status whenever the status changes
+    let completing = patched.status == "completed";
+    let completed_at = if patched.status == existing.status {
+        existing.completed_at
+    } else if !DependencyRepository::allows_status_in(conn, id, &patched.status).await? {
+        return Ok(Err(ItemFailure::DependencyBlocked("Task is blocked by its dependencies")));
+    } else if completing && HierarchyRepository::has_open_children_in(conn, id).await? {
+        return Ok(Err(ItemFailure::DependencyBlocked(OPEN_SUBTASKS)));
+    } else {
+        completing.then(Utc::now)
+    };
+
+    if let Some(parent_id) = patched.parent_id.filter(|&p| existing.parent_id != Some(p)) {
+        HierarchyRepository::validate_parent_in(conn, Some(id), parent_id, actor.max_task_depth)
+            .await?;
+    }
+    if let Some(project_id) = patched.project_id.filter(|&p| existing.project_id != Some(p)) {
+        ProjectRepository::check_task_project_in(conn, project_id, actor.user_id, actor.is_admin)
+            .await?;
+    }
+
+    let task = sqlx::query_as::<_, Task>(
+        r#"
+        UPDATE tasks
+        SET title = $1,
+            description = $2,
+            status = $3,
+            priority = $4,
+            assigned_to = $5,
+            due_date = $6,
+            completed_at = $7,
+            labels = $8,
+            parent_id = $9,
+            estimated_minutes = $10,
+            project_id = $11,
+            updated_at = NOW()
+        WHERE id = $12
+        RETURNING *
+        "#,
+    )
+    .bind(&patched.title)
+    .bind(&patched.description)
+    .bind(&patched.status)
+    .bind(&patched.priority)
+    .bind(patched.assigned_to)
+    .bind(patched.due_date)
+    .bind(completed_at)
+    .bind(&patched.labels)
+    .bind(patched.parent_id)
+    .bind(patched.estimated_minutes)
+    .bind(patched.project_id)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let entry = AuditRepository::log_batch_action(
+        conn,
+        actor.batch_id,
+        actor.user_id,
+        "UPDATE",
+        "task",
+        id,
+        Some(existing.snapshot()),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    PropagationRepository::propagate_in(
+        conn,
+        actor.propagation,
+        actor.user_id,
+        Some(actor.batch_id),
+        &entry,
+        Some(&existing.status),
+        &task,
+    )
+    .await?;
+
+    Ok(Ok(Some(task)))
+}
+
+async fn delete_item(conn: &mut PgConnection, actor: &Actor, id: Uuid) -> Result<ItemResult> {
+    let Some(existing) = lock_task(conn, actor, id).await? else {
+        return Ok(Err(ItemFailure::NotFound));
+    };
+    if !actor.can_delete(&existing) {
+        return Ok(Err(ItemFailure::Forbidden));
+    }
+
+    sqlx::query("UPDATE tasks SET deleted_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    AuditRepository::log_batch_action(
+        conn,
+        actor.batch_id,
+        actor.user_id,
+        "DELETE",
+        "task",
+        id,
+        Some(existing.live_snapshot()),
+        None,
+    )
+    .await?;
+
+    Ok(Ok(None))
+}
+
+async fn relabel_item(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    id: Uuid,
+    add: &[String],
+    remove: &[String],
+    set: Option<&[String]>,
+) -> Result<ItemResult> {
+    let Some(existing) = lock_task(conn, actor, id).await? else {
+        return Ok(Err(ItemFailure::NotFound));
+    };
+    if !actor.can_edit(&existing) {
+        return Ok(Err(ItemFailure::Forbidden));
+    }
+
+    let mut labels = set.map(<[String]>::to_vec).unwrap_or_else(|| existing.labels.clone());
+    labels.extend(add.iter().cloned());
+    let remove = normalize_labels(remove.to_vec());
+    let labels: Vec<String> =
+        normalize_labels(labels).into_iter().filter(|label| !remove.contains(label)).collect();
+
+    if labels == existing.labels {
+        return Ok(Ok(Some(existing)));
+    }
+
+    let task = sqlx::query_as::<_, Task>(
+        "UPDATE tasks SET labels = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
+    )
+    .bind(&labels)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    AuditRepository::log_batch_action(
+        conn,
+        actor.batch_id,
+        actor.user_id,
+        "UPDATE",
+        "task",
+        id,
+        Some(existing.snapshot()),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    Ok(Ok(Some(task)))
+}
@@ -0,0 +1,236 @@
+use crate::{
+    database::calendar_repo::CalendarRepository,
+    error::{AppError, Result},
+    models::{CalendarFeed, CalendarFeedWithToken, CalendarQuery},
+    state::AppState,
+    utils::{ical::ICalWriter, jwt::Claims, rrule::RRule, token::random_token},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+
+// Closed tasks stay in the feed this long, so they show as done rather than vanish
+const CLOSED_DAYS: i64 = 30;
+// Occurrences listed per recurring series at most
+const MAX_OCCURRENCES: usize = 100;
+
+#[derive(Clone, Copy)]
+enum Component {
+    Event,
+    Todo,
+}
+
+// Create the caller's calendar feed, or replace its token: the old URL stops
+// working. The token is only shown here.
+pub async fn create_token(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<CalendarFeedWithToken>> {
+    let token = random_token("cal_");
+
+    let repo = CalendarRepository::new(state.pool);
+    let feed = repo.set_token(claims.user_id()?, &token).await?;
+    let path = format!("/calendar/{}.ics", token);
+
+    Ok(Json(CalendarFeedWithToken { feed, token, path }))
+}
+
+pub async fn get_token(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<CalendarFeed>> {
+    let repo = CalendarRepository::new(state.pool);
+    let feed = repo.get(claims.user_id()?).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(feed))
+}
+
+pub async fn revoke_token(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<StatusCode> {
+    let repo = CalendarRepository::new(state.pool);
+    if !repo.delete(claims.user_id()?).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /calendar/{token}.ics: the due dates of the token owner's tasks, and of the
+// coming occurrences of their recurring tasks (?project_id=, ?label=,
+// ?component=event|todo). The token is the authentication.
+pub async fn calendar_feed(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+    Query(params): Query<CalendarQuery>,
+) -> Result<Response> {
+    let token = file.strip_suffix(".ics").ok_or(AppError::NotFound)?;
+    let component = match params.component.as_deref() {
+        None | Some("event") => Component::Event,
+        Some("todo") => Component::Todo,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown component '{}': expected event or todo",
+                other
+            )));
+        }
+    };
+
+    let repo = CalendarRepository::new(state.pool);
+    let owner = repo.open(token).await?.ok_or(AppError::NotFound)?;
+    let tasks = repo.tasks(&owner, &params, CLOSED_DAYS).await?;
+    let series = repo.series(&owner, &params).await?;
+
+    let now = Utc::now();
+    let horizon = now + Duration::days(state.config.calendar_horizon_days);
+
+    let mut calendar = ICalWriter::default();
+    calendar
+        .begin("VCALENDAR")
+        .property("VERSION", "2.0")
+        .property("PRODID", "-//task_api//Tasks//EN")
+        .property("CALSCALE", "GREGORIAN")
+        .property("METHOD", "PUBLISH")
+        .text("X-WR-CALNAME", &format!("Tasks of {}", owner.email))
+        .property("X-PUBLISHED-TTL", "PT15M")
+        .property("REFRESH-INTERVAL;VALUE=DURATION", "PT15M");
+
+    for task in &tasks {
+        let Some(due) = task.due_date else { continue };
+        write_entry(
+            &mut calendar,
+            component,
+            now,
+            &Entry {
+                uid: format!("{}@task-api", task.id),
+                summary: &task.title,
+                description: task.description.as_deref(),
+                due,
+                status: &task.status,
+                priority: &task.priority,
+                labels: &task.labels,
+                completed_at: task.completed_at,
+                last_modified: task.updated_at.unwrap_or(now),
+                sequence: task.version,
+            },
+        );
+    }
+
+    // Occurrences that do not exist yet; once one is created it is listed as a task
+    for series in &series {
+        let rule: RRule = series.rrule.parse()?;
+        let upcoming = rule
+            .occurrences(series.dtstart)
+            .enumerate()
+            .skip(series.next_occurrence as usize - 1)
+            .skip_while(|(_, due)| *due <= now)
+            .take_while(|(_, due)| *due <= horizon)
+            .take(MAX_OCCURRENCES);
+        let labels = if series.copy_labels { series.labels.clone() } else { Vec::new() };
+        for (index, due) in upcoming {
+            write_entry(
+                &mut calendar,
+                component,
+                now,
+                &Entry {
+                    uid: format!("{}-{}@task-api", series.id, index + 1),
+                    summary: &series.title,
+                    description: series.description.as_deref(),
+                    due,
+                    status: "pending",
+                    priority: &series.priority,
+                    labels: &labels,
+                    completed_at: None,
+                    last_modified: series.updated_at,
+                    sequence: 0,
+                },
+            );
+        }
+    }
+
+    calendar.end("VCALENDAR");
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "inline; filename=\"tasks.ics\""),
+            (header::CACHE_CONTROL, "private, max-age=300"),
+        ],
+        calendar.finish(),
+    )
+        .into_response())
+}
+
+// One due date, from a task or a future occurrence
+struct Entry<'a> {
+    uid: String,
+    summary: &'a str,
+    description: Option<&'a str>,
+    due: DateTime<Utc>,
+    status: &'a str,
+    priority: &'a str,
+    labels: &'a [String],
+    completed_at: Option<DateTime<Utc>>,
+    last_modified: DateTime<Utc>,
+    sequence: i32,
+}
+
+// Events are zero-length (DTSTART only) and do not block time; to-dos carry the
+// task's status, priority and completion
+fn write_entry(calendar: &mut ICalWriter, component: Component, now: DateTime<Utc>, entry: &Entry) {
+    let name = match component {
+        Component::Event => "VEVENT",
+        Component::Todo => "VTODO",
+    };
+    calendar
+        .begin(name)
+        .text("UID", &entry.uid)
+        .time("DTSTAMP", now)
+        .time("LAST-MODIFIED", entry.last_modified)
+        .property("SEQUENCE", &entry.sequence.to_string())
+        .text("SUMMARY", entry.summary);
+    if let Some(description) = entry.description {
+        calendar.text("DESCRIPTION", description);
+    }
+    if !entry.labels.is_empty() {
+        calendar.list("CATEGORIES", entry.labels);
+    }
+
+    match component {
+        Component::Event => {
+            let status = if entry.status == "cancelled" { "CANCELLED" } else { "CONFIRMED" };
+            calendar
+                .time("DTSTART", entry.due)
+                .property("STATUS", status)
+                .property("TRANSP", "TRANSPARENT");
+        }
+        Component::Todo => {
+            let status = match entry.status {
+                "in_progress" => "IN-PROCESS",
+                "completed" => "COMPLETED",
+                "cancelled" => "CANCELLED",
+                _ => "NEEDS-ACTION",
+            };
+            let priority = match entry.priority {
+                "urgent" => "1",
+                "high" => "3",
+                "low" => "9",
+                _ => "5",
+            };
+            calendar
+                .time("DUE", entry.due)
+                .property("STATUS", status)
+                .property("PRIORITY", priority);
+            if let Some(completed_at) = entry.completed_at {
+                calendar.time("COMPLETED", completed_at);
+            }
+        }
+    }
+
+    calendar.end(name);
+}
@@ -1,40 +1,58 @@
-use std::collections::{HashSet, VecDeque};
 use crate::{
-    database::dependency_repo::DependencyRepository,
+    database::{
+        dependency_repo::{DependencyRepository, TreeDirection},
+        project_repo::ProjectRepository,
+    },
     error::Result,
-    models::{AddDependencyRequest, DependencyInfo, TaskDependency},
+    handlers::tasks::{ensure_task_editable, ensure_task_visible},
+    models::{
+        AddDependencyRequest, DependencyInfo, DependencyTree, DependencyTreeQuery, GraphFormat,
+        GraphQuery, ReadyQuery, ReadyTask, ScheduleResponse, Task, TaskDependency,
+    },
     state::AppState,
-    utils::jwt::Claims,
+    utils::{graph_export, jwt::Claims, ready, schedule},
 };
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
     {Extension, Json},
 };
+use chrono::Utc;
 use uuid::Uuid;
 
+// How many links deep the transitive endpoints go by default, and at most
+const DEFAULT_TREE_DEPTH: i32 = 10;
+const MAX_TREE_DEPTH: i32 = 50;
+
+// Largest graph `/api/graph` renders
+const MAX_GRAPH_NODES: i64 = 5000;
+
 // Add a dependency
 pub async fn add_dependency(
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(task_id): Path<Uuid>,
     Json(payload): Json<AddDependencyRequest>,
 ) -> Result<Json<TaskDependency>> {
-    let repo = DependencyRepository::new(state.pool);
+    ensure_task_editable(&state, &claims, task_id).await?;
+    ensure_task_visible(&state, &claims, payload.depends_on).await?;
 
-    // TODO! Verify the user owns the task or is admin
-
-    let dependency = repo.add_dependency(task_id, payload.depends_on).await?;
+    let repo = DependencyRepository::new(state.pool);
+    let dependency =
+        repo.add_dependency(task_id, payload.depends_on, payload.kind, payload.lag_minutes).await?;
 
     Ok(Json(dependency))
 }
 
 // Remove a dependency
 pub async fn remove_dependency(
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path((task_id, depends_on)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode> {
+    ensure_task_editable(&state, &claims, task_id).await?;
+
     let repo = DependencyRepository::new(state.pool);
     repo.remove_dependency(task_id, depends_on).await?;
 
@@ -43,58 +61,156 @@ pub async fn remove_dependency(
 
 // Get all dependencies for a task
 pub async fn get_dependencies(
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(task_id): Path<Uuid>,
 ) -> Result<Json<Vec<DependencyInfo>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
     let repo = DependencyRepository::new(state.pool);
-    let dependencies = repo.get_dependencies(task_id).await?;
+    let dependencies =
+        repo.get_dependencies(task_id, claims.user_id()?, claims.role == "admin").await?;
 
     Ok(Json(dependencies))
 }
 
 // Get blocked tasks (tasks that depend on this one)
 pub async fn get_blocked_tasks(
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(task_id): Path<Uuid>,
 ) -> Result<Json<Vec<Uuid>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
     let repo = DependencyRepository::new(state.pool);
-    let blocked = repo.get_blocked_tasks(task_id).await?;
+    let blocked =
+        repo.get_blocked_tasks(task_id, claims.user_id()?, claims.role == "admin").await?;
 
     Ok(Json(blocked))
 }
 
-// Get all transitive dependencies (everything this task depends on, directly or indirectly)
+// Get all transitive dependencies (everything blocking this task, directly or indirectly)
 pub async fn get_all_dependencies(
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(task_id): Path<Uuid>,
-) -> Result<Json<Vec<Uuid>>> {
-    // BFS to find all transitive dependencies
-    let mut all_deps = HashSet::new();
-    let mut queue = VecDeque::new();
-    queue.push_back(task_id);
-
-    while let Some(current) = queue.pop_front() {
-        // Get direct dependencies
-        let deps = sqlx::query_scalar::<_, Uuid>(
-            "SELECT depends_on FROM task_dependencies WHERE task_id = $1"
+    Query(params): Query<DependencyTreeQuery>,
+) -> Result<Json<DependencyTree>> {
+    let repo = DependencyRepository::new(state.pool);
+    let tree = repo
+        .get_dependency_tree(
+            task_id,
+            TreeDirection::Upstream,
+            tree_depth(params.max_depth),
+            claims.user_id()?,
+            claims.role == "admin",
         )
-            .bind(current)
-            .fetch_all(&state.pool)
-            .await?;
+        .await?;
+
+    Ok(Json(tree))
+}
+
+// Get everything this task blocks, directly or indirectly
+pub async fn get_all_blocked_tasks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+    Query(params): Query<DependencyTreeQuery>,
+) -> Result<Json<DependencyTree>> {
+    let repo = DependencyRepository::new(state.pool);
+    let tree = repo
+        .get_dependency_tree(
+            task_id,
+            TreeDirection::Downstream,
+            tree_depth(params.max_depth),
+            claims.user_id()?,
+            claims.role == "admin",
+        )
+        .await?;
+
+    Ok(Json(tree))
+}
+
+fn tree_depth(max_depth: Option<i32>) -> i32 {
+    max_depth.unwrap_or(DEFAULT_TREE_DEPTH).clamp(1, MAX_TREE_DEPTH)
+}
+
+// Critical path schedule for a task and everything it transitively waits for
+pub async fn get_schedule(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<ScheduleResponse>> {
+    let repo = DependencyRepository::new(state.pool);
+    let (tasks, links) =
+        repo.get_schedule_graph(task_id, claims.user_id()?, claims.role == "admin").await?;
+    let schedule = schedule::compute(task_id, Utc::now(), tasks, links)?;
+
+    Ok(Json(schedule))
+}
+
+// What the caller can work on next
+pub async fn get_ready_tasks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<ReadyQuery>,
+) -> Result<Json<Vec<ReadyTask>>> {
+    let repo = DependencyRepository::new(state.pool);
+    let limit = params.limit.unwrap_or(50).clamp(1, 500) as usize;
+    let ranked = repo.get_ready_tasks(claims.user_id()?, params.project_id).await?;
+
+    Ok(Json(ready::order(ranked, limit)))
+}
+
+// Tasks that become ready when this one is completed
+pub async fn get_unblocks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<Task>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
 
-        for dep in deps {
-            if all_deps.insert(dep) {
-                // New dependency found, explore its dependencies
-                queue.push_back(dep);
-            }
-        }
+    let repo = DependencyRepository::new(state.pool);
+    let tasks = repo.get_unblocked_by(task_id, claims.user_id()?, claims.role == "admin").await?;
+
+    Ok(Json(tasks))
+}
+
+// Export the dependency graph (or the part selected by the query) for rendering
+pub async fn get_graph(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<GraphQuery>,
+) -> Result<Response> {
+    let user_id = claims.user_id()?;
+    let is_admin = claims.role == "admin";
+    if let Some(project_id) = params.project_id {
+        ProjectRepository::new(state.pool.clone())
+            .check_task_project(project_id, user_id, is_admin)
+            .await?;
     }
+    if let Some(root) = params.root {
+        ensure_task_visible(&state, &claims, root).await?;
+    }
+
+    let repo = DependencyRepository::new(state.pool);
+    let (nodes, edges) = repo
+        .get_graph(&params, user_id, is_admin, tree_depth(params.max_depth), MAX_GRAPH_NODES)
+        .await?;
 
-    // Remove the task itself if it's in there
-    all_deps.remove(&task_id);
+    let response = match params.format {
+        GraphFormat::Dot => (
+            [(header::CONTENT_TYPE, "text/vnd.graphviz; charset=utf-8")],
+            graph_export::to_dot(&nodes, &edges),
+        )
+            .into_response(),
+        GraphFormat::Mermaid => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            graph_export::to_mermaid(&nodes, &edges),
+        )
+            .into_response(),
+        GraphFormat::Json => Json(graph_export::to_json_graph(&nodes, &edges)).into_response(),
+    };
 
-    Ok(Json(all_deps.into_iter().collect()))
-}
\ No newline at end of file
+    Ok(response)
+}
@@ -0,0 +1,323 @@
+use crate::{
+    database::{
+        event_repo::EventRepository, project_repo::ProjectRepository,
+        watcher_repo::WatcherRepository,
+    },
+    error::Result,
+    models::{EventStreamQuery, TaskEvent},
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{
+    Extension,
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{Stream, stream};
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    convert::Infallible,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use uuid::Uuid;
+
+// Events read from the table at a time when a client resumes or falls behind
+const CATCH_UP_BATCH: i64 = 500;
+// Ids are handed out before commit, so an event can commit after others with higher
+// ids. Reads from the table start this far below the highest id seen, and events
+// within that window are told apart by id, so late ones are not skipped.
+const LOOKBACK_IDS: i64 = 128;
+// How long a connection trusts the project memberships it loaded
+const MEMBERSHIP_TTL: Duration = Duration::from_secs(30);
+
+// Stream task and dependency changes the caller is allowed to see (with
+// `?watching=true`, only those of tasks they watch). Clients resume with
+// `Last-Event-ID` (sent automatically by EventSource on reconnect) or
+// `?last_event_id=`, as long as the events are still retained.
+pub async fn stream_events(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<EventStreamQuery>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    // Subscribe before reading the backlog, so nothing falls between the two
+    let receiver = state.events.subscribe();
+
+    let projects = ProjectRepository::new(state.pool.clone());
+    let visibility = Visibility::load(&projects, &claims).await?;
+    let watched = if params.watching {
+        let repo = WatcherRepository::new(state.pool.clone());
+        Some(WatchedTasks::load(&repo, claims.user_id()?).await?)
+    } else {
+        None
+    };
+
+    let resume_from = headers
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .or(params.last_event_id);
+
+    let events = EventRepository::new(state.pool.clone());
+    let seen = match resume_from {
+        Some(resume_from) => stored_up_to(&events, resume_from).await?,
+        None => BTreeSet::new(),
+    };
+
+    let subscription = Subscription {
+        receiver,
+        events,
+        projects,
+        visibility,
+        watched,
+        backlog: VecDeque::new(),
+        catch_up_from: resume_from.map(|id| id - LOOKBACK_IDS),
+        seen,
+        highest: resume_from.unwrap_or(0),
+    };
+
+    let stream = stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription.next().await?;
+        Some((Ok(event), subscription))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+// The ids within LOOKBACK_IDS up to `resume_from` that the table already holds. The
+// client got those before it went away; ones that commit later are still new to it.
+async fn stored_up_to(events: &EventRepository, resume_from: i64) -> Result<BTreeSet<i64>> {
+    let window = events.list_after(resume_from - LOOKBACK_IDS, LOOKBACK_IDS).await?;
+    Ok(window.into_iter().map(|event| event.id).filter(|id| *id <= resume_from).collect())
+}
+
+// Tasks outside any project are visible to everyone, project tasks to the
+// project's members; admins see everything
+pub(crate) struct Visibility {
+    user_id: Uuid,
+    is_admin: bool,
+    projects: HashSet<Uuid>,
+    loaded_at: Instant,
+}
+
+impl Visibility {
+    pub(crate) async fn load(repo: &ProjectRepository, claims: &Claims) -> Result<Self> {
+        let user_id = claims.user_id()?;
+        Ok(Self {
+            user_id,
+            is_admin: claims.role == "admin",
+            projects: repo.member_project_ids(user_id).await?.into_iter().collect(),
+            loaded_at: Instant::now(),
+        })
+    }
+
+    // Whether a task in `project_id` (None: outside any project) may be seen
+    pub(crate) async fn allows(
+        &mut self,
+        repo: &ProjectRepository,
+        project_id: Option<Uuid>,
+    ) -> Result<bool> {
+        let Some(project_id) = project_id else {
+            return Ok(true);
+        };
+        if self.is_admin {
+            return Ok(true);
+        }
+        if self.loaded_at.elapsed() > MEMBERSHIP_TTL {
+            self.projects = repo.member_project_ids(self.user_id).await?.into_iter().collect();
+            self.loaded_at = Instant::now();
+        }
+
+        Ok(self.projects.contains(&project_id))
+    }
+}
+
+// The tasks a user watches, kept up to date from their watcher.added and
+// watcher.removed events
+pub(crate) struct WatchedTasks {
+    user_id: Uuid,
+    tasks: HashSet<Uuid>,
+}
+
+impl WatchedTasks {
+    pub(crate) async fn load(repo: &WatcherRepository, user_id: Uuid) -> Result<Self> {
+        Ok(Self { user_id, tasks: repo.watched_task_ids(user_id).await?.into_iter().collect() })
+    }
+
+    // Whether the event is about a watched task. The user's own (un)watching counts
+    // as such, so they see it happen.
+    pub(crate) fn observe(&mut self, event: &TaskEvent) -> bool {
+        let watched = self.tasks.contains(&event.task_id);
+        let own = event.payload.get("user_id").and_then(|id| id.as_str())
+            == Some(self.user_id.to_string().as_str());
+
+        match event.event_type.as_str() {
+            "watcher.added" if own => {
+                self.tasks.insert(event.task_id);
+                true
+            }
+            "watcher.removed" if own => {
+                self.tasks.remove(&event.task_id);
+                true
+            }
+            _ => watched,
+        }
+    }
+}
+
+struct Subscription {
+    receiver: Receiver<TaskEvent>,
+    events: EventRepository,
+    projects: ProjectRepository,
+    visibility: Visibility,
+    // Set with ?watching=true
+    watched: Option<WatchedTasks>,
+    // Events read from the table, sent before anything from the channel
+    backlog: VecDeque<TaskEvent>,
+    // Set while the client is behind: read the table after this id
+    catch_up_from: Option<i64>,
+    // Ids already handled, down to LOOKBACK_IDS below the highest; the channel
+    // repeats events read from the table
+    seen: BTreeSet<i64>,
+    highest: i64,
+}
+
+impl Subscription {
+    // The next SSE event for this client, or None to close the stream
+    async fn next(&mut self) -> Option<Event> {
+        loop {
+            let event = match self.next_task_event().await {
+                Ok(Some(event)) => event,
+                Ok(None) => return None,
+                Err(e) => {
+                    tracing::error!("Task event stream failed: {:?}", e);
+                    return None;
+                }
+            };
+            match self.visibility.allows(&self.projects, event.project_id).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::error!("Task event stream failed: {:?}", e);
+                    return None;
+                }
+            }
+            if let Some(watched) = &mut self.watched
+                && !watched.observe(&event)
+            {
+                continue;
+            }
+
+            match Event::default()
+                .id(event.id.to_string())
+                .event(&event.event_type)
+                .json_data(&event)
+            {
+                Ok(sse_event) => return Some(sse_event),
+                Err(e) => tracing::error!("Cannot encode task event {}: {:?}", event.id, e),
+            }
+        }
+    }
+
+    async fn next_task_event(&mut self) -> Result<Option<TaskEvent>> {
+        loop {
+            if let Some(event) = self.backlog.pop_front() {
+                if self.first_time(event.id) {
+                    return Ok(Some(event));
+                }
+                continue;
+            }
+
+            if let Some(after_id) = self.catch_up_from {
+                let events = self.events.list_after(after_id, CATCH_UP_BATCH).await?;
+                self.catch_up_from = match events.last() {
+                    Some(last) if events.len() as i64 == CATCH_UP_BATCH => Some(last.id),
+                    _ => None,
+                };
+                self.backlog.extend(events);
+                continue;
+            }
+
+            match self.receiver.recv().await {
+                Ok(event) if self.first_time(event.id) => return Ok(Some(event)),
+                // Already sent from the table
+                Ok(_) => continue,
+                // Dropped events are still in the table
+                Err(RecvError::Lagged(_)) => self.catch_up_from = Some(self.highest - LOOKBACK_IDS),
+                Err(RecvError::Closed) => return Ok(None),
+            }
+        }
+    }
+
+    // Record an event id; false if it was handled already. Ids below the window are
+    // no longer read from the table, so one arriving from the channel is new.
+    fn first_time(&mut self, id: i64) -> bool {
+        if !self.seen.insert(id) {
+            return false;
+        }
+        if id > self.highest {
+            self.highest = id;
+            self.seen = self.seen.split_off(&(self.highest - LOOKBACK_IDS));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+    use tokio::sync::broadcast;
+
+    async fn record(pool: &PgPool, id: i64) {
+        sqlx::query(
+            r#"
+            INSERT INTO task_events (id, event_type, task_id, payload)
+            VALUES ($1, 'task.updated', gen_random_uuid(), '{}')
+            "#,
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn resume_sends_only_what_the_client_has_not_seen(pool: PgPool) {
+        for id in [1, 3, 5] {
+            record(&pool, id).await;
+        }
+
+        // The client saw 1 and 3 and resumes from 3
+        let events = EventRepository::new(pool.clone());
+        let seen = stored_up_to(&events, 3).await.unwrap();
+        let (_sender, receiver) = broadcast::channel(16);
+        let mut subscription = Subscription {
+            receiver,
+            events,
+            projects: ProjectRepository::new(pool.clone()),
+            visibility: Visibility {
+                user_id: Uuid::nil(),
+                is_admin: true,
+                projects: HashSet::new(),
+                loaded_at: Instant::now(),
+            },
+            watched: None,
+            backlog: VecDeque::new(),
+            catch_up_from: Some(3 - LOOKBACK_IDS),
+            seen,
+            highest: 3,
+        };
+
+        // 2 commits late, after the client went away
+        record(&pool, 2).await;
+
+        let mut sent = Vec::new();
+        for _ in 0..2 {
+            sent.push(subscription.next_task_event().await.unwrap().unwrap().id);
+        }
+        assert_eq!(sent, vec![2, 5]);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    database::job_repo::JobRepository,
+    error::{AppError, Result},
+    models::{JOB_STATUSES, JobQuery, JobRecord, JobSchedule, JobStats},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use uuid::Uuid;
+
+// Background jobs, newest first (?status=queued|running|succeeded|dead, ?kind=, ?limit=)
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Query(params): Query<JobQuery>,
+) -> Result<Json<Vec<JobRecord>>> {
+    if let Some(status) = params.status.as_deref()
+        && !JOB_STATUSES.contains(&status)
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid status '{}': expected one of {}",
+            status,
+            JOB_STATUSES.join(", ")
+        )));
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    let repo = JobRepository::new(state.pool);
+    let jobs = repo.list(&params, limit).await?;
+
+    Ok(Json(jobs))
+}
+
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<JobRecord>> {
+    let repo = JobRepository::new(state.pool);
+    let job = repo.get(id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(job))
+}
+
+// Run a dead job again with fresh attempts, or a queued one waiting for a retry now
+pub async fn retry_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<JobRecord>> {
+    let repo = JobRepository::new(state.pool);
+    let job = repo.retry(id).await?;
+
+    Ok(Json(job))
+}
+
+// Job counts per kind and status
+pub async fn get_job_stats(State(state): State<AppState>) -> Result<Json<Vec<JobStats>>> {
+    let repo = JobRepository::new(state.pool);
+    let stats = repo.stats().await?;
+
+    Ok(Json(stats))
+}
+
+// Recurring jobs and when they run next
+pub async fn list_job_schedules(State(state): State<AppState>) -> Result<Json<Vec<JobSchedule>>> {
+    let repo = JobRepository::new(state.pool);
+    let schedules = repo.list_schedules().await?;
+
+    Ok(Json(schedules))
+}
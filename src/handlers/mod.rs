@@ -1,5 +1,17 @@
 pub(crate) mod audit;
 pub mod auth;
+pub mod bulk;
+pub mod calendar;
 pub mod dependencies;
+pub mod events;
 pub mod health;
+pub mod jobs;
+pub mod notifications;
+pub mod projects;
+pub mod recurrence;
+pub mod subtasks;
 pub mod tasks;
+pub mod time;
+pub mod watchers;
+pub mod webhooks;
+pub mod ws;
@@ -0,0 +1,129 @@
+use crate::{
+    database::notification_repo::NotificationRepository,
+    error::{AppError, Result},
+    models::{
+        NOTIFICATION_KINDS, Notification, NotificationPreferences, NotificationQuery,
+        ReminderSettings,
+    },
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+};
+use serde_json::{Value, json};
+use uuid::Uuid;
+use validator::Validate;
+
+// The caller's notifications, newest first (?unread=true, ?kind=, ?before=, ?limit=)
+pub async fn list_notifications(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<NotificationQuery>,
+) -> Result<Json<Vec<Notification>>> {
+    if let Some(kind) = params.kind.as_deref() {
+        check_kind(kind)?;
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+
+    let repo = NotificationRepository::new(state.pool);
+    let notifications = repo.list(claims.user_id()?, &params, limit).await?;
+
+    Ok(Json(notifications))
+}
+
+pub async fn get_unread_count(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>> {
+    let repo = NotificationRepository::new(state.pool);
+    let unread = repo.unread_count(claims.user_id()?).await?;
+
+    Ok(Json(json!({ "unread": unread })))
+}
+
+pub async fn mark_read(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Notification>> {
+    let repo = NotificationRepository::new(state.pool);
+    let notification = repo.mark_read(claims.user_id()?, id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(notification))
+}
+
+pub async fn mark_all_read(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Value>> {
+    let repo = NotificationRepository::new(state.pool);
+    let marked = repo.mark_all_read(claims.user_id()?).await?;
+
+    Ok(Json(json!({ "marked_read": marked })))
+}
+
+// Which kinds of notification the caller gets
+pub async fn get_preferences(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<NotificationPreferences>> {
+    let repo = NotificationRepository::new(state.pool);
+    let preferences = repo.get_preferences(claims.user_id()?).await?;
+
+    Ok(Json(preferences))
+}
+
+// Turn kinds on or off; kinds left out keep their setting
+pub async fn update_preferences(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Json(payload): Json<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>> {
+    for kind in payload.keys() {
+        check_kind(kind)?;
+    }
+
+    let repo = NotificationRepository::new(state.pool);
+    let preferences = repo.set_preferences(claims.user_id()?, &payload).await?;
+
+    Ok(Json(preferences))
+}
+
+// How long before a due date the caller is reminded
+pub async fn get_reminders(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<ReminderSettings>> {
+    let repo = NotificationRepository::new(state.pool);
+    let settings = repo
+        .get_reminder_settings(claims.user_id()?, state.config.notifications.due_soon_hours)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+pub async fn update_reminders(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Json(payload): Json<ReminderSettings>,
+) -> Result<Json<ReminderSettings>> {
+    payload.validate()?;
+
+    let repo = NotificationRepository::new(state.pool);
+    let settings = repo.set_reminder_settings(claims.user_id()?, &payload).await?;
+
+    Ok(Json(settings))
+}
+
+fn check_kind(kind: &str) -> Result<()> {
+    if !NOTIFICATION_KINDS.contains(&kind) {
+        return Err(AppError::BadRequest(format!(
+            "Unknown notification kind '{}': expected one of {}",
+            kind,
+            NOTIFICATION_KINDS.join(", ")
+        )));
+    }
+    Ok(())
+}
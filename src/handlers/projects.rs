@@ -0,0 +1,112 @@
+use crate::{
+    database::project_repo::ProjectRepository,
+    error::{AppError, Result},
+    models::{AddMemberRequest, CreateProjectRequest, Project, ProjectMember},
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+// Create a project (the caller becomes its owner)
+pub async fn create_project(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Json(payload): Json<CreateProjectRequest>,
+) -> Result<Json<Project>> {
+    payload.validate()?;
+
+    let repo = ProjectRepository::new(state.pool);
+    let project =
+        repo.create(claims.user_id()?, &payload.name, payload.description.as_deref()).await?;
+
+    Ok(Json(project))
+}
+
+// List the projects the caller is a member of
+pub async fn list_projects(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Project>>> {
+    let repo = ProjectRepository::new(state.pool);
+    let projects = repo.list_for_user(claims.user_id()?).await?;
+
+    Ok(Json(projects))
+}
+
+// Get a project (members and admins only)
+pub async fn get_project(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Project>> {
+    let repo = ProjectRepository::new(state.pool);
+    let project = repo.get(project_id).await?.ok_or(AppError::NotFound)?;
+
+    if claims.role != "admin" && !repo.is_member(project_id, claims.user_id()?).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(Json(project))
+}
+
+pub async fn list_members(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<ProjectMember>>> {
+    let repo = ProjectRepository::new(state.pool);
+
+    if claims.role != "admin" && !repo.is_member(project_id, claims.user_id()?).await? {
+        return Err(AppError::NotFound);
+    }
+
+    let members = repo.list_members(project_id).await?;
+
+    Ok(Json(members))
+}
+
+// Add a member (project owner or admin)
+pub async fn add_member(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    Json(payload): Json<AddMemberRequest>,
+) -> Result<StatusCode> {
+    let repo = ProjectRepository::new(state.pool);
+    ensure_owner(&repo, &claims, project_id).await?;
+
+    repo.add_member(project_id, payload.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Remove a member (project owner or admin)
+pub async fn remove_member(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path((project_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    let repo = ProjectRepository::new(state.pool);
+    ensure_owner(&repo, &claims, project_id).await?;
+
+    repo.remove_member(project_id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn ensure_owner(repo: &ProjectRepository, claims: &Claims, project_id: Uuid) -> Result<()> {
+    let project = repo.get(project_id).await?.ok_or(AppError::NotFound)?;
+
+    if claims.role != "admin" && project.owner_id != claims.user_id()? {
+        return Err(AppError::Unauthorized(
+            "Only the project owner can manage members".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::{
+    database::{project_repo::ProjectRepository, recurrence_repo::RecurrenceRepository},
+    error::{AppError, Result},
+    models::{
+        CreateRecurrenceRequest, GENERATE_MODES, SeriesDetail, SeriesQuery, Task, TaskSeries,
+        UpdateRecurrenceRequest,
+    },
+    state::AppState,
+    utils::{jwt::Claims, rrule::RRule},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+};
+use serde_json::{Value, json};
+use uuid::Uuid;
+use validator::Validate;
+
+// How many upcoming due dates a series shows
+const UPCOMING: usize = 5;
+
+// Make a task recurring: it becomes the first occurrence and the template of a new
+// series, counted from its due date
+pub async fn create_recurrence(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<CreateRecurrenceRequest>,
+) -> Result<Json<TaskSeries>> {
+    payload.validate()?;
+    if let Some(generate) = payload.generate.as_deref() {
+        check_generate(generate)?;
+    }
+    let rule: RRule = payload.rrule.parse()?;
+
+    let task = load_editable_task(&state, &claims, task_id).await?;
+    let dtstart = task.due_date.ok_or_else(|| {
+        AppError::BadRequest("A recurring task needs a due date to count from".to_string())
+    })?;
+
+    let repo = RecurrenceRepository::new(state.pool);
+    if repo.find_by_task(task_id).await?.is_some() {
+        return Err(AppError::Conflict("The task already belongs to a series".to_string()));
+    }
+    let series = repo.create(&task, dtstart, &rule, &payload, claims.user_id()?).await?;
+
+    Ok(Json(series))
+}
+
+// The series of a task, with its occurrences
+pub async fn get_task_recurrence(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<SeriesDetail>> {
+    let repo = RecurrenceRepository::new(state.pool.clone());
+    let (series, _) = repo.find_by_task(task_id).await?.ok_or(AppError::NotFound)?;
+    ensure_series_visible(&state, &claims, &series).await?;
+
+    Ok(Json(series_detail(&repo, series).await?))
+}
+
+// Edit this occurrence and all future ones: the rule, the generation settings and
+// the template, whose fields also go to the open occurrences from this one on
+pub async fn update_recurrence(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<UpdateRecurrenceRequest>,
+) -> Result<Json<Value>> {
+    payload.validate()?;
+    if let Some(generate) = payload.generate.as_deref() {
+        check_generate(generate)?;
+    }
+    let rule = payload.rrule.as_deref().map(str::parse::<RRule>).transpose()?;
+
+    load_editable_task(&state, &claims, task_id).await?;
+
+    let repo = RecurrenceRepository::new(state.pool);
+    let (series, occurrence) = repo.find_by_task(task_id).await?.ok_or(AppError::NotFound)?;
+    let (series, tasks) =
+        repo.update_from(series.id, occurrence, rule.as_ref(), &payload, claims.user_id()?).await?;
+
+    Ok(Json(json!({ "series": series, "updated_tasks": tasks })))
+}
+
+// Series the caller can see (?project_id=, ?include_ended=true)
+pub async fn list_recurrences(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<SeriesQuery>,
+) -> Result<Json<Vec<TaskSeries>>> {
+    let repo = RecurrenceRepository::new(state.pool);
+    let series = repo.list(claims.user_id()?, claims.role == "admin", &params).await?;
+
+    Ok(Json(series))
+}
+
+pub async fn get_recurrence(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SeriesDetail>> {
+    let repo = RecurrenceRepository::new(state.pool.clone());
+    let series = repo.get(id).await?.ok_or(AppError::NotFound)?;
+    ensure_series_visible(&state, &claims, &series).await?;
+
+    Ok(Json(series_detail(&repo, series).await?))
+}
+
+// Stop a series; its existing occurrences stay as they are
+pub async fn stop_recurrence(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TaskSeries>> {
+    let repo = RecurrenceRepository::new(state.pool.clone());
+    let series = repo.get(id).await?.ok_or(AppError::NotFound)?;
+    ensure_series_visible(&state, &claims, &series).await?;
+
+    // Same rule as editing a task: admins, the creator and the assignee
+    let user_id = claims.user_id()?;
+    if claims.role != "admin"
+        && series.created_by != Some(user_id)
+        && series.assigned_to != Some(user_id)
+    {
+        return Err(AppError::Unauthorized(
+            "Only the creator or assignee of a series can stop it".to_string(),
+        ));
+    }
+
+    let series = repo.stop(id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(series))
+}
+
+async fn series_detail(repo: &RecurrenceRepository, series: TaskSeries) -> Result<SeriesDetail> {
+    let occurrences = repo.occurrences(series.id).await?;
+    let upcoming = match series.next_due_at {
+        Some(_) => {
+            let rule: RRule = series.rrule.parse()?;
+            rule.occurrences(series.dtstart)
+                .skip(series.next_occurrence as usize - 1)
+                .take(UPCOMING)
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Ok(SeriesDetail { series, occurrences, upcoming })
+}
+
+// The task, provided the caller may edit it (admins, its creator and its assignee)
+async fn load_editable_task(state: &AppState, claims: &Claims, task_id: Uuid) -> Result<Task> {
+    let task =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(task_id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(AppError::NotFound)?;
+
+    let user_id = claims.user_id()?;
+    let is_admin = claims.role == "admin";
+    if let Some(project_id) = task.project_id
+        && !is_admin
+        && !ProjectRepository::new(state.pool.clone()).is_member(project_id, user_id).await?
+    {
+        return Err(AppError::NotFound);
+    }
+    if !is_admin && task.created_by != Some(user_id) && task.assigned_to != Some(user_id) {
+        return Err(AppError::Unauthorized(
+            "Only the creator or assignee of a task can change its recurrence".to_string(),
+        ));
+    }
+    Ok(task)
+}
+
+// Project series are only visible to the project's members
+async fn ensure_series_visible(
+    state: &AppState,
+    claims: &Claims,
+    series: &TaskSeries,
+) -> Result<()> {
+    if let Some(project_id) = series.project_id
+        && claims.role != "admin"
+        && !ProjectRepository::new(state.pool.clone())
+            .is_member(project_id, claims.user_id()?)
+            .await?
+    {
+        return Err(AppError::NotFound);
+    }
+    Ok(())
+}
+
+fn check_generate(generate: &str) -> Result<()> {
+    if !GENERATE_MODES.contains(&generate) {
+        return Err(AppError::BadRequest(format!(
+            "Unknown generate mode '{}': expected one of {}",
+            generate,
+            GENERATE_MODES.join(", ")
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::{
+    database::hierarchy_repo::HierarchyRepository,
+    error::Result,
+    handlers::tasks::ensure_task_visible,
+    models::{SubtreeResponse, Task},
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use uuid::Uuid;
+
+// Direct subtasks of a task
+pub async fn get_children(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<Task>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = HierarchyRepository::new(state.pool);
+    let children = repo.get_children(task_id, claims.user_id()?, claims.role == "admin").await?;
+
+    Ok(Json(children))
+}
+
+// Every subtask below a task as a tree, with the share of completed descendants
+// rolled up at each level
+pub async fn get_subtree(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<SubtreeResponse>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = HierarchyRepository::new(state.pool);
+    let (progress, children) =
+        repo.get_subtree(task_id, claims.user_id()?, claims.role == "admin").await?;
+
+    Ok(Json(SubtreeResponse { task_id, progress, children }))
+}
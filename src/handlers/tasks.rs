@@ -1,103 +1,83 @@
 use crate::{
-    database::{audit_repo::AuditRepository, dependency_repo::DependencyRepository},
+    database::{
+        audit_repo::AuditRepository,
+        dependency_repo::DependencyRepository,
+        hierarchy_repo::HierarchyRepository,
+        overdue_repo::OverdueRepository,
+        project_repo::{ProjectRepository, visible_to},
+        propagation_repo::PropagationRepository,
+    },
     error::{AppError, Result},
-    models::{CreateTaskRequest, Task, TaskQuery, UpdateTaskRequest},
+    models::{
+        AsOfQuery, CreateTaskRequest, OverdueQuery, OverdueTask, Task, TaskPatchDocument,
+        TaskQuery, UpdateTaskRequest, normalize_labels,
+    },
     state::AppState,
-    utils::jwt::Claims,
+    utils::{
+        etag::{check_if_match, etag},
+        jwt::Claims,
+    },
 };
 use axum::{
     Extension, Json,
+    body::Bytes,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
 };
-use serde_json::json;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use sqlx::{PgConnection, Postgres, QueryBuilder};
 use uuid::Uuid;
 use validator::Validate;
 
-// List tasks with filters
+// List the tasks the caller can see, with filters
 pub async fn list_tasks(
+    Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Query(params): Query<TaskQuery>,
 ) -> Result<Json<Vec<Task>>> {
-    // Build a dynamic query based on filters
-    let mut query = String::from("SELECT * FROM tasks WHERE 1=1");
-    if params.status.is_some() {
-        query.push_str(" AND status = $1");
-    }
-    if params.priority.is_some() {
-        query.push_str(" AND status = $2");
-    }
-    if params.assigned_to.is_some() {
-        query.push_str(" AND status = $3");
-    }
-    if params.created_by.is_some() {
-        query.push_str(" AND status = $4");
-    }
-    query.push_str(" ORDER BY created_at DESC");
-    let tasks = match (params.status, params.priority, params.assigned_to, params.created_by) {
-        (None, None, None, None) => {
-            // No filters
-            sqlx::query_as::<_, Task>("SELECT * FROM tasks ORDER BY created_at DESC")
-                .fetch_all(&state.pool)
-                .await?
-        }
-        (Some(status), None, None, None) => {
-            // Filter by status only
-            sqlx::query_as::<_, Task>(
-                "SELECT * FROM tasks WHERE status = $1 ORDER BY created_at DESC",
-            )
-            .bind(status)
-            .fetch_all(&state.pool)
-            .await?
-        }
-        (None, Some(priority), None, None) => {
-            // Filter by priority only
-            sqlx::query_as::<_, Task>(
-                "SELECT * FROM tasks WHERE priority = $1 ORDER BY created_at DESC",
-            )
-            .bind(priority)
-            .fetch_all(&state.pool)
-            .await?
-        }
-        (Some(status), Some(priority), None, None) => {
-            // Filter by status AND priority
-            sqlx::query_as::<_, Task>(
-                "SELECT * FROM tasks WHERE status = $1 AND priority = $2 ORDER BY created_at DESC",
-            )
-            .bind(status)
-            .bind(priority)
-            .fetch_all(&state.pool)
-            .await?
-        }
-        (None, None, Some(assigned_to), None) => {
-            // Filter by assigned_to
-            sqlx::query_as::<_, Task>(
-                "SELECT * FROM tasks WHERE assigned_to = $1 ORDER BY created_at DESC",
-            )
-            .bind(assigned_to)
-            .fetch_all(&state.pool)
-            .await?
-        }
-        // Add more combinations as needed...
-        _ => {
-            // For now, return all if combination not handled
-            sqlx::query_as::<_, Task>("SELECT * FROM tasks ORDER BY created_at DESC")
-                .fetch_all(&state.pool)
-                .await?
-        }
-    };
+    let query = format!(
+        r#"
+        SELECT t.* FROM tasks t
+        WHERE t.deleted_at IS NULL
+          AND {}
+          AND ($3::TEXT IS NULL OR t.status = $3)
+          AND ($4::TEXT IS NULL OR t.priority = $4)
+          AND ($5::UUID IS NULL OR t.assigned_to = $5)
+          AND ($6::UUID IS NULL OR t.created_by = $6)
+        ORDER BY t.created_at DESC
+        "#,
+        visible_to("t", "$1", "$2")
+    );
+
+    let tasks = sqlx::query_as::<_, Task>(&query)
+        .bind(claims.user_id()?)
+        .bind(claims.role == "admin")
+        .bind(params.status)
+        .bind(params.priority)
+        .bind(params.assigned_to)
+        .bind(params.created_by)
+        .fetch_all(&state.pool)
+        .await?;
 
     Ok(Json(tasks))
 }
 
-// Get a single task
-pub async fn get_task(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<Json<Task>> {
-    let task = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&state.pool)
-        .await?
-        .ok_or(crate::error::AppError::NotFound)?;
-    Ok(Json(task))
+// Get a single task (with its version as the ETag)
+pub async fn get_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<(HeaderMap, Json<Task>)> {
+    let task =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(crate::error::AppError::NotFound)?;
+    check_visible(&state, &claims, task.project_id).await?;
+
+    Ok((etag(&task), Json(task)))
 }
 
 // Create a task
@@ -111,10 +91,28 @@ pub async fn create_task(
 
     let created_by = claims.user_id()?;
 
+    if let Some(project_id) = payload.project_id {
+        let project_repo = ProjectRepository::new(state.pool.clone());
+        project_repo.check_task_project(project_id, created_by, claims.role == "admin").await?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+    if let Some(parent_id) = payload.parent_id {
+        HierarchyRepository::validate_parent_in(
+            &mut tx,
+            None,
+            parent_id,
+            state.config.max_task_depth,
+        )
+        .await?;
+    }
+
     let task = sqlx::query_as::<_, Task>(
         r#"
-        INSERT INTO tasks (title, description, priority, assigned_to, created_by, due_date)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO tasks
+        (title, description, priority, assigned_to, created_by, due_date, labels, parent_id,
+         estimated_minutes, project_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         RETURNING *
         "#,
     )
@@ -124,27 +122,25 @@ pub async fn create_task(
     .bind(payload.assigned_to)
     .bind(created_by)
     .bind(payload.due_date)
-    .fetch_one(&state.pool)
+    .bind(normalize_labels(payload.labels))
+    .bind(payload.parent_id)
+    .bind(payload.estimated_minutes)
+    .bind(payload.project_id)
+    .fetch_one(&mut *tx)
     .await?;
 
     // Audit log
-    let audit_repo = AuditRepository::new(state.pool.clone());
-    audit_repo
-        .log_action(
-            created_by,
-            "CREATE",
-            "task",
-            task.id,
-            Some(json!({})), // None -> No old values. None is getting error.
-            Some(json!({
-                "title": task.title,
-                "description": task.description,
-                "priority": task.priority,
-            })),
-            None, // IP address can be extracted from request
-            None, // User agent can be extracted from request
-        )
-        .await?;
+    AuditRepository::log_action_in(
+        &mut tx,
+        created_by,
+        "CREATE",
+        "task",
+        task.id,
+        Some(json!({})), // None -> No old values. None is getting error.
+        Some(task.snapshot()),
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok(Json(task))
 }
@@ -154,29 +150,26 @@ pub async fn update_task(
     Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateTaskRequest>,
-) -> Result<Json<Task>> {
+) -> Result<(HeaderMap, Json<Task>)> {
     payload.validate()?;
     let user_id = claims.user_id()?;
 
     // First, check if a task exists
-    let existing = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&state.pool)
-        .await?
-        .ok_or(crate::error::AppError::NotFound)?;
+    let existing =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(crate::error::AppError::NotFound)?;
+    check_editable(&state, &claims, &existing).await?;
+
+    check_if_match(&headers, &existing, state.config.require_if_match)?;
 
     // Check if trying to complete
     let new_status = payload.status.clone().unwrap_or(existing.status.clone());
-
-    if new_status == "completed" && existing.status != "completed" {
-        // Trying to complete - check dependencies
-        let dep_repo = DependencyRepository::new(state.pool.clone());
-
-        if !dep_repo.can_complete_task(id).await? {
-            return Err(AppError::ValidationError(validator::ValidationErrors::new()));
-        }
-    }
+    let completed_at = check_status_change(&state, &existing, &new_status).await?;
 
     // Build update
     let title = payload.title.unwrap_or(existing.title.clone());
@@ -184,15 +177,15 @@ pub async fn update_task(
     let priority = payload.priority.unwrap_or(existing.priority.clone());
     let assigned_to = payload.assigned_to.or(existing.assigned_to);
     let due_date = payload.due_date.or(existing.due_date);
+    let parent_id = payload.parent_id.or(existing.parent_id);
+    let estimated_minutes = payload.estimated_minutes.or(existing.estimated_minutes);
+    let project_id = payload.project_id.or(existing.project_id);
+    validate_new_project(&state, &claims, &existing, project_id).await?;
 
-    // Set completed_at if completing
-    let completed_at = if new_status == "completed" && existing.status != "completed" {
-        Some(chrono::Utc::now())
-    } else if new_status != "completed" {
-        None
-    } else {
-        existing.completed_at
-    };
+    // The parent check, the update, its audit entry and what it propagates to commit
+    // together
+    let mut tx = state.pool.begin().await?;
+    validate_new_parent(&mut tx, &state, &existing, parent_id).await?;
 
     let task = sqlx::query_as::<_, Task>(
         r#"
@@ -204,93 +197,737 @@ pub async fn update_task(
             assigned_to = $5,
             due_date = $6,
             completed_at = $7,
+            parent_id = $8,
+            estimated_minutes = $9,
+            project_id = $10,
             updated_at = NOW()
-        WHERE id = $8
+        WHERE id = $11 AND deleted_at IS NULL AND version = $12
         RETURNING *
         "#,
     )
     .bind(title)
     .bind(description)
     .bind(&new_status)
-    .bind(&priority)
+    .bind(priority)
     .bind(assigned_to)
     .bind(due_date)
     .bind(completed_at)
+    .bind(parent_id)
+    .bind(estimated_minutes)
+    .bind(project_id)
     .bind(id)
-    .fetch_one(&state.pool)
+    .bind(existing.version)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    // Audit log
-    let audit_repo = AuditRepository::new(state.pool.clone());
-    audit_repo
-        .log_action(
-            user_id,
-            "UPDATE",
-            "task",
-            task.id,
-            Some(json!({
-                "status": existing.status,
-                "priority": existing.priority,
-            })),
-            Some(json!({
-                "status": new_status,
-                "priority": priority,
-            })),
-            None,
-            None,
-        )
-        .await?;
+    // Someone else wrote the task between our read and our write
+    let Some(task) = task else {
+        drop(tx);
+        return Err(modified_since_read(&state, id).await);
+    };
 
-    Ok(Json(task))
+    finish_update(&mut tx, &state, user_id, &existing, &task).await?;
+    tx.commit().await?;
+
+    Ok((etag(&task), Json(task)))
 }
 
-// Delete task
-pub async fn delete_task(
+// Partially update a task with a JSON Merge Patch (RFC 7396) or JSON Patch (RFC 6902).
+// Unlike PUT, `null` clears a field. Only the columns that actually change are written.
+pub async fn patch_task(
     Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode> {
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(HeaderMap, Json<Task>)> {
     let user_id = claims.user_id()?;
-    let result =
-        sqlx::query!(r#"DELETE FROM tasks WHERE id = $1 AND created_by = $2"#, id, user_id)
-            .execute(&state.pool)
+
+    let existing =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(crate::error::AppError::NotFound)?;
+    check_editable(&state, &claims, &existing).await?;
+
+    check_if_match(&headers, &existing, state.config.require_if_match)?;
+
+    let patched = apply_patch(&headers, &body, &existing)?;
+    patched.validate()?;
+
+    let completed_at = check_status_change(&state, &existing, &patched.status).await?;
+    validate_new_project(&state, &claims, &existing, patched.project_id).await?;
+
+    let mut update = QueryBuilder::<Postgres>::new("UPDATE tasks SET updated_at = NOW()");
+    let mut changed = false;
+    if patched.title != existing.title {
+        update.push(", title = ").push_bind(&patched.title);
+        changed = true;
+    }
+    if patched.description != existing.description {
+        update.push(", description = ").push_bind(&patched.description);
+        changed = true;
+    }
+    if patched.status != existing.status {
+        update.push(", status = ").push_bind(&patched.status);
+        changed = true;
+    }
+    if completed_at != existing.completed_at {
+        update.push(", completed_at = ").push_bind(completed_at);
+    }
+    if patched.priority != existing.priority {
+        update.push(", priority = ").push_bind(&patched.priority);
+        changed = true;
+    }
+    if patched.assigned_to != existing.assigned_to {
+        update.push(", assigned_to = ").push_bind(patched.assigned_to);
+        changed = true;
+    }
+    if patched.due_date != existing.due_date {
+        update.push(", due_date = ").push_bind(patched.due_date);
+        changed = true;
+    }
+    if patched.labels != existing.labels {
+        update.push(", labels = ").push_bind(&patched.labels);
+        changed = true;
+    }
+    if patched.parent_id != existing.parent_id {
+        update.push(", parent_id = ").push_bind(patched.parent_id);
+        changed = true;
+    }
+    if patched.estimated_minutes != existing.estimated_minutes {
+        update.push(", estimated_minutes = ").push_bind(patched.estimated_minutes);
+        changed = true;
+    }
+    if patched.project_id != existing.project_id {
+        update.push(", project_id = ").push_bind(patched.project_id);
+        changed = true;
+    }
+
+    // Nothing to write: no new version, no audit entry
+    if !changed {
+        return Ok((etag(&existing), Json(existing)));
+    }
+
+    update.push(" WHERE id = ").push_bind(id);
+    update.push(" AND deleted_at IS NULL AND version = ").push_bind(existing.version);
+    update.push(" RETURNING *");
+
+    let mut tx = state.pool.begin().await?;
+    validate_new_parent(&mut tx, &state, &existing, patched.parent_id).await?;
+    let Some(task) = update.build_query_as::<Task>().fetch_optional(&mut *tx).await? else {
+        drop(tx);
+        return Err(modified_since_read(&state, id).await);
+    };
+
+    finish_update(&mut tx, &state, user_id, &existing, &task).await?;
+    tx.commit().await?;
+
+    Ok((etag(&task), Json(task)))
+}
+
+// Audit an update and propagate its status change, in the update's transaction, so
+// that a failure leaves nothing half done
+async fn finish_update(
+    conn: &mut PgConnection,
+    state: &AppState,
+    user_id: Uuid,
+    existing: &Task,
+    task: &Task,
+) -> Result<()> {
+    let entry = AuditRepository::log_action_in(
+        conn,
+        user_id,
+        "UPDATE",
+        "task",
+        task.id,
+        Some(existing.snapshot()),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    PropagationRepository::propagate_in(
+        conn,
+        state.config.propagation,
+        user_id,
+        None,
+        &entry,
+        Some(&existing.status),
+        task,
+    )
+    .await?;
+
+    Ok(())
+}
+
+// Apply the request body to the task's editable fields, according to Content-Type
+fn apply_patch(headers: &HeaderMap, body: &[u8], existing: &Task) -> Result<TaskPatchDocument> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let current = TaskPatchDocument::from(existing);
+
+    match content_type.as_str() {
+        "application/merge-patch+json" => {
+            let patch: Value = serde_json::from_slice(body)
+                .map_err(|e| AppError::BadRequest(format!("Invalid merge patch: {}", e)))?;
+            current.merged(&patch).map_err(|e| {
+                AppError::UnprocessableEntity(format!("Invalid task after patch: {}", e))
+            })
+        }
+        "application/json-patch+json" => {
+            let mut document = serde_json::to_value(current)
+                .map_err(|e| AppError::InternalError(format!("Failed to serialize task: {}", e)))?;
+            let patch: json_patch::Patch = serde_json::from_slice(body)
+                .map_err(|e| AppError::BadRequest(format!("Invalid JSON patch: {}", e)))?;
+            json_patch::patch(&mut document, &patch).map_err(|e| {
+                AppError::UnprocessableEntity(format!("JSON patch could not be applied: {}", e))
+            })?;
+            let mut patched: TaskPatchDocument = serde_json::from_value(document).map_err(|e| {
+                AppError::UnprocessableEntity(format!("Invalid task after patch: {}", e))
+            })?;
+            patched.labels = normalize_labels(patched.labels);
+            Ok(patched)
+        }
+        _ => Err(AppError::UnsupportedMediaType(
+            "Use application/merge-patch+json or application/json-patch+json".to_string(),
+        )),
+    }
+}
+
+// Check a status change and work out completed_at. Leaving the completed state
+// clears the timestamp.
+async fn check_status_change(
+    state: &AppState,
+    existing: &Task,
+    new_status: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    if new_status == existing.status {
+        return Ok(existing.completed_at);
+    }
+
+    let mut conn = state.pool.acquire().await?;
+    check_status_gate(&mut conn, existing.id, new_status).await?;
+
+    Ok((new_status == "completed").then(Utc::now))
+}
+
+// Moving into in_progress or completed, from whatever status, has to be allowed by
+// the task's dependency links; completing also requires all subtasks to be completed
+async fn check_status_gate(conn: &mut PgConnection, task_id: Uuid, status: &str) -> Result<()> {
+    if !DependencyRepository::allows_status_in(conn, task_id, status).await? {
+        return Err(if status == "completed" {
+            AppError::ValidationError(validator::ValidationErrors::new())
+        } else {
+            AppError::BadRequest("Task cannot start until its dependencies allow it".to_string())
+        });
+    }
+    if status == "completed" && HierarchyRepository::has_open_children_in(conn, task_id).await? {
+        return Err(open_subtasks());
+    }
+    Ok(())
+}
+
+// Trashed tasks, and project tasks of projects the caller is not in, do not exist as
+// far as the caller is concerned
+pub(crate) async fn ensure_task_visible(
+    state: &AppState,
+    claims: &Claims,
+    task_id: Uuid,
+) -> Result<()> {
+    let project_id = sqlx::query_scalar::<_, Option<Uuid>>(
+        "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(task_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    check_visible(state, claims, project_id).await
+}
+
+// Fails like `check_editable` for a live task given by id
+pub(crate) async fn ensure_task_editable(
+    state: &AppState,
+    claims: &Claims,
+    task_id: Uuid,
+) -> Result<()> {
+    let task =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(task_id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(AppError::NotFound)?;
+
+    check_editable(state, claims, &task).await
+}
+
+// Whether the user may read a task's history. Trashed tasks keep theirs; once a task
+// is purged only admins can read it.
+pub(crate) async fn ensure_history_visible(
+    state: &AppState,
+    claims: &Claims,
+    task_id: Uuid,
+) -> Result<()> {
+    let project_id =
+        sqlx::query_scalar::<_, Option<Uuid>>("SELECT project_id FROM tasks WHERE id = $1")
+            .bind(task_id)
+            .fetch_optional(&state.pool)
             .await?;
 
-    // Audit log
-    let audit_repo = AuditRepository::new(state.pool.clone());
-    audit_repo.log_action(user_id, "DELETE", "task", id, None, None, None, None).await?;
+    match project_id {
+        Some(project_id) => check_visible(state, claims, project_id).await,
+        None if claims.role == "admin" => Ok(()),
+        None => Err(AppError::NotFound),
+    }
+}
 
-    if result.rows_affected() == 0 {
-        return Err(crate::error::AppError::NotFound);
+// The same check for a task that has been read already
+async fn check_visible(state: &AppState, claims: &Claims, project_id: Option<Uuid>) -> Result<()> {
+    let mut conn = state.pool.acquire().await?;
+    let user_id = claims.user_id()?;
+    if !ProjectRepository::can_see_in(&mut conn, project_id, user_id, claims.role == "admin")
+        .await?
+    {
+        return Err(AppError::NotFound);
     }
+    Ok(())
+}
+
+// Changing a task takes seeing it, and being its creator, its assignee or an admin
+pub(crate) async fn check_editable(state: &AppState, claims: &Claims, task: &Task) -> Result<()> {
+    check_visible(state, claims, task.project_id).await?;
+    if !task.editable_by(claims.user_id()?, claims.role == "admin") {
+        return Err(AppError::Unauthorized(
+            "Only the task's creator, its assignee or an admin can change it".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Moving a task into a project requires membership of that project
+async fn validate_new_project(
+    state: &AppState,
+    claims: &Claims,
+    existing: &Task,
+    project_id: Option<Uuid>,
+) -> Result<()> {
+    match project_id {
+        Some(project_id) if existing.project_id != Some(project_id) => {
+            let project_repo = ProjectRepository::new(state.pool.clone());
+            project_repo
+                .check_task_project(project_id, claims.user_id()?, claims.role == "admin")
+                .await
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) const OPEN_SUBTASKS: &str = "Cannot complete a task while it has open subtasks";
+
+fn open_subtasks() -> AppError {
+    AppError::BadRequest(OPEN_SUBTASKS.to_string())
+}
+
+// Check a parent change in the transaction that writes it: a no-op and detaching
+// need no checks
+async fn validate_new_parent(
+    conn: &mut PgConnection,
+    state: &AppState,
+    existing: &Task,
+    parent_id: Option<Uuid>,
+) -> Result<()> {
+    match parent_id {
+        Some(parent_id) if existing.parent_id != Some(parent_id) => {
+            HierarchyRepository::validate_parent_in(
+                conn,
+                Some(existing.id),
+                parent_id,
+                state.config.max_task_depth,
+            )
+            .await
+        }
+        _ => Ok(()),
+    }
+}
+
+// Delete task (moves it to the trash)
+pub async fn delete_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    let user_id = claims.user_id()?;
+    let existing = sqlx::query_as::<_, Task>(
+        "SELECT * FROM tasks WHERE id = $1 AND created_by = $2 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(crate::error::AppError::NotFound)?;
+    check_visible(&state, &claims, existing.project_id).await?;
+
+    check_if_match(&headers, &existing, state.config.require_if_match)?;
+
+    let Some(deleted) = soft_delete(&state, &existing).await? else {
+        return Err(modified_since_read(&state, id).await);
+    };
+
+    // Audit log (keep the last live state so the task can be restored by a revert)
+    let audit_repo = AuditRepository::new(state.pool.clone());
+    audit_repo
+        .log_action(user_id, "DELETE", "task", id, Some(deleted.live_snapshot()), None, None, None)
+        .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-// Admin-only: Delete ANY task
+// Admin-only: Delete ANY task (moves it to the trash)
 pub async fn admin_delete_any_task(
     Extension(claims): Extension<Claims>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> Result<StatusCode> {
     // Middleware already checked a role, but let's be explicit
     if claims.role != "admin" {
         return Err(crate::error::AppError::Unauthorized("Admin access required".to_string()));
     }
 
-    let result = sqlx::query!(
+    let existing =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or(crate::error::AppError::NotFound)?;
+    check_editable(&state, &claims, &existing).await?;
+
+    check_if_match(&headers, &existing, state.config.require_if_match)?;
+
+    let Some(deleted) = soft_delete(&state, &existing).await? else {
+        return Err(modified_since_read(&state, id).await);
+    };
+
+    // Audit log
+    let audit_repo = AuditRepository::new(state.pool.clone());
+    audit_repo
+        .log_action(
+            claims.user_id()?,
+            "DELETE",
+            "task",
+            id,
+            Some(deleted.live_snapshot()),
+            None,
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Move a task to the trash, provided it is still at the version that was read
+async fn soft_delete(state: &AppState, existing: &Task) -> Result<Option<Task>> {
+    let deleted = sqlx::query_as::<_, Task>(
         r#"
-        DELETE FROM tasks
-        WHERE id = $1
+        UPDATE tasks
+        SET deleted_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL AND version = $2
+        RETURNING *
         "#,
-        id
     )
-    .execute(&state.pool)
+    .bind(existing.id)
+    .bind(existing.version)
+    .fetch_optional(&state.pool)
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(crate::error::AppError::NotFound);
+    Ok(deleted)
+}
+
+// A conditional write matched no row: report the task's current state, if any
+async fn modified_since_read(state: &AppState, id: Uuid) -> AppError {
+    let current =
+        sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&state.pool)
+            .await;
+
+    match current {
+        Ok(Some(task)) => AppError::PreconditionFailed(Box::new(task.snapshot())),
+        Ok(None) => AppError::NotFound,
+        Err(e) => e.into(),
     }
+}
 
-    Ok(StatusCode::NO_CONTENT)
+// Reconstruct what a task looked like at a point in time from its audit history
+pub async fn get_task_as_of(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<AsOfQuery>,
+) -> Result<Json<Value>> {
+    ensure_history_visible(&state, &claims, id).await?;
+
+    let audit_repo = AuditRepository::new(state.pool.clone());
+    let snapshot = audit_repo
+        .reconstruct_state("task", id, params.ts, true)
+        .await?
+        .ok_or(crate::error::AppError::NotFound)?;
+
+    Ok(Json(snapshot))
+}
+
+// Restore a task to the state it had right before the given audit entry. The same
+// people who can change the task can revert it. A trashed task has to be restored
+// first; reverting the DELETE of a purged one recreates it (without its dependencies),
+// which like a restore is up to its creator or an admin.
+pub async fn revert_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path((id, audit_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Task>> {
+    let user_id = claims.user_id()?;
+    let audit_repo = AuditRepository::new(state.pool.clone());
+
+    let existing = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await?;
+    match &existing {
+        Some(task) if task.deleted_at.is_some() => {
+            if task.created_by != Some(user_id) && claims.role != "admin" {
+                return Err(AppError::NotFound);
+            }
+            return Err(AppError::Conflict(
+                "The task is in the trash, restore it before reverting changes".to_string(),
+            ));
+        }
+        Some(task) => check_editable(&state, &claims, task).await?,
+        None => {}
+    }
+
+    let entry = audit_repo
+        .get_entry(audit_id)
+        .await?
+        .filter(|entry| entry.resource_type == "task" && entry.resource_id == id)
+        .ok_or(crate::error::AppError::NotFound)?;
+
+    if entry.action == "CREATE" {
+        return Err(AppError::Conflict(
+            "Cannot revert a task's creation, delete the task instead".to_string(),
+        ));
+    }
+
+    let previous = audit_repo
+        .reconstruct_state("task", id, entry.created_at, false)
+        .await?
+        .ok_or_else(|| AppError::Conflict("No recorded state before this change".to_string()))?;
+
+    // Recreating a purged task is up to whoever created it
+    if existing.is_none()
+        && previous.get("created_by") != Some(&json!(user_id))
+        && claims.role != "admin"
+    {
+        return Err(AppError::NotFound);
+    }
+    let existing = existing.as_ref();
+
+    // Fields missing from older, partial audit entries keep their current values
+    let mut restored = existing.map(Task::snapshot).unwrap_or_else(|| json!({}));
+    if let (Value::Object(fields), Value::Object(previous)) = (&mut restored, previous) {
+        fields.extend(previous);
+    }
+    let restored: Task = serde_json::from_value(restored).map_err(|_| {
+        AppError::Conflict("Audit history is too incomplete to restore this task".to_string())
+    })?;
+
+    let mut tx = state.pool.begin().await?;
+    if existing.is_none_or(|task| task.status != restored.status) {
+        check_status_gate(&mut tx, id, &restored.status).await?;
+    }
+
+    // The task's place in the hierarchy and its project are left as they are now (a
+    // recreated task starts at the top level, outside any project): the old parent may
+    // have moved or gone since, and the user may have left the project.
+    let task = if let Some(existing) = existing {
+        let task = sqlx::query_as::<_, Task>(
+            r#"
+            UPDATE tasks
+            SET title = $1,
+                description = $2,
+                status = $3,
+                priority = $4,
+                assigned_to = $5,
+                due_date = $6,
+                completed_at = $7,
+                estimated_minutes = $8,
+                updated_at = NOW()
+            WHERE id = $9 AND deleted_at IS NULL AND version = $10
+            RETURNING *
+            "#,
+        )
+        .bind(&restored.title)
+        .bind(&restored.description)
+        .bind(&restored.status)
+        .bind(&restored.priority)
+        .bind(restored.assigned_to)
+        .bind(restored.due_date)
+        .bind(restored.completed_at)
+        .bind(restored.estimated_minutes)
+        .bind(id)
+        .bind(existing.version)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Someone else wrote the task between our read and our write
+        let Some(task) = task else {
+            drop(tx);
+            return Err(modified_since_read(&state, id).await);
+        };
+        task
+    } else {
+        sqlx::query_as::<_, Task>(
+            r#"
+            INSERT INTO tasks
+            (id, title, description, status, priority, assigned_to, created_by, due_date,
+             completed_at, created_at, estimated_minutes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, NOW()), $11)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(&restored.title)
+        .bind(&restored.description)
+        .bind(&restored.status)
+        .bind(&restored.priority)
+        .bind(restored.assigned_to)
+        .bind(restored.created_by.unwrap_or(user_id))
+        .bind(restored.due_date)
+        .bind(restored.completed_at)
+        .bind(restored.created_at)
+        .bind(restored.estimated_minutes)
+        .fetch_one(&mut *tx)
+        .await?
+    };
+
+    let entry = AuditRepository::log_action_in(
+        &mut tx,
+        user_id,
+        "REVERT",
+        "task",
+        id,
+        Some(existing.map(Task::snapshot).unwrap_or_else(|| json!({}))),
+        Some(task.snapshot()),
+    )
+    .await?;
+
+    let previous_status = existing.map(|task| task.status.as_str());
+    PropagationRepository::propagate_in(
+        &mut tx,
+        state.config.propagation,
+        user_id,
+        None,
+        &entry,
+        previous_status,
+        &task,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(task))
+}
+
+// Open tasks past their due date that the caller can see, most overdue first
+// (?project_id=, ?assigned_to=, ?priority=, ?limit=)
+pub async fn list_overdue_tasks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<OverdueQuery>,
+) -> Result<Json<Vec<OverdueTask>>> {
+    if let Some(priority) = params.priority.as_deref()
+        && !["low", "medium", "high", "urgent"].contains(&priority)
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid priority '{}': expected low, medium, high or urgent",
+            priority
+        )));
+    }
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    let repo = OverdueRepository::new(state.pool);
+    let tasks = repo.list(claims.user_id()?, claims.role == "admin", &params, limit).await?;
+
+    Ok(Json(tasks))
+}
+
+// List tasks in the trash (own tasks, or every task for admins)
+pub async fn list_trash(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Task>>> {
+    let user_id = claims.user_id()?;
+
+    let tasks = sqlx::query_as::<_, Task>(
+        r#"
+        SELECT * FROM tasks
+        WHERE deleted_at IS NOT NULL AND (created_by = $1 OR $2)
+        ORDER BY deleted_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(claims.role == "admin")
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tasks))
+}
+
+// Take a task out of the trash. Its dependency edges were kept while it was
+// trashed, so they come back with it.
+pub async fn restore_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Task>> {
+    let user_id = claims.user_id()?;
+
+    let task = sqlx::query_as::<_, Task>(
+        r#"
+        UPDATE tasks
+        SET deleted_at = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NOT NULL AND (created_by = $2 OR $3)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(claims.role == "admin")
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(crate::error::AppError::NotFound)?;
+
+    let audit_repo = AuditRepository::new(state.pool.clone());
+    audit_repo
+        .log_action(
+            user_id,
+            "RESTORE",
+            "task",
+            id,
+            Some(json!({})),
+            Some(task.snapshot()),
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(Json(task))
 }
@@ -0,0 +1,399 @@
+use crate::{
+    database::{
+        audit_repo::AuditRepository,
+        project_repo::ProjectRepository,
+        time_repo::{TimeEntryRepository, TimeFilter},
+    },
+    error::{AppError, Result},
+    handlers::tasks::ensure_task_visible,
+    models::{
+        AuditLogWithUser, CreateTimeEntryRequest, DayTotal, ProjectTimeSummary, StartTimerRequest,
+        TaskTimeSummary, TimeEntry, Timesheet, TimesheetQuery, UpdateTimeEntryRequest,
+    },
+    state::AppState,
+    utils::{csv::write_row, jwt::Claims},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use validator::Validate;
+
+// Longest manual entry, and longest timesheet range
+const MAX_ENTRY_MINUTES: i64 = 24 * 60;
+const MAX_RANGE_DAYS: i64 = 366;
+
+// Start a timer on a task; the caller's running timer, if any, is stopped first
+pub async fn start_timer(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+    payload: Option<Json<StartTimerRequest>>,
+) -> Result<Json<TimeEntry>> {
+    let note = match &payload {
+        Some(Json(payload)) => {
+            payload.validate()?;
+            payload.note.as_deref()
+        }
+        None => None,
+    };
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let (_, entry) = repo.start_timer(task_id, claims.user_id()?, note).await?;
+
+    Ok(Json(entry))
+}
+
+// The caller's running timer
+pub async fn get_timer(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<TimeEntry>> {
+    let repo = TimeEntryRepository::new(state.pool);
+    let entry = repo.running(claims.user_id()?).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(entry))
+}
+
+pub async fn stop_timer(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<TimeEntry>> {
+    let repo = TimeEntryRepository::new(state.pool);
+    let entry = repo.stop_timer(claims.user_id()?).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(entry))
+}
+
+// Log time after the fact, with an end time or a number of minutes
+pub async fn create_time_entry(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+    Json(payload): Json<CreateTimeEntryRequest>,
+) -> Result<Json<TimeEntry>> {
+    payload.validate()?;
+    let ended_at = match (payload.ended_at, payload.minutes) {
+        (Some(ended_at), None) => ended_at,
+        (None, Some(minutes)) => payload.started_at + Duration::minutes(minutes),
+        _ => {
+            return Err(AppError::BadRequest("Give either ended_at or minutes".to_string()));
+        }
+    };
+    check_span(payload.started_at, ended_at)?;
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let entry = repo
+        .create_manual(
+            task_id,
+            claims.user_id()?,
+            payload.started_at,
+            ended_at,
+            payload.note.as_deref(),
+        )
+        .await?;
+
+    Ok(Json(entry))
+}
+
+pub async fn list_task_time_entries(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TimeEntry>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let entries = repo.list_for_task(task_id).await?;
+
+    Ok(Json(entries))
+}
+
+// Change the times or note of an entry; setting ended_at on a running timer stops it
+pub async fn update_time_entry(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateTimeEntryRequest>,
+) -> Result<Json<TimeEntry>> {
+    payload.validate()?;
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let existing = load_own_entry(&repo, &claims, id).await?;
+
+    let started_at = payload.started_at.unwrap_or(existing.started_at);
+    let ended_at = payload.ended_at.or(existing.ended_at);
+    match ended_at {
+        Some(ended_at) => check_span(started_at, ended_at)?,
+        None if started_at > Utc::now() => {
+            return Err(AppError::BadRequest("A timer cannot start in the future".to_string()));
+        }
+        None => {}
+    }
+    let note = payload.note.as_deref().or(existing.note.as_deref());
+
+    let entry = repo
+        .update(id, started_at, ended_at, note, claims.user_id()?)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(entry))
+}
+
+pub async fn delete_time_entry(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let repo = TimeEntryRepository::new(state.pool);
+    load_own_entry(&repo, &claims, id).await?;
+
+    if !repo.delete(id, claims.user_id()?).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Audit history of an entry, including its deletion
+pub async fn get_time_entry_history(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<AuditLogWithUser>>> {
+    let repo = AuditRepository::new(state.pool);
+    let logs = repo.get_resource_history("time_entry", id).await?;
+
+    // Only the entry's user and admins see it; a deleted entry is known from its log
+    let owner = logs
+        .iter()
+        .find_map(|log| log.old_value.as_ref().or(log.new_value.as_ref())?.get("user_id"))
+        .and_then(|user_id| user_id.as_str())
+        .and_then(|user_id| Uuid::parse_str(user_id).ok());
+    if logs.is_empty() || (claims.role != "admin" && owner != Some(claims.user_id()?)) {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(Json(logs))
+}
+
+// Estimate and logged time of a task and of its whole subtree, per user
+pub async fn get_task_time(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskTimeSummary>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let summary = repo.task_summary(task_id).await?;
+
+    Ok(Json(summary))
+}
+
+// A user's time per day and per task over a date range (the last 7 days by
+// default), with the entries. Admins can see anyone's; ?format=csv exports the
+// entries.
+pub async fn get_timesheet(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Query(params): Query<TimesheetQuery>,
+) -> Result<Response> {
+    let caller = claims.user_id()?;
+    let user_id = params.user_id.unwrap_or(caller);
+    if user_id != caller && claims.role != "admin" {
+        return Err(AppError::Unauthorized(
+            "Only admins can see other users' timesheets".to_string(),
+        ));
+    }
+    let csv = wants_csv(&params)?;
+
+    let to = params.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = params.from.unwrap_or(to - Duration::days(6));
+    let (start, end) = day_range(from, to)?;
+    let filter = TimeFilter {
+        user_id: Some(user_id),
+        project_id: params.project_id,
+        from: Some(start),
+        until: Some(end),
+    };
+
+    let repo = TimeEntryRepository::new(state.pool);
+    let entries = repo.list(&filter).await?;
+    if csv {
+        return Ok(csv_response(&format!("timesheet-{}-{}.csv", from, to), &entries));
+    }
+    let tasks = repo.totals_by_task(&filter).await?;
+
+    let mut days: BTreeMap<NaiveDate, i64> =
+        from.iter_days().take_while(|day| *day <= to).map(|day| (day, 0)).collect();
+    for entry in &entries {
+        *days.entry(entry.started_at.date_naive()).or_default() += entry.minutes;
+    }
+
+    Ok(Json(Timesheet {
+        user_id,
+        from,
+        to,
+        total_minutes: entries.iter().map(|entry| entry.minutes).sum(),
+        days: days.into_iter().map(|(date, minutes)| DayTotal { date, minutes }).collect(),
+        tasks,
+        entries,
+    })
+    .into_response())
+}
+
+// Time logged on a project's tasks, per task and per user, against the project's
+// estimate (?from=, ?to=, ?user_id=, ?format=csv for the entries)
+pub async fn get_project_time(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    Query(params): Query<TimesheetQuery>,
+) -> Result<Response> {
+    let project_repo = ProjectRepository::new(state.pool.clone());
+    project_repo.get(project_id).await?.ok_or(AppError::NotFound)?;
+    if claims.role != "admin" && !project_repo.is_member(project_id, claims.user_id()?).await? {
+        return Err(AppError::NotFound);
+    }
+    let csv = wants_csv(&params)?;
+
+    let (start, end) = match (params.from, params.to) {
+        (Some(from), Some(to)) => {
+            let (start, end) = day_range(from, to)?;
+            (Some(start), Some(end))
+        }
+        (from, to) => (from.map(start_of_day), to.map(|to| start_of_day(to + Duration::days(1)))),
+    };
+    let filter = TimeFilter {
+        user_id: params.user_id,
+        project_id: Some(project_id),
+        from: start,
+        until: end,
+    };
+
+    let repo = TimeEntryRepository::new(state.pool);
+    if csv {
+        let entries = repo.list(&filter).await?;
+        return Ok(csv_response(&format!("project-{}.csv", project_id), &entries));
+    }
+    let tasks = repo.totals_by_task(&filter).await?;
+    let users = repo.totals_by_user(&filter).await?;
+
+    Ok(Json(ProjectTimeSummary {
+        project_id,
+        from: params.from,
+        to: params.to,
+        estimated_minutes: repo.project_estimate(project_id).await?,
+        logged_minutes: users.iter().map(|user| user.minutes).sum(),
+        tasks,
+        users,
+    })
+    .into_response())
+}
+
+// Entries can only be changed by their user and by admins
+async fn load_own_entry(
+    repo: &TimeEntryRepository,
+    claims: &Claims,
+    id: Uuid,
+) -> Result<TimeEntry> {
+    let entry = repo.get(id).await?.ok_or(AppError::NotFound)?;
+    if claims.role != "admin" && entry.user_id != claims.user_id()? {
+        return Err(AppError::NotFound);
+    }
+    Ok(entry)
+}
+
+fn check_span(started_at: DateTime<Utc>, ended_at: DateTime<Utc>) -> Result<()> {
+    if ended_at <= started_at {
+        return Err(AppError::BadRequest("ended_at must be after started_at".to_string()));
+    }
+    if ended_at > Utc::now() {
+        return Err(AppError::BadRequest("Time cannot be logged in the future".to_string()));
+    }
+    if ended_at - started_at > Duration::minutes(MAX_ENTRY_MINUTES) {
+        return Err(AppError::BadRequest("An entry cannot be longer than 24 hours".to_string()));
+    }
+    Ok(())
+}
+
+fn wants_csv(params: &TimesheetQuery) -> Result<bool> {
+    match params.format.as_deref() {
+        None | Some("json") => Ok(false),
+        Some("csv") => Ok(true),
+        Some(other) => {
+            Err(AppError::BadRequest(format!("Unknown format '{}': expected json or csv", other)))
+        }
+    }
+}
+
+// `from` to `to` as [start, end) times, both days included
+fn day_range(from: NaiveDate, to: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    if from > to {
+        return Err(AppError::BadRequest("from must not be after to".to_string()));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(AppError::BadRequest(format!(
+            "The range cannot be longer than {} days",
+            MAX_RANGE_DAYS
+        )));
+    }
+    Ok((start_of_day(from), start_of_day(to + Duration::days(1))))
+}
+
+fn start_of_day(day: NaiveDate) -> DateTime<Utc> {
+    day.and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+fn csv_response(filename: &str, entries: &[TimeEntry]) -> Response {
+    let mut out = String::new();
+    write_row(
+        &mut out,
+        &[
+            "date",
+            "user",
+            "task_id",
+            "task",
+            "project_id",
+            "started_at",
+            "ended_at",
+            "minutes",
+            "source",
+            "note",
+        ],
+    );
+    for entry in entries {
+        write_row(
+            &mut out,
+            &[
+                &entry.started_at.date_naive().to_string(),
+                &entry.user_email,
+                &entry.task_id.to_string(),
+                &entry.task_title,
+                &entry.project_id.map(|id| id.to_string()).unwrap_or_default(),
+                &entry.started_at.to_rfc3339(),
+                &entry.ended_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+                &entry.minutes.to_string(),
+                &entry.source,
+                entry.note.as_deref().unwrap_or(""),
+            ],
+        );
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        out,
+    )
+        .into_response()
+}
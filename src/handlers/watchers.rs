@@ -0,0 +1,66 @@
+use crate::{
+    database::watcher_repo::WatcherRepository,
+    error::Result,
+    handlers::tasks::ensure_task_visible,
+    models::{Task, TaskWatcher},
+    state::AppState,
+    utils::jwt::Claims,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn list_watchers(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskWatcher>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = WatcherRepository::new(state.pool);
+    let watchers = repo.list(task_id).await?;
+
+    Ok(Json(watchers))
+}
+
+// Start watching a task; returns everyone watching it
+pub async fn watch_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskWatcher>>> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = WatcherRepository::new(state.pool);
+    repo.watch(task_id, claims.user_id()?).await?;
+    let watchers = repo.list(task_id).await?;
+
+    Ok(Json(watchers))
+}
+
+pub async fn unwatch_task(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    ensure_task_visible(&state, &claims, task_id).await?;
+
+    let repo = WatcherRepository::new(state.pool);
+    repo.unwatch(task_id, claims.user_id()?).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Every task the caller watches
+pub async fn list_watched_tasks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Task>>> {
+    let repo = WatcherRepository::new(state.pool);
+    let tasks = repo.list_watched_tasks(claims.user_id()?, claims.role == "admin").await?;
+
+    Ok(Json(tasks))
+}
@@ -0,0 +1,227 @@
+use crate::{
+    database::{project_repo::ProjectRepository, webhook_repo::WebhookRepository},
+    error::{AppError, Result},
+    models::{
+        CreateWebhookRequest, DeliveryQuery, UpdateWebhookRequest, WEBHOOK_EVENT_TYPES, Webhook,
+        WebhookDelivery, WebhookDeliveryDetail, WebhookWithSecret,
+    },
+    state::AppState,
+    utils::{jwt::Claims, outbound::check_public_url, token::random_token},
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+// Subscribe a URL to task and dependency events. The response carries the signing
+// secret; it is not shown again.
+pub async fn create_webhook(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<Json<WebhookWithSecret>> {
+    payload.validate()?;
+    check_url(&state, &payload.url).await?;
+    let event_types = normalize_event_types(payload.event_types)?;
+
+    let user_id = claims.user_id()?;
+    if let Some(project_id) = payload.project_id {
+        ProjectRepository::new(state.pool.clone())
+            .check_task_project(project_id, user_id, claims.role == "admin")
+            .await?;
+    }
+
+    let secret = payload.secret.unwrap_or_else(|| random_token("whsec_"));
+    let repo = WebhookRepository::new(state.pool);
+    let webhook =
+        repo.create(user_id, &payload.url, &event_types, payload.project_id, &secret).await?;
+
+    Ok(Json(WebhookWithSecret { webhook, secret }))
+}
+
+// The caller's webhooks
+pub async fn list_webhooks(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Webhook>>> {
+    let repo = WebhookRepository::new(state.pool);
+    let webhooks = repo.list_for_owner(claims.user_id()?).await?;
+
+    Ok(Json(webhooks))
+}
+
+pub async fn get_webhook(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Webhook>> {
+    let repo = WebhookRepository::new(state.pool);
+    let webhook = owned_webhook(&repo, &claims, id).await?;
+
+    Ok(Json(webhook))
+}
+
+// Change the URL, event types or secret, or enable/disable the webhook. A new
+// secret is returned once, like on creation.
+pub async fn update_webhook(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(mut payload): Json<UpdateWebhookRequest>,
+) -> Result<Json<serde_json::Value>> {
+    payload.validate()?;
+    if let Some(url) = &payload.url {
+        check_url(&state, url).await?;
+    }
+    if let Some(event_types) = payload.event_types.take() {
+        payload.event_types = Some(normalize_event_types(event_types)?);
+    }
+
+    let repo = WebhookRepository::new(state.pool);
+    owned_webhook(&repo, &claims, id).await?;
+    let webhook = repo.update(id, &payload).await?;
+
+    let body = match payload.secret {
+        Some(_) => {
+            let secret = webhook.secret.clone();
+            serde_json::to_value(WebhookWithSecret { webhook, secret })
+        }
+        None => serde_json::to_value(webhook),
+    }
+    .map_err(|e| AppError::InternalError(format!("Cannot encode webhook: {}", e)))?;
+
+    Ok(Json(body))
+}
+
+pub async fn delete_webhook(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let repo = WebhookRepository::new(state.pool);
+    owned_webhook(&repo, &claims, id).await?;
+    repo.delete(id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Queue a `ping` event, to try out a receiver
+pub async fn ping_webhook(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookDelivery>> {
+    let repo = WebhookRepository::new(state.pool);
+    let webhook = owned_webhook(&repo, &claims, id).await?;
+    ensure_active(&webhook)?;
+
+    let delivery = repo.enqueue_ping(&webhook).await?;
+
+    Ok(Json(delivery))
+}
+
+// The delivery log, newest first (?status=pending|delivered|failed, ?limit=)
+pub async fn list_deliveries(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<DeliveryQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>> {
+    if let Some(status) = params.status.as_deref()
+        && !["pending", "delivered", "failed"].contains(&status)
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid status '{}': expected pending, delivered or failed",
+            status
+        )));
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    let repo = WebhookRepository::new(state.pool);
+    owned_webhook(&repo, &claims, id).await?;
+    let deliveries = repo.list_deliveries(id, params.status.as_deref(), limit).await?;
+
+    Ok(Json(deliveries))
+}
+
+// A delivery with every attempt: its response status code, error and duration
+pub async fn get_delivery(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<WebhookDeliveryDetail>> {
+    let repo = WebhookRepository::new(state.pool);
+    owned_webhook(&repo, &claims, id).await?;
+
+    let delivery = repo.get_delivery(id, delivery_id).await?.ok_or(AppError::NotFound)?;
+    let attempts_log = repo.list_attempts(delivery_id).await?;
+
+    Ok(Json(WebhookDeliveryDetail { delivery, attempts_log }))
+}
+
+// Send a delivery's payload again, as a new delivery
+pub async fn redeliver(
+    Extension(claims): Extension<Claims>,
+    State(state): State<AppState>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<WebhookDelivery>> {
+    let repo = WebhookRepository::new(state.pool);
+    let webhook = owned_webhook(&repo, &claims, id).await?;
+    ensure_active(&webhook)?;
+
+    let original = repo.get_delivery(id, delivery_id).await?.ok_or(AppError::NotFound)?;
+    let delivery = repo.redeliver(&original).await?;
+
+    Ok(Json(delivery))
+}
+
+// Webhooks are managed by their owner (and admins); they are invisible to others
+async fn owned_webhook(repo: &WebhookRepository, claims: &Claims, id: Uuid) -> Result<Webhook> {
+    let webhook = repo.get(id).await?.ok_or(AppError::NotFound)?;
+
+    if claims.role != "admin" && webhook.owner_id != claims.user_id()? {
+        return Err(AppError::NotFound);
+    }
+    Ok(webhook)
+}
+
+fn ensure_active(webhook: &Webhook) -> Result<()> {
+    if !webhook.active {
+        return Err(AppError::Conflict(
+            "Webhook is disabled; enable it before sending deliveries".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Deliveries are checked again when sent: see utils::outbound
+async fn check_url(state: &AppState, url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| AppError::BadRequest(format!("Invalid URL '{}': {}", url, e)))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(AppError::BadRequest("Webhook URLs must use http or https".to_string()));
+    }
+    if !state.config.webhooks.allow_private_targets {
+        check_public_url(&parsed).await?;
+    }
+    Ok(())
+}
+
+// Sorted and deduplicated; an empty list subscribes to everything
+fn normalize_event_types(mut event_types: Vec<String>) -> Result<Vec<String>> {
+    if let Some(unknown) = event_types.iter().find(|t| !WEBHOOK_EVENT_TYPES.contains(&t.as_str())) {
+        return Err(AppError::BadRequest(format!(
+            "Unknown event type '{}': expected one of {}",
+            unknown,
+            WEBHOOK_EVENT_TYPES.join(", ")
+        )));
+    }
+    event_types.sort();
+    event_types.dedup();
+
+    Ok(event_types)
+}
@@ -0,0 +1,402 @@
+use crate::{
+    database::project_repo::ProjectRepository,
+    database::watcher_repo::WatcherRepository,
+    error::{AppError, Result},
+    handlers::events::{Visibility, WatchedTasks},
+    models::{ClientMessage, ServerMessage, TaskEvent, WsQuery},
+    state::AppState,
+    utils::{
+        jwt::{Claims, verify_token},
+        presence::RoomMessage,
+    },
+};
+use axum::{
+    extract::{
+        Query, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::Response,
+};
+use futures::{SinkExt, StreamExt, stream::SplitSink};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+use tokio::sync::{
+    broadcast::error::RecvError,
+    mpsc::{self, error::TrySendError},
+};
+use uuid::Uuid;
+
+// Messages queued for a client before it counts as too slow and is disconnected
+const OUTBOX_CAPACITY: usize = 64;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// A client that sent nothing (not even a pong) for this long is gone
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(75);
+// Time to send the auth message when no ?token= was given
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+// Authenticated WebSocket for subscriptions, presence and typing indicators. The
+// JWT comes as ?token= (checked before the upgrade) or in a first `auth` message.
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<WsQuery>,
+) -> Result<Response> {
+    let claims = match params.token {
+        Some(token) => Some(verify_token(&token, &state.config.jwt_secret)?),
+        None => None,
+    };
+
+    Ok(ws.max_message_size(MAX_MESSAGE_SIZE).on_upgrade(move |socket| run(socket, state, claims)))
+}
+
+async fn run(socket: WebSocket, state: AppState, claims: Option<Claims>) {
+    let (sink, mut incoming) = socket.split();
+    let (outbox, queued) = mpsc::channel(OUTBOX_CAPACITY);
+    let writer = tokio::spawn(write_loop(sink, queued));
+
+    let claims = match claims {
+        Some(claims) => Some(claims),
+        None => authenticate(&state, &mut incoming).await,
+    };
+    let close = match claims {
+        Some(claims) => match Connection::open(&state, &claims, outbox.clone()).await {
+            Ok(mut connection) => {
+                let close = connection.serve(&mut incoming).await;
+                connection.leave_all();
+                close
+            }
+            Err(e) => {
+                tracing::error!("WebSocket setup failed: {:?}", e);
+                (close_code::ERROR, "Internal error")
+            }
+        },
+        None => (close_code::POLICY, "Authentication required"),
+    };
+
+    // Flush what is queued, then say goodbye
+    drop(outbox);
+    if let Ok(Ok(mut sink)) = tokio::time::timeout(Duration::from_secs(5), writer).await {
+        let frame = CloseFrame { code: close.0, reason: close.1.into() };
+        let _ = sink.send(Message::Close(Some(frame))).await;
+    }
+}
+
+async fn write_loop(
+    mut sink: SplitSink<WebSocket, Message>,
+    mut queued: mpsc::Receiver<Message>,
+) -> SplitSink<WebSocket, Message> {
+    while let Some(message) = queued.recv().await {
+        if sink.send(message).await.is_err() {
+            break;
+        }
+    }
+    sink
+}
+
+// Wait for {"type": "auth", "token": "..."}
+async fn authenticate(
+    state: &AppState,
+    incoming: &mut futures::stream::SplitStream<WebSocket>,
+) -> Option<Claims> {
+    let message = tokio::time::timeout(AUTH_TIMEOUT, incoming.next()).await.ok()??.ok()?;
+    let Message::Text(text) = message else {
+        return None;
+    };
+    match serde_json::from_str::<ClientMessage>(&text) {
+        Ok(ClientMessage::Auth { token }) => verify_token(&token, &state.config.jwt_secret).ok(),
+        _ => None,
+    }
+}
+
+struct Connection {
+    state: AppState,
+    id: u64,
+    user_id: Uuid,
+    projects: ProjectRepository,
+    visibility: Visibility,
+    outbox: mpsc::Sender<Message>,
+    tasks: HashSet<Uuid>,
+    project_ids: HashSet<Uuid>,
+    // Set while subscribed to the user's watched tasks
+    watched: Option<WatchedTasks>,
+    last_seen: Instant,
+}
+
+type Close = (u16, &'static str);
+
+impl Connection {
+    async fn open(
+        state: &AppState,
+        claims: &Claims,
+        outbox: mpsc::Sender<Message>,
+    ) -> Result<Self> {
+        let projects = ProjectRepository::new(state.pool.clone());
+        let visibility = Visibility::load(&projects, claims).await?;
+
+        Ok(Self {
+            state: state.clone(),
+            id: state.presence.connection_id(),
+            user_id: claims.user_id()?,
+            projects,
+            visibility,
+            outbox,
+            tasks: HashSet::new(),
+            project_ids: HashSet::new(),
+            watched: None,
+            last_seen: Instant::now(),
+        })
+    }
+
+    // Run until the client leaves or has to be disconnected
+    async fn serve(&mut self, incoming: &mut futures::stream::SplitStream<WebSocket>) -> Close {
+        let mut events = self.state.events.subscribe();
+        let mut rooms = self.state.presence.subscribe();
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+        if let Err(close) = self.send(ServerMessage::Ready { user_id: self.user_id }) {
+            return close;
+        }
+
+        loop {
+            let outcome = tokio::select! {
+                message = incoming.next() => match message {
+                    Some(Ok(message)) => {
+                        self.last_seen = Instant::now();
+                        self.handle_message(message).await
+                    }
+                    // Closed by the client, or the connection broke
+                    _ => return (close_code::NORMAL, ""),
+                },
+                event = events.recv() => match event {
+                    Ok(event) => self.forward_event(event).await,
+                    Err(RecvError::Lagged(missed)) => self.send(ServerMessage::Lagged { missed }),
+                    Err(RecvError::Closed) => return (close_code::AWAY, "Server shutting down"),
+                },
+                message = rooms.recv() => match message {
+                    Ok(message) => {
+                        self.forward_room_message(message);
+                        Ok(())
+                    }
+                    // Presence and typing are best effort
+                    Err(RecvError::Lagged(_)) => Ok(()),
+                    Err(RecvError::Closed) => return (close_code::AWAY, "Server shutting down"),
+                },
+                _ = heartbeat.tick() => {
+                    if self.last_seen.elapsed() > CLIENT_TIMEOUT {
+                        return (close_code::AWAY, "Heartbeat timeout");
+                    }
+                    self.queue(Message::Ping(Default::default()))
+                }
+            };
+
+            if let Err(close) = outcome {
+                return close;
+            }
+        }
+    }
+
+    async fn handle_message(&mut self, message: Message) -> std::result::Result<(), Close> {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => return Err((close_code::NORMAL, "")),
+            // Pongs only keep the connection alive; axum answers pings itself
+            Message::Ping(_) | Message::Pong(_) => return Ok(()),
+            Message::Binary(_) => return self.error("Binary messages are not supported"),
+        };
+
+        let message = match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(message) => message,
+            Err(e) => return self.error(&format!("Invalid message: {}", e)),
+        };
+
+        match message {
+            ClientMessage::Auth { .. } => self.error("Already authenticated"),
+            ClientMessage::Subscribe {
+                task_id: Some(task_id),
+                project_id: None,
+                watching: false,
+            } => {
+                match self.task_visible(task_id).await {
+                    Ok(true) => {}
+                    Ok(false) => return self.error("Task not found"),
+                    Err(e) => return self.internal_error(e),
+                }
+                self.send(ServerMessage::Subscribed {
+                    task_id: Some(task_id),
+                    project_id: None,
+                    watching: false,
+                })?;
+                if self.tasks.insert(task_id) {
+                    // Everyone in the room, this client included, gets the new viewer list
+                    self.state.presence.join(task_id, self.id, self.user_id);
+                }
+                Ok(())
+            }
+            ClientMessage::Subscribe {
+                task_id: None,
+                project_id: Some(project_id),
+                watching: false,
+            } => {
+                match self.project_visible(project_id).await {
+                    Ok(true) => {}
+                    Ok(false) => return self.error("Project not found"),
+                    Err(e) => return self.internal_error(e),
+                }
+                self.project_ids.insert(project_id);
+                self.send(ServerMessage::Subscribed {
+                    task_id: None,
+                    project_id: Some(project_id),
+                    watching: false,
+                })
+            }
+            ClientMessage::Subscribe { task_id: None, project_id: None, watching: true } => {
+                if self.watched.is_none() {
+                    let repo = WatcherRepository::new(self.state.pool.clone());
+                    match WatchedTasks::load(&repo, self.user_id).await {
+                        Ok(watched) => self.watched = Some(watched),
+                        Err(e) => return self.internal_error(e),
+                    }
+                }
+                self.send(ServerMessage::Subscribed {
+                    task_id: None,
+                    project_id: None,
+                    watching: true,
+                })
+            }
+            ClientMessage::Unsubscribe {
+                task_id: Some(task_id),
+                project_id: None,
+                watching: false,
+            } => {
+                if self.tasks.remove(&task_id) {
+                    self.state.presence.leave(task_id, self.id);
+                }
+                self.send(ServerMessage::Unsubscribed {
+                    task_id: Some(task_id),
+                    project_id: None,
+                    watching: false,
+                })
+            }
+            ClientMessage::Unsubscribe {
+                task_id: None,
+                project_id: Some(project_id),
+                watching: false,
+            } => {
+                self.project_ids.remove(&project_id);
+                self.send(ServerMessage::Unsubscribed {
+                    task_id: None,
+                    project_id: Some(project_id),
+                    watching: false,
+                })
+            }
+            ClientMessage::Unsubscribe { task_id: None, project_id: None, watching: true } => {
+                self.watched = None;
+                self.send(ServerMessage::Unsubscribed {
+                    task_id: None,
+                    project_id: None,
+                    watching: true,
+                })
+            }
+            ClientMessage::Subscribe { .. } | ClientMessage::Unsubscribe { .. } => {
+                self.error("Give exactly one of task_id, project_id and watching")
+            }
+            ClientMessage::Typing { task_id } => {
+                if !self.tasks.contains(&task_id) {
+                    return self.error("Subscribe to the task first");
+                }
+                self.state.presence.typing(task_id, self.id, self.user_id);
+                Ok(())
+            }
+            ClientMessage::Ping => self.send(ServerMessage::Pong),
+        }
+    }
+
+    async fn forward_event(&mut self, event: TaskEvent) -> std::result::Result<(), Close> {
+        // Observed even when subscribed otherwise, to keep the watched set current
+        let watched = self.watched.as_mut().is_some_and(|watched| watched.observe(&event));
+        let subscribed = watched
+            || self.tasks.contains(&event.task_id)
+            || event.project_id.is_some_and(|id| self.project_ids.contains(&id));
+        if !subscribed {
+            return Ok(());
+        }
+        // Membership may have been revoked since the subscription
+        match self.visibility.allows(&self.projects, event.project_id).await {
+            Ok(true) => self.send(ServerMessage::Event { event }),
+            Ok(false) => Ok(()),
+            Err(e) => self.internal_error(e),
+        }
+    }
+
+    fn forward_room_message(&mut self, message: RoomMessage) {
+        let message = match message {
+            RoomMessage::Presence { task_id, viewers } if self.tasks.contains(&task_id) => {
+                ServerMessage::Presence { task_id, viewers }
+            }
+            RoomMessage::Typing { task_id, user_id, connection_id }
+                if self.tasks.contains(&task_id) && connection_id != self.id =>
+            {
+                ServerMessage::Typing { task_id, user_id }
+            }
+            _ => return,
+        };
+        // Dropped rather than queued when the client is behind
+        if let Ok(text) = serde_json::to_string(&message) {
+            let _ = self.outbox.try_send(Message::Text(text.into()));
+        }
+    }
+
+    async fn task_visible(&mut self, task_id: Uuid) -> Result<bool> {
+        let project = sqlx::query_scalar::<_, Option<Uuid>>(
+            "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(task_id)
+        .fetch_optional(&self.state.pool)
+        .await?;
+
+        match project {
+            Some(project_id) => self.visibility.allows(&self.projects, project_id).await,
+            None => Ok(false),
+        }
+    }
+
+    async fn project_visible(&mut self, project_id: Uuid) -> Result<bool> {
+        if self.projects.get(project_id).await?.is_none() {
+            return Ok(false);
+        }
+        self.visibility.allows(&self.projects, Some(project_id)).await
+    }
+
+    fn send(&self, message: ServerMessage) -> std::result::Result<(), Close> {
+        let text = serde_json::to_string(&message)
+            .map_err(|_| (close_code::ERROR, "Failed to encode message"))?;
+        self.queue(Message::Text(text.into()))
+    }
+
+    // A full outbox means the client does not keep up; it has to reconnect and resync
+    fn queue(&self, message: Message) -> std::result::Result<(), Close> {
+        match self.outbox.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err((close_code::AGAIN, "Client is too slow")),
+            Err(TrySendError::Closed(_)) => Err((close_code::NORMAL, "")),
+        }
+    }
+
+    fn error(&self, message: &str) -> std::result::Result<(), Close> {
+        self.send(ServerMessage::Error { message: message.to_string() })
+    }
+
+    fn internal_error(&self, e: AppError) -> std::result::Result<(), Close> {
+        tracing::error!("WebSocket request failed: {:?}", e);
+        self.error("Internal error")
+    }
+
+    fn leave_all(&mut self) {
+        for task_id in self.tasks.drain() {
+            self.state.presence.leave(task_id, self.id);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    config::Config,
+    database::audit_repo::AuditRepository,
+    error::{AppError, Result},
+    jobs::runner::Job,
+    models::{AuditArchive, AuditPartition},
+    state::AppState,
+};
+
+// Arbitrary key for the advisory lock that keeps two archival runs from overlapping
+const RETENTION_LOCK_KEY: i64 = 0x6175_6469_745f_7274;
+
+// How many months of partitions to create ahead of time
+const PARTITIONS_AHEAD: u32 = 2;
+
+// Apply the retention policy (scheduled every AUDIT_RETENTION_INTERVAL_SECS)
+#[derive(Serialize, Deserialize)]
+pub struct AuditRetention;
+
+impl Job for AuditRetention {
+    const KIND: &'static str = "audit_retention";
+    const TIMEOUT: Duration = Duration::from_secs(3600);
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let archives = run(&state.pool, &state.config).await?;
+        if !archives.is_empty() {
+            tracing::info!("Archived {} audit log partition(s)", archives.len());
+        }
+        Ok(())
+    }
+}
+
+// Create upcoming partitions, then archive and drop every partition that ended
+// before the retention cutoff. With `audit_retention_months = 6` in October, the six
+// full months April through September stay hot along with the current month, and
+// March and older are archived.
+pub async fn run(pool: &PgPool, config: &Config) -> Result<Vec<AuditArchive>> {
+    let mut lock_conn = pool.acquire().await?;
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+        .bind(RETENTION_LOCK_KEY)
+        .fetch_one(&mut *lock_conn)
+        .await?;
+
+    if !locked {
+        return Err(AppError::Conflict("Audit log archival is already running".to_string()));
+    }
+
+    let result = archive_expired(pool, config).await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(RETENTION_LOCK_KEY)
+        .execute(&mut *lock_conn)
+        .await?;
+
+    result
+}
+
+async fn archive_expired(pool: &PgPool, config: &Config) -> Result<Vec<AuditArchive>> {
+    let repo = AuditRepository::new(pool.clone());
+
+    let today = Utc::now().date_naive();
+    let current_month = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)
+        .ok_or_else(|| AppError::InternalError("Invalid current date".to_string()))?;
+    repo.ensure_partitions(current_month, PARTITIONS_AHEAD).await?;
+    repo.drain_default_partition().await?;
+
+    let cutoff = current_month - Months::new(config.audit_retention_months);
+
+    let mut archives = Vec::new();
+    for partition in repo.list_partitions().await? {
+        if partition.range_end <= cutoff {
+            archives.push(archive_partition(&repo, &partition, &config.audit_archive_dir).await?);
+        }
+    }
+
+    Ok(archives)
+}
+
+// Write the partition to `<dir>/<partition>.ndjson.gz`, then drop it.
+// The file is written under a temporary name and only renamed once it is complete,
+// so a crash never leaves a truncated archive next to a dropped partition.
+async fn archive_partition(
+    repo: &AuditRepository,
+    partition: &AuditPartition,
+    dir: &str,
+) -> Result<AuditArchive> {
+    fs::create_dir_all(dir).map_err(io_error)?;
+
+    let final_path = Path::new(dir).join(format!("{}.ndjson.gz", partition.partition_name));
+    let partial_path = final_path.with_extension("gz.partial");
+
+    let file = File::create(&partial_path).map_err(io_error)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+
+    let row_count = repo.export_partition(partition, &mut encoder).await?;
+
+    let file =
+        encoder.finish().map_err(io_error)?.into_inner().map_err(|e| io_error(e.into_error()))?;
+    file.sync_all().map_err(io_error)?;
+    fs::rename(&partial_path, &final_path).map_err(io_error)?;
+
+    let file_size = fs::metadata(&final_path).map_err(io_error)?.len() as i64;
+
+    tracing::info!(
+        "Archived {} ({} rows) to {}",
+        partition.partition_name,
+        row_count,
+        final_path.display()
+    );
+
+    repo.drop_archived_partition(partition, row_count, &final_path.to_string_lossy(), file_size)
+        .await
+}
+
+fn io_error(e: std::io::Error) -> AppError {
+    AppError::InternalError(format!("Audit archive I/O failed: {}", e))
+}
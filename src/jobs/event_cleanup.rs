@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::event_repo::EventRepository, error::Result, jobs::runner::Job, state::AppState,
+};
+
+// Drop task events older than EVENT_RETENTION_HOURS
+#[derive(Serialize, Deserialize)]
+pub struct EventCleanup;
+
+impl Job for EventCleanup {
+    const KIND: &'static str = "event_cleanup";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = EventRepository::new(state.pool);
+        let purged = repo.purge_older_than(state.config.event_retention_hours).await?;
+        if purged > 0 {
+            tracing::info!("Removed {} old task event(s)", purged);
+        }
+        Ok(())
+    }
+}
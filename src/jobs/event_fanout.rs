@@ -0,0 +1,63 @@
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+
+use crate::{database::event_repo::EventRepository, error::Result, state::AppState};
+
+const CHANNEL: &str = "task_events";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 500;
+
+// Listen for new task events and broadcast them to this instance's SSE connections.
+// Each notification carries the id of a committed event, which is then read from
+// the table. Ids are handed out before commit, so they are not read as a range
+// except to catch up on what was recorded while the listener was down.
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let repo = EventRepository::new(state.pool.clone());
+        let mut last_id = loop {
+            match repo.latest_id().await {
+                Ok(id) => break id,
+                Err(e) => {
+                    tracing::error!("Task event fan-out cannot start: {:?}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        };
+
+        loop {
+            if let Err(e) = listen(&state, &repo, &mut last_id).await {
+                tracing::error!("Task event listener failed, reconnecting: {:?}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn listen(state: &AppState, repo: &EventRepository, last_id: &mut i64) -> Result<()> {
+    let mut listener = PgListener::connect_with(&state.pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    loop {
+        let events = repo.list_after(*last_id, BATCH_SIZE).await?;
+        let done = (events.len() as i64) < BATCH_SIZE;
+        for event in events {
+            *last_id = event.id;
+            // No receivers just means nobody is connected right now
+            let _ = state.events.send(event);
+        }
+        if done {
+            break;
+        }
+    }
+
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(id) = notification.payload().parse::<i64>() else {
+            continue;
+        };
+        if let Some(event) = repo.get(id).await? {
+            *last_id = (*last_id).max(id);
+            let _ = state.events.send(event);
+        }
+    }
+}
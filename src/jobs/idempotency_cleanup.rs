@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::idempotency_repo::IdempotencyRepository, error::Result, jobs::runner::Job,
+    state::AppState,
+};
+
+// Expired keys are replaced lazily on reuse; this keeps the table from growing
+#[derive(Serialize, Deserialize)]
+pub struct IdempotencyCleanup;
+
+impl Job for IdempotencyCleanup {
+    const KIND: &'static str = "idempotency_cleanup";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let purged = IdempotencyRepository::new(state.pool).purge_expired().await?;
+        if purged > 0 {
+            tracing::info!("Removed {} expired idempotency key(s)", purged);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{database::job_repo::JobRepository, error::Result, jobs::runner::Job, state::AppState};
+
+// Drop succeeded and dead jobs older than JOB_RETENTION_DAYS
+#[derive(Serialize, Deserialize)]
+pub struct JobCleanup;
+
+impl Job for JobCleanup {
+    const KIND: &'static str = "job_cleanup";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = JobRepository::new(state.pool);
+        let purged = repo.purge_finished_older_than(state.config.job_retention_days).await?;
+        if purged > 0 {
+            tracing::info!("Removed {} finished job(s)", purged);
+        }
+        Ok(())
+    }
+}
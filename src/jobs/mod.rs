@@ -0,0 +1,57 @@
+pub mod audit_retention;
+pub mod event_cleanup;
+pub mod event_fanout;
+pub mod idempotency_cleanup;
+pub mod job_cleanup;
+pub mod notifications;
+pub mod overdue;
+pub mod recurrence;
+pub mod runner;
+pub mod trash_purge;
+pub mod webhook_delivery;
+
+use std::time::Duration;
+
+use crate::config::Config;
+use runner::{JobRegistry, Schedule};
+
+// Every job kind this server runs, and the recurring ones
+pub fn registry(config: &Config) -> JobRegistry {
+    let mut registry = JobRegistry::default();
+    registry
+        .schedule(
+            "audit_retention",
+            Ok(Schedule::Every(Duration::from_secs(config.audit_retention_interval_secs))),
+            audit_retention::AuditRetention,
+        )
+        .schedule(
+            "trash_purge",
+            Ok(Schedule::Every(Duration::from_secs(config.trash_purge_interval_secs))),
+            trash_purge::TrashPurge,
+        )
+        .schedule(
+            "idempotency_cleanup",
+            Schedule::cron("5 * * * *"),
+            idempotency_cleanup::IdempotencyCleanup,
+        )
+        .schedule("event_cleanup", Schedule::cron("10 * * * *"), event_cleanup::EventCleanup)
+        .schedule(
+            "webhook_delivery_cleanup",
+            Schedule::cron("15 * * * *"),
+            webhook_delivery::WebhookDeliveryCleanup,
+        )
+        .schedule("job_cleanup", Schedule::cron("20 3 * * *"), job_cleanup::JobCleanup)
+        .schedule(
+            "due_soon_notifications",
+            Schedule::cron("*/15 * * * *"),
+            notifications::DueSoonNotifications,
+        )
+        .schedule("overdue_check", Schedule::cron("*/15 * * * *"), overdue::OverdueCheck)
+        .schedule("recurrence_scan", Schedule::cron("* * * * *"), recurrence::RecurrenceScan)
+        .schedule(
+            "notification_cleanup",
+            Schedule::cron("25 3 * * *"),
+            notifications::NotificationCleanup,
+        );
+    registry
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::notification_repo::NotificationRepository, error::Result, jobs::runner::Job,
+    state::AppState,
+};
+
+// Remind people of their tasks due within their reminder window
+// (NOTIFY_DUE_SOON_HOURS unless they chose one)
+#[derive(Serialize, Deserialize)]
+pub struct DueSoonNotifications;
+
+impl Job for DueSoonNotifications {
+    const KIND: &'static str = "due_soon_notifications";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = NotificationRepository::new(state.pool);
+        let created = repo.notify_due_soon(state.config.notifications.due_soon_hours).await?;
+        if created > 0 {
+            tracing::info!("Sent {} due-soon notification(s)", created);
+        }
+        Ok(())
+    }
+}
+
+// Drop notifications older than NOTIFICATION_RETENTION_DAYS, read or not
+#[derive(Serialize, Deserialize)]
+pub struct NotificationCleanup;
+
+impl Job for NotificationCleanup {
+    const KIND: &'static str = "notification_cleanup";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = NotificationRepository::new(state.pool);
+        let purged = repo.purge_older_than(state.config.notifications.retention_days).await?;
+        if purged > 0 {
+            tracing::info!("Removed {} old notification(s)", purged);
+        }
+        Ok(())
+    }
+}
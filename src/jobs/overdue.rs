@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::overdue_repo::OverdueRepository, error::Result, jobs::runner::Job, state::AppState,
+};
+
+// Mark tasks that passed their due date and escalate the important ones
+#[derive(Serialize, Deserialize)]
+pub struct OverdueCheck;
+
+impl Job for OverdueCheck {
+    const KIND: &'static str = "overdue_check";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = OverdueRepository::new(state.pool);
+        let scan = repo.scan(state.config.notifications.escalate_overdue_after_hours).await?;
+        if scan.marked > 0 || scan.cleared > 0 || scan.escalated > 0 {
+            tracing::info!(
+                "{} task(s) went overdue, {} no longer are, {} escalated to their project owner",
+                scan.marked,
+                scan.cleared,
+                scan.escalated
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::recurrence_repo::RecurrenceRepository, error::Result, jobs::runner::Job,
+    state::AppState,
+};
+
+// Create the next occurrences of recurring tasks. Also queued right away when an
+// occurrence of an on_complete series is closed.
+#[derive(Serialize, Deserialize)]
+pub struct RecurrenceScan;
+
+impl Job for RecurrenceScan {
+    const KIND: &'static str = "recurrence_scan";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = RecurrenceRepository::new(state.pool);
+        let mut created = 0;
+        // One broken series must not hold up the others
+        for series_id in repo.due_series().await? {
+            match repo.generate_for(series_id).await {
+                Ok(count) => created += count,
+                Err(e) => tracing::error!(
+                    "Cannot create occurrences of recurring series {}: {:?}",
+                    series_id,
+                    e
+                ),
+            }
+        }
+        if created > 0 {
+            tracing::info!("Created {} recurring task occurrence(s)", created);
+        }
+        Ok(())
+    }
+}
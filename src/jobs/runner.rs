@@ -0,0 +1,398 @@
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use futures::future::BoxFuture;
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{postgres::PgListener, types::JsonValue};
+use std::{
+    collections::HashMap,
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{Notify, Semaphore, watch},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::job_repo::JobRepository,
+    error::{AppError, Result},
+    models::{JobRecord, NewJob},
+    state::AppState,
+};
+
+const CHANNEL: &str = "jobs_queued";
+// Due jobs (retries, scheduled runs) are also found without a notification
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Wait before the first retry; doubles with every further attempt
+const RETRY_BASE_SECS: i64 = 10;
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+// A kind of background work. The job itself is the payload, stored as JSON, so it
+// has to survive a round trip through serde. Jobs can run more than once (a worker
+// dies after the work but before recording it), so they should be idempotent.
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    // Stored in the jobs table; must stay the same across releases
+    const KIND: &'static str;
+    const MAX_ATTEMPTS: i32 = 5;
+    const TIMEOUT: Duration = Duration::from_secs(300);
+
+    fn run(self, state: AppState) -> impl Future<Output = Result<()>> + Send;
+
+    fn to_new_job(&self, run_at: Option<DateTime<Utc>>) -> Result<NewJob> {
+        let payload = serde_json::to_value(self).map_err(|e| {
+            AppError::InternalError(format!("Cannot encode {} job: {}", Self::KIND, e))
+        })?;
+
+        Ok(NewJob {
+            kind: Self::KIND,
+            payload,
+            max_attempts: Self::MAX_ATTEMPTS,
+            timeout_secs: Self::TIMEOUT.as_secs() as i32,
+            run_at,
+            schedule_name: None,
+        })
+    }
+}
+
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<Cron>),
+}
+
+impl Schedule {
+    // Standard five-field cron expression, in UTC
+    pub fn cron(expression: &str) -> Result<Self> {
+        let cron = Cron::from_str(expression).map_err(|e| {
+            AppError::InternalError(format!("Invalid cron expression '{}': {}", expression, e))
+        })?;
+        Ok(Self::Cron(Box::new(cron)))
+    }
+
+    // The next run after `now`. Runs missed while no instance was up are skipped.
+    fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Every(interval) => {
+                now + chrono::Duration::from_std(*interval).unwrap_or(chrono::Duration::MAX)
+            }
+            Self::Cron(cron) => {
+                cron.find_next_occurrence(&now, false).unwrap_or(now + chrono::Duration::days(365))
+            }
+        }
+    }
+
+    fn first_run(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            // Like a ticker: right away, then on every interval
+            Self::Every(_) => now,
+            Self::Cron(_) => self.next_after(now),
+        }
+    }
+}
+
+type Handler = Arc<dyn Fn(AppState, JsonValue) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct ScheduledJob {
+    name: &'static str,
+    schedule: Schedule,
+    job: NewJob,
+}
+
+// The job kinds this instance can run, and the recurring jobs it queues
+#[derive(Default)]
+pub struct JobRegistry {
+    handlers: HashMap<&'static str, Handler>,
+    schedules: Vec<ScheduledJob>,
+}
+
+impl JobRegistry {
+    pub fn register<J: Job>(&mut self) -> &mut Self {
+        let handler: Handler = Arc::new(|state, payload| {
+            Box::pin(async move {
+                let job = serde_json::from_value::<J>(payload).map_err(|e| {
+                    AppError::InternalError(format!("Invalid {} payload: {}", J::KIND, e))
+                })?;
+                job.run(state).await
+            })
+        });
+        self.handlers.insert(J::KIND, handler);
+        self
+    }
+
+    // Run `job` on `schedule`. A run is skipped while the previous one is pending. An
+    // invalid schedule is logged and left out; jobs of that kind still run when queued.
+    pub fn schedule<J: Job>(
+        &mut self,
+        name: &'static str,
+        schedule: Result<Schedule>,
+        job: J,
+    ) -> &mut Self {
+        let schedule = match schedule {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                tracing::error!("Skipping job schedule {}: {:?}", name, e);
+                return self.register::<J>();
+            }
+        };
+        let mut new_job = job.to_new_job(None).expect("scheduled job payloads are serializable");
+        new_job.schedule_name = Some(name);
+        self.schedules.push(ScheduledJob { name, schedule, job: new_job });
+        self.register::<J>()
+    }
+}
+
+struct Runner {
+    state: AppState,
+    repo: JobRepository,
+    registry: JobRegistry,
+    // Identifies this instance's claims; released on shutdown
+    worker: String,
+    slots: Arc<Semaphore>,
+    concurrency: usize,
+    // A job finished, so a slot is free again
+    finished: Notify,
+}
+
+// Runs queued jobs in the background until shut down
+pub struct JobRunner {
+    runner: Arc<Runner>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl JobRunner {
+    pub fn start(state: AppState, registry: JobRegistry) -> Self {
+        let concurrency = state.config.job_concurrency.max(1);
+        let runner = Arc::new(Runner {
+            repo: JobRepository::new(state.pool.clone()),
+            state,
+            registry,
+            worker: format!("{}-{}", std::process::id(), &Uuid::new_v4().simple().to_string()[..8]),
+            slots: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            finished: Notify::new(),
+        });
+        let (stop, stopped) = watch::channel(false);
+        let task = tokio::spawn(runner.clone().run(stopped));
+
+        Self { runner, stop, task }
+    }
+
+    // Stop taking jobs and wait up to `grace` for the running ones. Jobs still
+    // running after that go back to the queue for another instance.
+    pub async fn shutdown(self, grace: Duration) {
+        let _ = self.stop.send(true);
+        let _ = self.task.await;
+
+        let all_slots = self.runner.slots.acquire_many(self.runner.concurrency as u32);
+        if tokio::time::timeout(grace, all_slots).await.is_ok() {
+            tracing::info!("Job runner stopped");
+            return;
+        }
+        match self.runner.repo.release(&self.runner.worker).await {
+            Ok(released) => {
+                tracing::warn!("Job runner stopped; {} unfinished job(s) requeued", released)
+            }
+            Err(e) => tracing::error!("Cannot requeue unfinished jobs: {:?}", e),
+        }
+    }
+}
+
+impl Runner {
+    async fn run(self: Arc<Self>, mut stopped: watch::Receiver<bool>) {
+        self.register_schedules().await;
+
+        let mut listener = None;
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+
+        while !*stopped.borrow() {
+            if listener.is_none() {
+                listener = self.listen().await;
+            }
+
+            self.queue_due_schedules().await;
+            match self.repo.reap_expired().await {
+                Ok(0) => {}
+                Ok(reaped) => tracing::warn!("Took back {} job(s) from stopped workers", reaped),
+                Err(e) => tracing::error!("Cannot reap expired jobs: {:?}", e),
+            }
+            self.clone().dispatch().await;
+
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.finished.notified() => {}
+                changed = stopped.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                notification = next_notification(&mut listener) => {
+                    if let Err(e) = notification {
+                        tracing::error!("Job listener failed, polling until it reconnects: {:?}", e);
+                        listener = None;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn listen(&self) -> Option<PgListener> {
+        let result = async {
+            let mut listener = PgListener::connect_with(&self.state.pool).await?;
+            listener.listen(CHANNEL).await?;
+            Ok::<_, sqlx::Error>(listener)
+        }
+        .await;
+
+        result.map_err(|e| tracing::error!("Cannot listen for queued jobs: {:?}", e)).ok()
+    }
+
+    async fn register_schedules(&self) {
+        let now = Utc::now();
+        for scheduled in &self.registry.schedules {
+            let (cron, interval_secs) = match &scheduled.schedule {
+                Schedule::Every(interval) => (None, Some(interval.as_secs() as i64)),
+                Schedule::Cron(cron) => (Some(cron.pattern.to_string()), None),
+            };
+            if let Err(e) = self
+                .repo
+                .upsert_schedule(
+                    scheduled.name,
+                    scheduled.job.kind,
+                    &scheduled.job.payload,
+                    cron.as_deref(),
+                    interval_secs,
+                    scheduled.schedule.first_run(now),
+                )
+                .await
+            {
+                tracing::error!("Cannot register job schedule {}: {:?}", scheduled.name, e);
+            }
+        }
+    }
+
+    async fn queue_due_schedules(&self) {
+        if self.registry.schedules.is_empty() {
+            return;
+        }
+        if let Err(e) = self.try_queue_due_schedules().await {
+            tracing::error!("Cannot queue scheduled jobs: {:?}", e);
+        }
+    }
+
+    async fn try_queue_due_schedules(&self) -> Result<()> {
+        let names: Vec<&str> = self.registry.schedules.iter().map(|s| s.name).collect();
+        let mut tx = self.state.pool.begin().await?;
+
+        for due in JobRepository::lock_due_schedules_in(&mut tx, &names).await? {
+            let Some(scheduled) = self.registry.schedules.iter().find(|s| s.name == due.name)
+            else {
+                continue;
+            };
+            let job = JobRepository::enqueue_in(&mut tx, &scheduled.job).await?;
+            if job.is_none() {
+                tracing::warn!("Skipping a run of {}: the previous one is still pending", due.name);
+            }
+            let next_run_at = scheduled.schedule.next_after(Utc::now());
+            JobRepository::advance_schedule_in(&mut tx, &due.name, next_run_at, job.map(|j| j.id))
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Claim jobs while there are free slots
+    async fn dispatch(self: Arc<Self>) {
+        let kinds: Vec<&str> = self.registry.handlers.keys().copied().collect();
+
+        while let Ok(permit) = self.slots.clone().try_acquire_owned() {
+            let job = match self.repo.claim(&kinds, &self.worker).await {
+                Ok(Some(job)) => job,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Cannot claim a job: {:?}", e);
+                    break;
+                }
+            };
+
+            let runner = self.clone();
+            tokio::spawn(async move {
+                runner.execute(job).await;
+                drop(permit);
+                runner.finished.notify_one();
+            });
+        }
+    }
+
+    async fn execute(&self, job: JobRecord) {
+        let handler = self.registry.handlers[job.kind.as_str()].clone();
+        let timeout = Duration::from_secs(job.timeout_secs.max(1) as u64);
+        let started = Instant::now();
+
+        // In its own task, so that a panic fails the job instead of the runner
+        let task = tokio::spawn(handler(self.state.clone(), job.payload.clone()));
+        let abort = task.abort_handle();
+        let outcome = match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(Ok(()))) => Ok(()),
+            Ok(Ok(Err(e))) => Err(format!("{:?}", e)),
+            Ok(Err(e)) => Err(format!("Job panicked: {}", e)),
+            Err(_) => {
+                abort.abort();
+                Err(format!("Timed out after {}s", timeout.as_secs()))
+            }
+        };
+
+        let recorded = match outcome {
+            Ok(()) => {
+                tracing::debug!("Job {} {} done in {:?}", job.kind, job.id, started.elapsed());
+                self.repo.complete(job.id, &self.worker).await.map(|_| ())
+            }
+            Err(error) => {
+                let delay = RETRY_BASE_SECS
+                    .saturating_mul(1i64 << (job.attempts - 1).clamp(0, 20))
+                    .min(MAX_RETRY_DELAY_SECS);
+                match self.repo.fail(job.id, &self.worker, &error, delay).await {
+                    Ok(Some(status)) if status == "dead" => {
+                        tracing::error!(
+                            "Job {} {} failed for good after {} attempt(s): {}",
+                            job.kind,
+                            job.id,
+                            job.attempts,
+                            error
+                        );
+                        Ok(())
+                    }
+                    Ok(_) => {
+                        tracing::warn!(
+                            "Job {} {} failed (attempt {}/{}), retrying in {}s: {}",
+                            job.kind,
+                            job.id,
+                            job.attempts,
+                            job.max_attempts,
+                            delay,
+                            error
+                        );
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        if let Err(e) = recorded {
+            tracing::error!("Cannot record the outcome of job {}: {:?}", job.id, e);
+        }
+    }
+}
+
+async fn next_notification(listener: &mut Option<PgListener>) -> Result<()> {
+    match listener {
+        Some(listener) => {
+            listener.recv().await?;
+            Ok(())
+        }
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{error::Result, jobs::runner::Job, state::AppState};
+
+// Permanently delete trashed tasks once the retention window has passed (scheduled
+// every TRASH_PURGE_INTERVAL_SECS)
+#[derive(Serialize, Deserialize)]
+pub struct TrashPurge;
+
+impl Job for TrashPurge {
+    const KIND: &'static str = "trash_purge";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let purged = purge(&state.pool, state.config.trash_retention_days).await?;
+        if purged > 0 {
+            tracing::info!("Purged {} task(s) from the trash", purged);
+        }
+        Ok(())
+    }
+}
+
+// Dependency edges of purged tasks go with them (ON DELETE CASCADE). Tasks with time
+// entries stay in the trash, so the time spent on them is kept.
+pub async fn purge(pool: &PgPool, retention_days: i64) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM tasks
+        WHERE deleted_at IS NOT NULL
+          AND deleted_at < NOW() - make_interval(days => $1::INT)
+          AND NOT EXISTS (SELECT 1 FROM time_entries te WHERE te.task_id = tasks.id)
+        "#,
+    )
+    .bind(retention_days)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn tasks_with_time_entries_are_not_purged(pool: PgPool) {
+        let user: Uuid = sqlx::query_scalar(
+            "INSERT INTO users (email, password_hash) VALUES ('alice@example.com', '') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let mut tasks = Vec::new();
+        for title in ["tracked", "untracked"] {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO tasks (title, created_by, deleted_at)
+                VALUES ($1, $2, NOW() - INTERVAL '60 days')
+                RETURNING id
+                "#,
+            )
+            .bind(title)
+            .bind(user)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            tasks.push(id);
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO time_entries (task_id, user_id, started_at, ended_at, source)
+            VALUES ($1, $2, NOW() - INTERVAL '61 days', NOW() - INTERVAL '61 days', 'manual')
+            "#,
+        )
+        .bind(tasks[0])
+        .bind(user)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(purge(&pool, 30).await.unwrap(), 1);
+
+        let left: Vec<Uuid> =
+            sqlx::query_scalar("SELECT id FROM tasks").fetch_all(&pool).await.unwrap();
+        assert_eq!(left, vec![tasks[0]]);
+        let entries: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM time_entries").fetch_one(&pool).await.unwrap();
+        assert_eq!(entries, 1);
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::Utc;
+use futures::{StreamExt, stream};
+use reqwest::{Client, Url, header::CONTENT_TYPE, redirect::Policy};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    config::WebhookSettings,
+    database::webhook_repo::WebhookRepository,
+    error::Result,
+    jobs::runner::Job,
+    models::{AttemptOutcome, DueDelivery},
+    state::AppState,
+    utils::{
+        outbound::{PublicResolver, is_public, literal_ip},
+        webhook_signature::sign,
+    },
+};
+
+// Due deliveries are also picked up without a new event (retries, redeliveries)
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 32;
+// Requests in flight at a time
+const CONCURRENCY: usize = 8;
+
+// Send queued webhook deliveries. The queue is the webhook_deliveries table, filled
+// by a trigger on task_events, so nothing is lost while no instance is running.
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let settings = state.config.webhooks;
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_secs))
+            // A redirect is an answer like any other: the receiver should be updated
+            .redirect(Policy::none())
+            .user_agent(concat!("task-api-webhooks/", env!("CARGO_PKG_VERSION")));
+        if !settings.allow_private_targets {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = match builder.build() {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Webhook delivery cannot start: {:?}", e);
+                return;
+            }
+        };
+        let repo = WebhookRepository::new(state.pool.clone());
+        let mut events = state.events.subscribe();
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {}
+                // A new event usually comes with new deliveries
+                event = events.recv() => {
+                    if let Err(RecvError::Closed) = event {
+                        return;
+                    }
+                }
+            }
+
+            loop {
+                match send_due(&state, &repo, &client).await {
+                    Ok(sent) if sent as i64 == BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!("Webhook delivery failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+            // Everything that arrived meanwhile was just sent
+            events = events.resubscribe();
+        }
+    });
+}
+
+// Send one batch of due deliveries; returns how many were claimed
+async fn send_due(state: &AppState, repo: &WebhookRepository, client: &Client) -> Result<usize> {
+    let settings = &state.config.webhooks;
+    // Long enough for a request to time out before anyone else retries it
+    let lease_secs = settings.timeout_secs as i64 * 2 + 60;
+
+    let due = repo.claim_due(BATCH_SIZE, lease_secs).await?;
+    let claimed = due.len();
+
+    stream::iter(due)
+        .for_each_concurrent(CONCURRENCY, |delivery| async move {
+            let outcome = deliver(client, settings, &delivery).await;
+            match repo.record_attempt(settings, &delivery, &outcome).await {
+                Ok(true) => tracing::warn!(
+                    "Webhook {} disabled after {} failed delivery attempts in a row",
+                    delivery.webhook_id,
+                    settings.disable_after
+                ),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::error!("Cannot record webhook delivery {}: {:?}", delivery.id, e)
+                }
+            }
+        })
+        .await;
+
+    Ok(claimed)
+}
+
+async fn deliver(
+    client: &Client,
+    settings: &WebhookSettings,
+    delivery: &DueDelivery,
+) -> AttemptOutcome {
+    // Names are checked by the resolver; addresses in the URL are not resolved
+    if !settings.allow_private_targets {
+        let literal = Url::parse(&delivery.url).ok().and_then(|url| literal_ip(&url));
+        if let Some(ip) = literal.filter(|ip| !is_public(*ip)) {
+            return AttemptOutcome {
+                response_status: None,
+                error: Some(format!("Refused: {} is a private or local address", ip)),
+                duration_ms: 0,
+            };
+        }
+    }
+
+    let body = delivery.payload.to_string().into_bytes();
+    let timestamp = Utc::now().timestamp();
+    let signature = sign(&delivery.secret, timestamp, &body);
+    let started = Instant::now();
+
+    let result = client
+        .post(&delivery.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Id", delivery.webhook_id.to_string())
+        .header("X-Webhook-Delivery", delivery.id.to_string())
+        .header("X-Webhook-Event", &delivery.event_type)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", signature)
+        .body(body)
+        .send()
+        .await;
+
+    // Only the status is kept: the receiver's response body is not shown back
+    let (response_status, error) = match result {
+        Ok(response) => (Some(response.status().as_u16() as i32), None),
+        Err(e) => (None, Some(describe(&e))),
+    };
+
+    AttemptOutcome {
+        response_status,
+        error,
+        duration_ms: started.elapsed().as_millis().min(i32::MAX as u128) as i32,
+    }
+}
+
+// Drop delivery logs older than WEBHOOK_DELIVERY_RETENTION_DAYS
+#[derive(Serialize, Deserialize)]
+pub struct WebhookDeliveryCleanup;
+
+impl Job for WebhookDeliveryCleanup {
+    const KIND: &'static str = "webhook_delivery_cleanup";
+
+    async fn run(self, state: AppState) -> Result<()> {
+        let repo = WebhookRepository::new(state.pool);
+        let purged =
+            repo.purge_deliveries_older_than(state.config.webhooks.delivery_retention_days).await?;
+        if purged > 0 {
+            tracing::info!("Removed {} old webhook deliveries", purged);
+        }
+        Ok(())
+    }
+}
+
+// reqwest's own message only says that sending failed; the cause is further down
+fn describe(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        return "Request timed out".to_string();
+    }
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
@@ -2,6 +2,7 @@ mod config;
 mod database;
 mod error;
 mod handlers;
+mod jobs;
 mod middleware;
 mod models;
 mod state;
@@ -9,11 +10,16 @@ mod utils;
 
 use axum::{
     Router, middleware as axum_middleware,
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
 };
+use jobs::runner::JobRunner;
 use state::AppState;
+use std::time::Duration;
+use tokio::{signal, sync::watch};
 use tower_http::cors::CorsLayer;
-use tracing_subscriber;
+
+const HTTP_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+const JOB_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() {
@@ -30,18 +36,33 @@ async fn main() {
 
     let app_state = AppState::new(pool, config.clone());
 
+    // Background work: queued and scheduled jobs, plus the long-running listeners
+    let job_runner = JobRunner::start(app_state.clone(), jobs::registry(&config));
+    jobs::event_fanout::spawn(app_state.clone());
+    jobs::webhook_delivery::spawn(app_state.clone());
+
     // Public routes (no authentication required)
     let public_routes = Router::new()
         .route("/health", get(handlers::health::health_check))
         .route("/auth/register", post(handlers::auth::register))
-        .route("/auth/login", post(handlers::auth::login));
+        .route("/auth/login", post(handlers::auth::login))
+        // Authenticates itself: browsers cannot set headers on a WebSocket
+        .route("/ws", get(handlers::ws::ws_handler))
+        // Authenticated by the secret token in the URL, for calendar apps
+        .route("/calendar/{file}", get(handlers::calendar::calendar_feed));
 
     // Protected routes (authentication required)
     let protected_routes = Router::new()
         .route("/api/tasks", get(handlers::tasks::list_tasks))
         .route("/api/tasks/{id}", get(handlers::tasks::get_task))
         .route("/api/tasks", post(handlers::tasks::create_task))
+        .route("/api/tasks/bulk", post(handlers::bulk::bulk_tasks))
+        .route("/api/tasks/ready", get(handlers::dependencies::get_ready_tasks))
+        .route("/api/tasks/overdue", get(handlers::tasks::list_overdue_tasks))
+        .route("/api/graph", get(handlers::dependencies::get_graph))
+        .route("/api/events", get(handlers::events::stream_events))
         .route("/api/tasks/{id}", put(handlers::tasks::update_task))
+        .route("/api/tasks/{id}", patch(handlers::tasks::patch_task))
         .route("/api/tasks/{id}", delete(handlers::tasks::delete_task))
         .route("/auth/whoami", get(handlers::auth::whoami))
         .route("/api/tasks/{id}/dependencies", post(handlers::dependencies::add_dependency))
@@ -51,9 +72,76 @@ async fn main() {
         )
         .route("/api/tasks/{id}/dependencies", get(handlers::dependencies::get_dependencies))
         .route("/api/tasks/{id}/blocked", get(handlers::dependencies::get_blocked_tasks))
+        .route("/api/tasks/{id}/schedule", get(handlers::dependencies::get_schedule))
+        .route("/api/tasks/{id}/unblocks", get(handlers::dependencies::get_unblocks))
+        .route("/api/projects", post(handlers::projects::create_project))
+        .route("/api/projects", get(handlers::projects::list_projects))
+        .route("/api/projects/{id}", get(handlers::projects::get_project))
+        .route("/api/projects/{id}/members", get(handlers::projects::list_members))
+        .route("/api/projects/{id}/members", post(handlers::projects::add_member))
+        .route("/api/projects/{id}/members/{user_id}", delete(handlers::projects::remove_member))
+        .route("/api/webhooks", post(handlers::webhooks::create_webhook))
+        .route("/api/webhooks", get(handlers::webhooks::list_webhooks))
+        .route("/api/webhooks/{id}", get(handlers::webhooks::get_webhook))
+        .route("/api/webhooks/{id}", patch(handlers::webhooks::update_webhook))
+        .route("/api/webhooks/{id}", delete(handlers::webhooks::delete_webhook))
+        .route("/api/webhooks/{id}/ping", post(handlers::webhooks::ping_webhook))
+        .route("/api/webhooks/{id}/deliveries", get(handlers::webhooks::list_deliveries))
+        .route("/api/webhooks/{id}/deliveries/{delivery_id}", get(handlers::webhooks::get_delivery))
+        .route(
+            "/api/webhooks/{id}/deliveries/{delivery_id}/redeliver",
+            post(handlers::webhooks::redeliver),
+        )
+        .route("/api/notifications", get(handlers::notifications::list_notifications))
+        .route("/api/notifications/unread-count", get(handlers::notifications::get_unread_count))
+        .route("/api/notifications/read-all", post(handlers::notifications::mark_all_read))
+        .route("/api/notifications/preferences", get(handlers::notifications::get_preferences))
+        .route("/api/notifications/preferences", put(handlers::notifications::update_preferences))
+        .route("/api/notifications/reminders", get(handlers::notifications::get_reminders))
+        .route("/api/notifications/reminders", put(handlers::notifications::update_reminders))
+        .route("/api/notifications/{id}/read", post(handlers::notifications::mark_read))
+        .route("/api/tasks/{id}/blocked/all", get(handlers::dependencies::get_all_blocked_tasks))
+        .route("/api/tasks/{id}/watchers", get(handlers::watchers::list_watchers))
+        .route("/api/tasks/{id}/watchers", post(handlers::watchers::watch_task))
+        .route("/api/tasks/{id}/watchers", delete(handlers::watchers::unwatch_task))
+        .route("/api/watching", get(handlers::watchers::list_watched_tasks))
+        .route("/api/calendar/token", get(handlers::calendar::get_token))
+        .route("/api/calendar/token", post(handlers::calendar::create_token))
+        .route("/api/calendar/token", delete(handlers::calendar::revoke_token))
+        .route("/api/tasks/{id}/recurrence", post(handlers::recurrence::create_recurrence))
+        .route("/api/tasks/{id}/recurrence", get(handlers::recurrence::get_task_recurrence))
+        .route("/api/tasks/{id}/recurrence", patch(handlers::recurrence::update_recurrence))
+        .route("/api/recurrences", get(handlers::recurrence::list_recurrences))
+        .route("/api/recurrences/{id}", get(handlers::recurrence::get_recurrence))
+        .route("/api/recurrences/{id}", delete(handlers::recurrence::stop_recurrence))
+        .route("/api/tasks/{id}/timer", post(handlers::time::start_timer))
+        .route("/api/timer", get(handlers::time::get_timer))
+        .route("/api/timer/stop", post(handlers::time::stop_timer))
+        .route("/api/tasks/{id}/time-entries", get(handlers::time::list_task_time_entries))
+        .route("/api/tasks/{id}/time-entries", post(handlers::time::create_time_entry))
+        .route("/api/tasks/{id}/time", get(handlers::time::get_task_time))
+        .route("/api/time-entries/{id}", patch(handlers::time::update_time_entry))
+        .route("/api/time-entries/{id}", delete(handlers::time::delete_time_entry))
+        .route("/api/time-entries/{id}/history", get(handlers::time::get_time_entry_history))
+        .route("/api/timesheets", get(handlers::time::get_timesheet))
+        .route("/api/projects/{id}/time", get(handlers::time::get_project_time))
+        .route("/api/tasks/{id}/children", get(handlers::subtasks::get_children))
+        .route("/api/tasks/{id}/subtree", get(handlers::subtasks::get_subtree))
         .route("/api/tasks/{id}/history", get(handlers::audit::get_task_history))
+        .route("/api/tasks/{id}/restore", post(handlers::tasks::restore_task))
+        .route("/api/trash", get(handlers::tasks::list_trash))
+        .route("/api/tasks/{id}/as-of", get(handlers::tasks::get_task_as_of))
+        .route("/api/tasks/{id}/revert/{audit_id}", post(handlers::tasks::revert_task))
         .route("/api/audit/user-activity", get(handlers::audit::get_user_activity))
-        .route("/api/tasks/{id}/dependencies/all", get(handlers::dependencies::get_all_dependencies))
+        .route(
+            "/api/tasks/{id}/dependencies/all",
+            get(handlers::dependencies::get_all_dependencies),
+        )
+        // Runs after auth, so idempotency keys are scoped to the caller
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::idempotency::idempotency_middleware,
+        ))
         .layer(axum_middleware::from_fn_with_state(
             app_state.clone(),
             middleware::auth::auth_middleware,
@@ -63,7 +151,18 @@ async fn main() {
     let admin_routes = Router::new()
         .route("/admin/tasks/{id}", delete(handlers::tasks::admin_delete_any_task))
         .route("/admin/audit/recent", get(handlers::audit::get_recent_activity))
+        .route("/admin/audit/partitions", get(handlers::audit::get_audit_partitions))
+        .route("/admin/audit/archive", post(handlers::audit::archive_audit_partitions))
+        .route("/admin/jobs", get(handlers::jobs::list_jobs))
+        .route("/admin/jobs/stats", get(handlers::jobs::get_job_stats))
+        .route("/admin/jobs/schedules", get(handlers::jobs::list_job_schedules))
+        .route("/admin/jobs/{id}", get(handlers::jobs::get_job))
+        .route("/admin/jobs/{id}/retry", post(handlers::jobs::retry_job))
         .layer(axum_middleware::from_fn(middleware::auth::admin_middleware))
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::idempotency::idempotency_middleware,
+        ))
         .layer(axum_middleware::from_fn_with_state(
             app_state.clone(),
             middleware::auth::auth_middleware,
@@ -83,5 +182,47 @@ async fn main() {
 
     let listener = tokio::net::TcpListener::bind(&addr).await.expect("Failed to bind address");
 
-    axum::serve(listener, app).await.expect("Server error");
+    // On shutdown, stop accepting requests and give open ones (event streams and
+    // WebSockets never finish on their own) a moment, then let running jobs finish
+    let (stop, mut stopped) = watch::channel(false);
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = stopped.changed().await;
+            })
+            .await
+    });
+
+    shutdown_signal().await;
+    tracing::info!("Shutting down");
+    let _ = stop.send(true);
+
+    let (served, _) = tokio::join!(
+        tokio::time::timeout(HTTP_DRAIN_TIMEOUT, server),
+        job_runner.shutdown(JOB_SHUTDOWN_TIMEOUT),
+    );
+    match served {
+        Ok(Ok(Ok(()))) => {}
+        Ok(Ok(Err(e))) => tracing::error!("Server error: {:?}", e),
+        Ok(Err(e)) => tracing::error!("Server task failed: {:?}", e),
+        Err(_) => tracing::warn!("Closing connections still open after {:?}", HTTP_DRAIN_TIMEOUT),
+    }
+}
+
+// Ctrl+C, or SIGTERM from the process manager
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
@@ -39,7 +39,7 @@ pub async fn auth_middleware(
 }
 
 // Optional: Middleware to check for admin role
-pub async fn admin_middleware(mut request: Request, next: Next) -> Result<Response, AppError> {
+pub async fn admin_middleware(request: Request, next: Next) -> Result<Response, AppError> {
     // Get claims from extensions (added by auth_middleware)
     let claims = request
         .extensions()
@@ -0,0 +1,225 @@
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::{task::AbortHandle, time::interval};
+use uuid::Uuid;
+
+use crate::{
+    database::idempotency_repo::IdempotencyRepository, error::AppError, models::IdempotencyRecord,
+    state::AppState, utils::jwt::Claims,
+};
+
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+const MAX_KEY_LENGTH: usize = 255;
+// Request and response bodies larger than this are not accepted for idempotent requests
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+// How long an in-flight request holds its key. The lease is renewed every third of
+// that while the request runs, so it only runs out when the server went away; a
+// retry after that runs again instead of being blocked until the key expires.
+const LEASE_SECS: i64 = 60;
+// Response headers that are stored and replayed along with the body
+const REPLAYED_HEADERS: [HeaderName; 3] = [header::CONTENT_TYPE, header::ETAG, header::LOCATION];
+
+// Middleware that makes mutating requests with an Idempotency-Key header safe to retry.
+// The first request runs normally and its response is stored; a retry with the same
+// key and body gets the stored response back, a retry with a different body is
+// rejected with 422. Keys are scoped per user, so it must run after auth_middleware;
+// it is not used on public routes, whose responses (tokens) must not be replayed.
+pub async fn idempotency_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let is_mutating =
+        matches!(*request.method(), Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+    let scope = request.extensions().get::<Claims>().map(|claims| claims.sub.clone());
+    let (key, scope) = match (request.headers().get(IDEMPOTENCY_KEY), scope) {
+        (Some(key), Some(scope)) if is_mutating => (key, scope),
+        _ => return Ok(next.run(request).await),
+    };
+
+    let key = key
+        .to_str()
+        .ok()
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_KEY_LENGTH
+            ))
+        })?
+        .to_string();
+
+    // Buffer the body so it can be hashed and still handed to the handler
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::BadRequest("Request body too large".to_string()))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(b" ");
+    hasher.update(parts.uri.path_and_query().map_or("/", |path| path.as_str()));
+    hasher.update(b"\n");
+    hasher.update(&body);
+    let request_hash = hex::encode(hasher.finalize());
+
+    let repo = IdempotencyRepository::new(state.pool.clone());
+    let lease_id = Uuid::new_v4();
+    let ttl_hours = state.config.idempotency_ttl_hours;
+
+    if !repo.reserve(&scope, &key, &request_hash, lease_id, LEASE_SECS, ttl_hours).await? {
+        let record = repo.get(&scope, &key).await?.ok_or_else(|| {
+            AppError::Conflict("Idempotency key is being reused, retry the request".to_string())
+        })?;
+        return replay(record, &request_hash);
+    }
+    // Keeps the lease while the handler runs, and stores a marker response if this
+    // request ends without one (the client disconnects or the handler panics)
+    let reservation = Reservation::new(state.pool.clone(), scope, key, lease_id);
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    // Server errors are not stored, so the client can retry with the same key
+    if response.status().is_server_error() {
+        reservation.release().await?;
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        // The request has been carried out, so the key is not given back for a retry
+        // to run it again: its status is kept, without the body
+        Err(_) => {
+            tracing::warn!("Response to idempotent request too large to store, dropping its body");
+            parts.headers.remove(header::CONTENT_TYPE);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Bytes::new()
+        }
+    };
+
+    let mut stored_headers = Map::new();
+    for name in REPLAYED_HEADERS {
+        if let Some(value) = parts.headers.get(&name).and_then(|v| v.to_str().ok()) {
+            stored_headers.insert(name.to_string(), Value::String(value.to_string()));
+        }
+    }
+    reservation.complete(parts.status.as_u16(), Value::Object(stored_headers), &body).await?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+// A key held by the request in progress
+struct Reservation {
+    pool: PgPool,
+    scope: String,
+    key: String,
+    lease_id: Uuid,
+    done: bool,
+    renewal: AbortHandle,
+}
+
+impl Reservation {
+    fn new(pool: PgPool, scope: String, key: String, lease_id: Uuid) -> Self {
+        let repo = IdempotencyRepository::new(pool.clone());
+        let (renew_scope, renew_key) = (scope.clone(), key.clone());
+        let renewal = tokio::spawn(async move {
+            let mut ticks = interval(Duration::from_secs(LEASE_SECS as u64 / 3));
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                match repo.renew(&renew_scope, &renew_key, lease_id, LEASE_SECS).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        tracing::error!("Cannot renew idempotency key {}: {:?}", renew_key, e)
+                    }
+                }
+            }
+        })
+        .abort_handle();
+
+        Self { pool, scope, key, lease_id, done: false, renewal }
+    }
+
+    async fn complete(mut self, status: u16, headers: Value, body: &[u8]) -> Result<(), AppError> {
+        self.done = true;
+        IdempotencyRepository::new(self.pool.clone())
+            .complete(&self.scope, &self.key, self.lease_id, status, headers, body)
+            .await
+    }
+
+    async fn release(mut self) -> Result<(), AppError> {
+        self.done = true;
+        IdempotencyRepository::new(self.pool.clone())
+            .release(&self.scope, &self.key, self.lease_id)
+            .await
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.renewal.abort();
+        if self.done {
+            return;
+        }
+        // The handler may have committed before it was cut off, so the key is not given
+        // back: retries get an error instead of running the request a second time
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+        let repo = IdempotencyRepository::new(self.pool.clone());
+        let (scope, key, lease_id) = (self.scope.clone(), self.key.clone(), self.lease_id);
+        runtime.spawn(async move {
+            let headers = json!({ header::CONTENT_TYPE.as_str(): "application/json" });
+            let body = json!({
+                "error": "The original request was interrupted and its response was not stored"
+            });
+            let status = StatusCode::INTERNAL_SERVER_ERROR.as_u16();
+            if let Err(e) = repo
+                .complete(&scope, &key, lease_id, status, headers, body.to_string().as_bytes())
+                .await
+            {
+                tracing::error!("Cannot store idempotency key {}: {:?}", key, e);
+            }
+        });
+    }
+}
+
+fn replay(record: IdempotencyRecord, request_hash: &str) -> Result<Response, AppError> {
+    if record.request_hash != request_hash {
+        return Err(AppError::UnprocessableEntity(
+            "Idempotency key was already used with a different request".to_string(),
+        ));
+    }
+
+    let Some(status) = record.response_status else {
+        return Err(AppError::Conflict(
+            "A request with this idempotency key is still being processed".to_string(),
+        ));
+    };
+
+    let status = StatusCode::from_u16(status as u16)
+        .map_err(|_| AppError::InternalError("Invalid stored status code".to_string()))?;
+
+    let mut headers = HeaderMap::new();
+    if let Some(Value::Object(stored)) = record.response_headers {
+        for (name, value) in stored {
+            if let (Ok(name), Some(Ok(value))) =
+                (HeaderName::try_from(name.as_str()), value.as_str().map(HeaderValue::from_str))
+            {
+                headers.insert(name, value);
+            }
+        }
+    }
+    headers.insert("idempotent-replayed", HeaderValue::from_static("true"));
+
+    Ok((status, headers, record.response_body.unwrap_or_default()).into_response())
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use sqlx::types::JsonValue;
@@ -11,11 +11,16 @@ pub struct AuditLog {
     pub action: String,
     pub resource_type: String,
     pub resource_id: Uuid,
+    #[sqlx(rename = "old_values")]
     pub old_value: Option<JsonValue>,
+    #[sqlx(rename = "new_values")]
     pub new_value: Option<JsonValue>,
     pub ip_address: Option<std::net::IpAddr>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub batch_id: Option<Uuid>,
+    // The entry of the change that caused this one (automatic status propagation)
+    pub caused_by: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -25,12 +30,46 @@ pub struct AuditLogWithUser {
     pub action: String,
     pub resource_type: String,
     pub resource_id: Uuid,
+    #[sqlx(rename = "old_values")]
     pub old_value: Option<JsonValue>,
+    #[sqlx(rename = "new_values")]
     pub new_value: Option<JsonValue>,
     pub created_at: DateTime<Utc>,
+    pub batch_id: Option<Uuid>,
+    // The entry of the change that caused this one (automatic status propagation)
+    pub caused_by: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
 pub struct AuditQuery {
     pub limit: Option<i64>,
 }
+
+// A monthly partition of audit_logs that is still in the database
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditPartition {
+    pub partition_name: String,
+    pub range_start: NaiveDate,
+    pub range_end: NaiveDate,
+    pub estimated_rows: i64,
+    pub size_bytes: i64,
+}
+
+// A partition that was written to disk and dropped
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditArchive {
+    pub partition_name: String,
+    pub range_start: NaiveDate,
+    pub range_end: NaiveDate,
+    pub row_count: i64,
+    pub file_path: String,
+    pub file_size_bytes: i64,
+    pub archived_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditPartitionReport {
+    pub retention_months: u32,
+    pub partitions: Vec<AuditPartition>,
+    pub archives: Vec<AuditArchive>,
+}
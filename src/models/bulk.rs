@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::JsonValue;
+use uuid::Uuid;
+
+use super::{CreateTaskRequest, Task};
+
+#[derive(Debug, Deserialize)]
+pub struct BulkRequest {
+    #[serde(flatten)]
+    pub operation: BulkOperation,
+    // Target tasks for update/delete/relabel: either explicit IDs or a filter
+    pub ids: Option<Vec<Uuid>>,
+    pub filter: Option<BulkFilter>,
+    // Roll everything back if any item fails
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Create {
+        items: Vec<CreateTaskRequest>,
+    },
+    // `set` is a JSON Merge Patch applied to each task, like PATCH /api/tasks/{id}
+    Update {
+        set: JsonValue,
+    },
+    Delete,
+    Relabel {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+        set: Option<Vec<String>>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkFilter {
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub assigned_to: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemStatus {
+    Success,
+    ValidationError,
+    Forbidden,
+    DependencyBlocked,
+    NotFound,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkItemResult {
+    pub index: usize,
+    pub task_id: Option<Uuid>,
+    pub status: BulkItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkResponse {
+    pub batch_id: Uuid,
+    pub committed: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkItemResult>,
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct CalendarFeed {
+    pub created_at: DateTime<Utc>,
+    // When a calendar app last fetched it
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// Returned when a token is created; the token is not shown again
+#[derive(Debug, Serialize)]
+pub struct CalendarFeedWithToken {
+    #[serde(flatten)]
+    pub feed: CalendarFeed,
+    pub token: String,
+    // Path of the feed, to append to the server's address
+    pub path: String,
+}
+
+// Whose feed a token opens
+#[derive(Debug, FromRow)]
+pub struct CalendarOwner {
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    pub project_id: Option<Uuid>,
+    pub label: Option<String>,
+    // event (VEVENT, the default, for calendar apps) or todo (VTODO, for task apps)
+    pub component: Option<String>,
+}
@@ -3,16 +3,60 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::Task;
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct TaskDependency {
     pub task_id: Uuid,
     pub depends_on: Uuid,
     pub created_at: DateTime<Utc>,
+    pub kind: String,
+    pub lag_minutes: i32,
+}
+
+// How `task_id` relates to `depends_on`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    // Cannot start until the other task is completed
+    #[default]
+    FinishToStart,
+    // Cannot start until the other task has started
+    StartToStart,
+    // Cannot be completed until the other task is completed
+    FinishToFinish,
+    RelatesTo,
+    Duplicates,
+}
+
+impl DependencyKind {
+    // Kinds that constrain status changes (and therefore must not form cycles)
+    pub const BLOCKING: [&'static str; 3] =
+        ["finish_to_start", "start_to_start", "finish_to_finish"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DependencyKind::FinishToStart => "finish_to_start",
+            DependencyKind::StartToStart => "start_to_start",
+            DependencyKind::FinishToFinish => "finish_to_finish",
+            DependencyKind::RelatesTo => "relates_to",
+            DependencyKind::Duplicates => "duplicates",
+        }
+    }
+
+    pub fn is_blocking(self) -> bool {
+        Self::BLOCKING.contains(&self.as_str())
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AddDependencyRequest {
     pub depends_on: Uuid,
+    #[serde(default)]
+    pub kind: DependencyKind,
+    // Minutes to wait after the other task starts/finishes (blocking kinds only)
+    #[serde(default)]
+    pub lag_minutes: i32,
 }
 #[derive(Debug, Serialize, FromRow)]
 pub struct DependencyInfo {
@@ -21,19 +65,88 @@ pub struct DependencyInfo {
     pub depends_on: Uuid,
     pub depends_on_title: String,
     pub dependency_status: String,
+    pub kind: String,
+    pub lag_minutes: i32,
 }
 
-#[derive(Debug, Serialize)]
+// One link reached while walking the dependency graph from a task
+#[derive(Debug, Serialize, FromRow)]
 pub struct DependencyNode {
+    // The task this one was reached from (None for the starting task)
+    pub parent_id: Option<Uuid>,
     pub task_id: Uuid,
     pub title: String,
     pub status: String,
+    pub kind: Option<String>,
+    // Length of the shortest path from the starting task
+    pub depth: i32,
+    // Direct blocking dependencies that do not allow this task to complete yet, each
+    // judged by its kind and lag
+    pub blocked_by: Vec<Uuid>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DependencyTree {
     pub task_id: Uuid,
     pub title: String,
+    pub status: String,
+    // Kind of the link to the parent node
+    pub kind: Option<String>,
+    pub depth: i32,
+    // Upstream: what this task depends on. Downstream: what depends on this task.
     pub dependencies: Vec<DependencyTree>,
     pub blocked_by: Vec<Uuid>,
+    // Reached through more than one path (a diamond). Such a task is expanded once,
+    // under a parent on its shortest path; other occurrences have no dependencies.
+    pub shared: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DependencyTreeQuery {
+    pub max_depth: Option<i32>,
+}
+
+// A task that can be worked on now, with how it was ranked
+#[derive(Debug, Serialize, FromRow)]
+pub struct ReadyTask {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub task: Task,
+    // Open tasks that transitively wait for this one
+    pub blocks_count: i64,
+    // priority weight (low 1 .. urgent 4) times (1 + blocks_count)
+    pub score: i64,
+    // Ready tasks that this one transitively waits for
+    #[serde(skip)]
+    pub waits_for: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadyQuery {
+    pub project_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    #[default]
+    Json,
+}
+
+// Which part of the dependency graph to export. Without a root every task matching
+// the filters is included; with one, only tasks within `max_depth` links of it.
+#[derive(Debug, Deserialize)]
+pub struct GraphQuery {
+    #[serde(default)]
+    pub format: GraphFormat,
+    pub project_id: Option<Uuid>,
+    pub root: Option<Uuid>,
+    pub max_depth: Option<i32>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub assigned_to: Option<Uuid>,
+    pub label: Option<String>,
 }
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::JsonValue};
+use uuid::Uuid;
+
+// A recorded task or dependency change (see the task_events migration)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TaskEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub task_id: Uuid,
+    pub project_id: Option<Uuid>,
+    // The task or dependency row after the change (before it, for removals)
+    pub payload: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventStreamQuery {
+    // For clients that cannot send a Last-Event-ID header on their first connection
+    pub last_event_id: Option<i64>,
+    // Only events about tasks the caller watches
+    #[serde(default)]
+    pub watching: bool,
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A live descendant of a task, as returned by the subtree query
+#[derive(Debug, FromRow)]
+pub struct SubtaskRow {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub title: String,
+    pub status: String,
+    pub priority: String,
+    pub assigned_to: Option<Uuid>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub depth: i32,
+}
+
+// Completion of everything below a task (not the task itself)
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+pub struct SubtaskProgress {
+    pub total_descendants: i64,
+    pub completed_descendants: i64,
+    // None for tasks without subtasks
+    pub percentage: Option<f64>,
+}
+
+impl SubtaskProgress {
+    pub fn new(total_descendants: i64, completed_descendants: i64) -> Self {
+        let percentage = (total_descendants > 0).then(|| {
+            (completed_descendants as f64 * 1000.0 / total_descendants as f64).round() / 10.0
+        });
+        Self { total_descendants, completed_descendants, percentage }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubtaskNode {
+    pub id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub priority: String,
+    pub assigned_to: Option<Uuid>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub depth: i32,
+    pub progress: SubtaskProgress,
+    pub children: Vec<SubtaskNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubtreeResponse {
+    pub task_id: Uuid,
+    pub progress: SubtaskProgress,
+    pub children: Vec<SubtaskNode>,
+}
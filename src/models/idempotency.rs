@@ -0,0 +1,10 @@
+use sqlx::{FromRow, types::JsonValue};
+
+// What is needed to answer a retried request
+#[derive(Debug, FromRow)]
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    pub response_status: Option<i16>,
+    pub response_headers: Option<JsonValue>,
+    pub response_body: Option<Vec<u8>>,
+}
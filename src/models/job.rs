@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::JsonValue};
+use uuid::Uuid;
+
+pub const JOB_STATUSES: [&str; 4] = ["queued", "running", "succeeded", "dead"];
+
+// A row of the jobs table (see the jobs migration)
+#[derive(Debug, Serialize, FromRow)]
+pub struct JobRecord {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: JsonValue,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub timeout_secs: i32,
+    // When the job may run next (the retry time for a failed attempt)
+    pub run_at: DateTime<Utc>,
+    pub schedule_name: Option<String>,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+    // Error of the last failed attempt
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+// A job to put in the queue
+#[derive(Debug)]
+pub struct NewJob {
+    pub kind: &'static str,
+    pub payload: JsonValue,
+    pub max_attempts: i32,
+    pub timeout_secs: i32,
+    // None: as soon as possible
+    pub run_at: Option<DateTime<Utc>>,
+    pub schedule_name: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct JobSchedule {
+    pub name: String,
+    pub kind: String,
+    pub payload: JsonValue,
+    pub cron: Option<String>,
+    pub interval_secs: Option<i64>,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_job_id: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct JobStats {
+    pub kind: String,
+    pub status: String,
+    pub count: i64,
+    pub oldest_run_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobQuery {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
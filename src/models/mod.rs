@@ -1,13 +1,61 @@
 pub mod audit;
 pub mod auth;
+pub mod bulk;
+pub mod calendar;
 mod dependency;
+pub mod event;
+pub mod hierarchy;
+pub mod idempotency;
+pub mod job;
+pub mod notification;
+pub mod project;
+pub mod realtime;
+pub mod recurrence;
+pub mod schedule;
 pub mod task;
+pub mod time_entry;
 pub mod user;
+pub mod watcher;
+pub mod webhook;
 
-pub use audit::{AuditLog, AuditLogWithUser, AuditQuery};
+pub use audit::{
+    AuditArchive, AuditLog, AuditLogWithUser, AuditPartition, AuditPartitionReport, AuditQuery,
+};
 pub use auth::{AuthResponse, LoginRequest, RegisterRequest};
+pub use bulk::{
+    BulkFilter, BulkItemResult, BulkItemStatus, BulkOperation, BulkRequest, BulkResponse,
+};
+pub use calendar::{CalendarFeed, CalendarFeedWithToken, CalendarOwner, CalendarQuery};
 pub use dependency::{
-    AddDependencyRequest, DependencyInfo, DependencyNode, DependencyTree, TaskDependency,
+    AddDependencyRequest, DependencyInfo, DependencyKind, DependencyNode, DependencyTree,
+    DependencyTreeQuery, GraphFormat, GraphQuery, ReadyQuery, ReadyTask, TaskDependency,
+};
+pub use event::{EventStreamQuery, TaskEvent};
+pub use hierarchy::{SubtaskNode, SubtaskProgress, SubtaskRow, SubtreeResponse};
+pub use idempotency::IdempotencyRecord;
+pub use job::{JOB_STATUSES, JobQuery, JobRecord, JobSchedule, JobStats, NewJob};
+pub use notification::{
+    NOTIFICATION_KINDS, Notification, NotificationPreferences, NotificationQuery, ReminderSettings,
+};
+pub use project::{AddMemberRequest, CreateProjectRequest, Project, ProjectMember};
+pub use realtime::{ClientMessage, ServerMessage, WsQuery};
+pub use recurrence::{
+    CreateRecurrenceRequest, GENERATE_MODES, SeriesDetail, SeriesOccurrence, SeriesQuery,
+    TaskSeries, UpdateRecurrenceRequest,
+};
+pub use schedule::{ScheduleLink, ScheduleResponse, ScheduleTaskRow, ScheduledTask};
+pub use task::{
+    AsOfQuery, CreateTaskRequest, OverdueQuery, OverdueTask, Task, TaskPatchDocument, TaskQuery,
+    UpdateTaskRequest, normalize_labels,
+};
+pub use time_entry::{
+    CreateTimeEntryRequest, DayTotal, ProjectTimeSummary, StartTimerRequest, TaskTimeSummary,
+    TaskTimeTotal, TimeEntry, Timesheet, TimesheetQuery, UpdateTimeEntryRequest, UserTimeTotal,
 };
-pub use task::{CreateTaskRequest, Task, TaskQuery, UpdateTaskRequest};
 pub use user::User;
+pub use watcher::TaskWatcher;
+pub use webhook::{
+    AttemptOutcome, CreateWebhookRequest, DeliveryQuery, DueDelivery, UpdateWebhookRequest,
+    WEBHOOK_EVENT_TYPES, Webhook, WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryDetail,
+    WebhookWithSecret,
+};
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::JsonValue};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use validator::Validate;
+
+// What a notification is about (see the notifications migration)
+pub const NOTIFICATION_KINDS: [&str; 7] =
+    ["assigned", "mentioned", "status_changed", "unblocked", "due_soon", "overdue", "escalated"];
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: String,
+    pub task_id: Uuid,
+    pub task_title: String,
+    // Who made the change; null for due_soon, overdue and escalated
+    pub actor_id: Option<Uuid>,
+    pub actor_email: Option<String>,
+    // Kind-specific: the status change, the previous assignee, the completed blocker, ...
+    pub details: JsonValue,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    // Only unread notifications
+    #[serde(default)]
+    pub unread: bool,
+    pub kind: Option<String>,
+    // Notifications created before this time, to page through older ones
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+// Whether each kind notifies, e.g. {"assigned": true, "due_soon": false}. Kinds
+// left out of an update keep their setting.
+pub type NotificationPreferences = BTreeMap<String, bool>;
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ReminderSettings {
+    // How long before a due date the due_soon reminder comes
+    #[validate(range(min = 1, max = 720, message = "hours_before_due must be between 1 and 720"))]
+    pub hours_before_due: i32,
+}
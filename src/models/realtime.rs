@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::TaskEvent;
+
+// Messages a WebSocket client sends, e.g. {"type": "subscribe", "task_id": "..."}
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    // First message, when the token was not passed as ?token=
+    Auth {
+        token: String,
+    },
+    // Exactly one of task_id, project_id and `"watching": true` (every task the user
+    // watches). Subscribing to a task also marks the user as viewing it.
+    Subscribe {
+        task_id: Option<Uuid>,
+        project_id: Option<Uuid>,
+        #[serde(default)]
+        watching: bool,
+    },
+    Unsubscribe {
+        task_id: Option<Uuid>,
+        project_id: Option<Uuid>,
+        #[serde(default)]
+        watching: bool,
+    },
+    // The user is typing a comment on a subscribed task
+    Typing {
+        task_id: Uuid,
+    },
+    Ping,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Ready {
+        user_id: Uuid,
+    },
+    Subscribed {
+        task_id: Option<Uuid>,
+        project_id: Option<Uuid>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        watching: bool,
+    },
+    Unsubscribed {
+        task_id: Option<Uuid>,
+        project_id: Option<Uuid>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        watching: bool,
+    },
+    // A change to a subscribed or watched task, or to a task of a subscribed project
+    Event {
+        event: TaskEvent,
+    },
+    // Everyone currently viewing the task
+    Presence {
+        task_id: Uuid,
+        viewers: Vec<Uuid>,
+    },
+    Typing {
+        task_id: Uuid,
+        user_id: Uuid,
+    },
+    // Events were dropped because the connection fell behind; refetch what you show
+    Lagged {
+        missed: u64,
+    },
+    Pong,
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    pub token: Option<String>,
+}
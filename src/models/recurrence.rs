@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+// When a series creates its next occurrence (see the recurring tasks migration)
+pub const GENERATE_MODES: [&str; 2] = ["on_complete", "on_schedule"];
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TaskSeries {
+    pub id: Uuid,
+    pub rrule: String,
+    pub dtstart: DateTime<Utc>,
+    pub generate: String,
+    pub lead_time_hours: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: String,
+    pub labels: Vec<String>,
+    pub assigned_to: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub estimated_minutes: Option<i32>,
+    pub copy_labels: bool,
+    pub copy_assignee: bool,
+    pub copy_dependencies: bool,
+    pub next_occurrence: i32,
+    pub next_due_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A task of a series
+#[derive(Debug, Serialize, FromRow)]
+pub struct SeriesOccurrence {
+    pub task_id: Uuid,
+    pub occurrence: i32,
+    // The due date the rule gave it
+    pub scheduled_for: DateTime<Utc>,
+    pub title: String,
+    pub status: String,
+    pub due_date: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeriesDetail {
+    #[serde(flatten)]
+    pub series: TaskSeries,
+    pub occurrences: Vec<SeriesOccurrence>,
+    // Due dates of the next occurrences the rule will create
+    pub upcoming: Vec<DateTime<Utc>>,
+}
+
+// Make a task the first occurrence of a series; the template is the task itself
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRecurrenceRequest {
+    pub rrule: String,
+    pub generate: Option<String>,
+    #[validate(range(
+        min = 1,
+        max = 8760,
+        message = "lead_time_hours must be between 1 and 8760"
+    ))]
+    pub lead_time_hours: Option<i32>,
+    pub copy_labels: Option<bool>,
+    pub copy_assignee: Option<bool>,
+    pub copy_dependencies: Option<bool>,
+}
+
+// A "this and future occurrences" edit: the rule and generation settings, and
+// template fields that are also applied to the open occurrences from this one on
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRecurrenceRequest {
+    pub rrule: Option<String>,
+    pub generate: Option<String>,
+    #[validate(range(
+        min = 1,
+        max = 8760,
+        message = "lead_time_hours must be between 1 and 8760"
+    ))]
+    pub lead_time_hours: Option<i32>,
+    pub copy_labels: Option<bool>,
+    pub copy_assignee: Option<bool>,
+    pub copy_dependencies: Option<bool>,
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Title must be between 1 and 255 characters"
+    ))]
+    pub title: Option<String>,
+    #[validate(length(max = 5000, message = "Description too long"))]
+    pub description: Option<String>,
+    pub priority: Option<String>,
+    pub labels: Option<Vec<String>>,
+    pub assigned_to: Option<Uuid>,
+    #[validate(range(min = 0, message = "Estimate cannot be negative"))]
+    pub estimated_minutes: Option<i32>,
+}
+
+impl UpdateRecurrenceRequest {
+    // Whether the edit changes the occurrences themselves, not just the series
+    pub fn changes_tasks(&self) -> bool {
+        self.title.is_some()
+            || self.description.is_some()
+            || self.priority.is_some()
+            || self.labels.is_some()
+            || self.assigned_to.is_some()
+            || self.estimated_minutes.is_some()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeriesQuery {
+    pub project_id: Option<Uuid>,
+    // Include stopped, finished and split series
+    #[serde(default)]
+    pub include_ended: bool,
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+// A task taking part in a schedule computation
+#[derive(Debug, FromRow)]
+pub struct ScheduleTaskRow {
+    pub id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub estimated_minutes: Option<i32>,
+}
+
+// A blocking link between two tasks of the schedule
+#[derive(Debug, FromRow)]
+pub struct ScheduleLink {
+    pub task_id: Uuid,
+    pub depends_on: Uuid,
+    pub kind: String,
+    pub lag_minutes: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledTask {
+    pub task_id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub duration_minutes: i64,
+    pub earliest_start: DateTime<Utc>,
+    pub earliest_finish: DateTime<Utc>,
+    pub latest_start: DateTime<Utc>,
+    pub latest_finish: DateTime<Utc>,
+    pub slack_minutes: i64,
+    pub critical: bool,
+    pub due_date: Option<DateTime<Utc>>,
+    // The task cannot finish by its due date, given what it waits for
+    pub due_date_infeasible: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleResponse {
+    pub task_id: Uuid,
+    pub computed_at: DateTime<Utc>,
+    // Earliest time everything in the schedule can be finished
+    pub finish: DateTime<Utc>,
+    // Open tasks with zero slack, in the order they have to happen
+    pub critical_path: Vec<Uuid>,
+    pub infeasible_due_dates: Vec<Uuid>,
+    // Open tasks without an estimate; they count as taking no time
+    pub unestimated: Vec<Uuid>,
+    // In dependency order
+    pub tasks: Vec<ScheduledTask>,
+}
@@ -2,7 +2,7 @@ use core::str;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, types::JsonValue};
 use uuid::Uuid;
 use validator::Validate;
 
@@ -19,6 +19,39 @@ pub struct Task {
     pub completed_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    // Older audit snapshots predate versioning
+    #[serde(default)]
+    pub version: i32,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub estimated_minutes: Option<i32>,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+}
+
+impl Task {
+    // Full copy of the row as stored in the audit log, so history can be replayed
+    pub fn snapshot(&self) -> JsonValue {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    // Its creator, its assignee and admins can change a task (provided they can see it)
+    pub fn editable_by(&self, user_id: Uuid, is_admin: bool) -> bool {
+        is_admin || self.created_by == Some(user_id) || self.assigned_to == Some(user_id)
+    }
+
+    // Snapshot as it was before being moved to the trash
+    pub fn live_snapshot(&self) -> JsonValue {
+        let mut snapshot = self.snapshot();
+        snapshot["deleted_at"] = JsonValue::Null;
+        snapshot
+    }
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -34,6 +67,12 @@ pub struct CreateTaskRequest {
     pub priority: Option<String>,
     pub assigned_to: Option<Uuid>,
     pub due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub parent_id: Option<Uuid>,
+    #[validate(range(min = 0, message = "Estimate cannot be negative"))]
+    pub estimated_minutes: Option<i32>,
+    pub project_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -46,6 +85,78 @@ pub struct UpdateTaskRequest {
     pub priority: Option<String>,
     pub assigned_to: Option<Uuid>,
     pub due_date: Option<DateTime<Utc>>,
+    pub parent_id: Option<Uuid>,
+    #[validate(range(min = 0))]
+    pub estimated_minutes: Option<i32>,
+    pub project_id: Option<Uuid>,
+}
+
+// The editable fields of a task. PATCH documents are applied to this shape, so
+// `null` clears a field and anything outside it is rejected.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct TaskPatchDocument {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Title must be between 1 and 255 characters"
+    ))]
+    pub title: String,
+    #[validate(length(max = 5000, message = "Description too long"))]
+    pub description: Option<String>,
+    pub status: String,
+    pub priority: String,
+    pub assigned_to: Option<Uuid>,
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Estimate cannot be negative"))]
+    pub estimated_minutes: Option<i32>,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+}
+
+impl TaskPatchDocument {
+    // Apply a JSON Merge Patch (RFC 7396) on top of this document
+    pub fn merged(&self, patch: &JsonValue) -> Result<Self, serde_json::Error> {
+        let mut document = serde_json::to_value(self)?;
+        json_patch::merge(&mut document, patch);
+        let mut merged: Self = serde_json::from_value(document)?;
+        merged.labels = normalize_labels(merged.labels);
+        Ok(merged)
+    }
+}
+
+impl From<&Task> for TaskPatchDocument {
+    fn from(task: &Task) -> Self {
+        Self {
+            title: task.title.clone(),
+            description: task.description.clone(),
+            status: task.status.clone(),
+            priority: task.priority.clone(),
+            assigned_to: task.assigned_to,
+            due_date: task.due_date,
+            labels: task.labels.clone(),
+            parent_id: task.parent_id,
+            estimated_minutes: task.estimated_minutes,
+            project_id: task.project_id,
+        }
+    }
+}
+
+// Labels are stored trimmed, de-duplicated and sorted
+pub fn normalize_labels(labels: Vec<String>) -> Vec<String> {
+    let mut labels: Vec<String> = labels
+        .into_iter()
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+        .collect();
+    labels.sort();
+    labels.dedup();
+    labels
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,3 +166,29 @@ pub struct TaskQuery {
     pub assigned_to: Option<Uuid>,
     pub created_by: Option<Uuid>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct AsOfQuery {
+    pub ts: DateTime<Utc>,
+}
+
+// An open task past its due date
+#[derive(Debug, Serialize, FromRow)]
+pub struct OverdueTask {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub task: Task,
+    pub overdue_minutes: i64,
+    // When the overdue scan noticed it (null until the next scan)
+    pub marked_at: Option<DateTime<Utc>>,
+    // When the project owner was told
+    pub escalated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OverdueQuery {
+    pub project_id: Option<Uuid>,
+    pub assigned_to: Option<Uuid>,
+    pub priority: Option<String>,
+    pub limit: Option<i64>,
+}
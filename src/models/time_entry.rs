@@ -0,0 +1,124 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::JsonValue};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TimeEntry {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub project_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub user_email: String,
+    pub started_at: DateTime<Utc>,
+    // Null while the timer runs
+    pub ended_at: Option<DateTime<Utc>>,
+    // Up to now for a running timer
+    pub minutes: i64,
+    pub note: Option<String>,
+    // timer or manual
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TimeEntry {
+    // Copy of the entry as stored in the audit log
+    pub fn snapshot(&self) -> JsonValue {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+// Log time after the fact: a start and either an end or a number of minutes
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTimeEntryRequest {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    #[validate(range(min = 1, max = 1440, message = "minutes must be between 1 and 1440"))]
+    pub minutes: Option<i64>,
+    #[validate(length(max = 1000, message = "Note too long"))]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct StartTimerRequest {
+    #[validate(length(max = 1000, message = "Note too long"))]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTimeEntryRequest {
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 1000, message = "Note too long"))]
+    pub note: Option<String>,
+}
+
+// A UTC date range, both ends included; entries count on the day they started
+#[derive(Debug, Deserialize)]
+pub struct TimesheetQuery {
+    pub user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    // json (the default) or csv
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DayTotal {
+    pub date: NaiveDate,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TaskTimeTotal {
+    pub task_id: Uuid,
+    pub title: String,
+    pub estimated_minutes: Option<i32>,
+    pub logged_minutes: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct UserTimeTotal {
+    pub user_id: Uuid,
+    pub email: String,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timesheet {
+    pub user_id: Uuid,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_minutes: i64,
+    pub days: Vec<DayTotal>,
+    pub tasks: Vec<TaskTimeTotal>,
+    pub entries: Vec<TimeEntry>,
+}
+
+// Estimate and logged time of a task, and of the task with all its subtasks
+#[derive(Debug, Serialize)]
+pub struct TaskTimeSummary {
+    pub task_id: Uuid,
+    pub estimated_minutes: Option<i32>,
+    pub logged_minutes: i64,
+    pub subtree_estimated_minutes: i64,
+    pub subtree_logged_minutes: i64,
+    // Subtree estimate minus subtree logged time; negative once over the estimate
+    pub remaining_minutes: i64,
+    pub users: Vec<UserTimeTotal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectTimeSummary {
+    pub project_id: Uuid,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub estimated_minutes: i64,
+    pub logged_minutes: i64,
+    pub tasks: Vec<TaskTimeTotal>,
+    pub users: Vec<UserTimeTotal>,
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TaskWatcher {
+    pub user_id: Uuid,
+    pub email: String,
+    // When they started watching
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::JsonValue};
+use uuid::Uuid;
+use validator::Validate;
+
+// Event types a webhook can subscribe to (see the task_events migration;
+// task.overdue comes from the overdue scan)
+pub const WEBHOOK_EVENT_TYPES: [&str; 10] = [
+    "task.created",
+    "task.updated",
+    "task.deleted",
+    "task.restored",
+    "task.overdue",
+    "dependency.added",
+    "dependency.updated",
+    "dependency.removed",
+    "watcher.added",
+    "watcher.removed",
+];
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub url: String,
+    // Empty: every event type
+    pub event_types: Vec<String>,
+    pub project_id: Option<Uuid>,
+    // Only shown once, when the webhook is created or the secret is replaced
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub active: bool,
+    pub consecutive_failures: i32,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A webhook together with its signing secret
+#[derive(Debug, Serialize)]
+pub struct WebhookWithSecret {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(length(
+        min = 1,
+        max = 2048,
+        message = "URL must be between 1 and 2048 characters"
+    ))]
+    pub url: String,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    pub project_id: Option<Uuid>,
+    // Generated when not given
+    #[validate(length(
+        min = 16,
+        max = 255,
+        message = "Secret must be between 16 and 255 characters"
+    ))]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWebhookRequest {
+    #[validate(length(
+        min = 1,
+        max = 2048,
+        message = "URL must be between 1 and 2048 characters"
+    ))]
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    // Re-enabling a webhook also resets its failure count
+    pub active: Option<bool>,
+    #[validate(length(
+        min = 16,
+        max = 255,
+        message = "Secret must be between 16 and 255 characters"
+    ))]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_id: Option<i64>,
+    pub event_type: String,
+    pub payload: JsonValue,
+    // pending, delivered or failed (gave up)
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub redelivery_of: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WebhookDeliveryAttempt {
+    pub attempt: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryDetail {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub attempts_log: Vec<WebhookDeliveryAttempt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// A delivery claimed by the sender, with what it needs to make the request
+#[derive(Debug, FromRow)]
+pub struct DueDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: JsonValue,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+// How one attempt went
+#[derive(Debug)]
+pub struct AttemptOutcome {
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+}
+
+impl AttemptOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.response_status.is_some_and(|status| (200..300).contains(&status))
+    }
+}
@@ -1,14 +1,25 @@
-use crate::config::Config;
+use crate::{config::Config, models::TaskEvent, utils::presence::PresenceHub};
 use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+// How many events a slow SSE client may fall behind before it has to catch up
+// from the database
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub config: Config,
+    // Task events received through LISTEN/NOTIFY, fanned out to SSE connections
+    pub events: broadcast::Sender<TaskEvent>,
+    // Who is viewing which task over WebSockets
+    pub presence: Arc<PresenceHub>,
 }
 
 impl AppState {
     pub fn new(pool: PgPool, config: Config) -> Self {
-        Self { pool, config }
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { pool, config, events, presence: Arc::new(PresenceHub::new()) }
     }
 }
@@ -0,0 +1,28 @@
+// CSV (RFC 4180) for spreadsheet exports
+use std::fmt::Write;
+
+pub fn write_row(out: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_field(out, field);
+    }
+    out.push_str("\r\n");
+}
+
+fn write_field(out: &mut String, field: &str) {
+    // Spreadsheets run cells starting with these as formulas (a leading tab or carriage
+    // return can be dropped on import, leaving a formula behind)
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+
+    if field.contains([',', '"', '\r', '\n']) {
+        let _ = write!(out, "\"{}\"", field.replace('"', "\"\""));
+    } else {
+        out.push_str(&field);
+    }
+}
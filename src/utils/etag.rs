@@ -0,0 +1,42 @@
+use axum::http::{HeaderMap, HeaderValue, header};
+
+use crate::{
+    error::{AppError, Result},
+    models::Task,
+};
+
+// Tasks are tagged with their row version, e.g. `"3"`
+pub fn etag(task: &Task) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", task.version)) {
+        headers.insert(header::ETAG, value);
+    }
+    headers
+}
+
+// Check the request's If-Match header against the current task.
+// Without the header the request goes through unless `required` is set.
+// Weak tags never match, as RFC 9110 requires strong comparison for If-Match.
+pub fn check_if_match(headers: &HeaderMap, current: &Task, required: bool) -> Result<()> {
+    let Some(if_match) = headers.get(header::IF_MATCH) else {
+        if required {
+            return Err(AppError::PreconditionRequired(
+                "If-Match header is required for this request".to_string(),
+            ));
+        }
+        return Ok(());
+    };
+
+    let if_match = if_match
+        .to_str()
+        .map_err(|_| AppError::PreconditionFailed(Box::new(current.snapshot())))?;
+
+    let current_tag = format!("\"{}\"", current.version);
+    let matches = if_match.split(',').map(str::trim).any(|tag| tag == "*" || tag == current_tag);
+
+    if !matches {
+        return Err(AppError::PreconditionFailed(Box::new(current.snapshot())));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+// Dependency graph rendering in DOT, Mermaid and JSON Graph Format.
+//
+// Self-contained on purpose: `bin/visualize_deps` includes this file with `#[path]`,
+// so it must not reach into the rest of the crate. Edges point from the dependency
+// to the task waiting on it, the direction work flows in.
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GraphNode {
+    pub id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub priority: String,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GraphEdge {
+    pub task_id: Uuid,
+    pub depends_on: Uuid,
+    pub kind: String,
+    pub lag_minutes: i32,
+}
+
+fn status_fill(status: &str) -> &'static str {
+    match status {
+        "completed" => "#bbf7d0",
+        "in_progress" => "#bfdbfe",
+        _ => "#e5e7eb",
+    }
+}
+
+fn priority_stroke(priority: &str) -> (&'static str, u8) {
+    match priority {
+        "urgent" => ("#dc2626", 3),
+        "high" => ("#ea580c", 2),
+        "medium" => ("#ca8a04", 1),
+        _ => ("#6b7280", 1),
+    }
+}
+
+fn is_blocking(kind: &str) -> bool {
+    matches!(kind, "finish_to_start" | "start_to_start" | "finish_to_finish")
+}
+
+// Only non-default links are labelled, to keep large graphs readable
+fn edge_label(edge: &GraphEdge) -> Option<String> {
+    let kind = match edge.kind.as_str() {
+        "finish_to_start" => None,
+        "start_to_start" => Some("SS"),
+        "finish_to_finish" => Some("FF"),
+        other => Some(other),
+    };
+    let lag = (edge.lag_minutes > 0).then(|| format!("+{}m", edge.lag_minutes));
+    match (kind, lag) {
+        (None, None) => None,
+        (Some(kind), None) => Some(kind.to_string()),
+        (None, Some(lag)) => Some(lag),
+        (Some(kind), Some(lag)) => Some(format!("{kind} {lag}")),
+    }
+}
+
+pub fn to_dot(nodes: &[GraphNode], edges: &[GraphEdge]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+
+    let mut out = String::from("digraph dependencies {\n");
+    out.push_str("  rankdir=LR;\n");
+    out.push_str("  node [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\"];\n");
+    for node in nodes {
+        let (stroke, width) = priority_stroke(&node.priority);
+        let _ = writeln!(
+            out,
+            "  \"{}\" [label=\"{}\\n{} · {}\", fillcolor=\"{}\", color=\"{}\", penwidth={}];",
+            node.id,
+            escape(&node.title),
+            node.status,
+            node.priority,
+            status_fill(&node.status),
+            stroke,
+            width,
+        );
+    }
+    for edge in edges {
+        let mut attrs = Vec::new();
+        if let Some(label) = edge_label(edge) {
+            attrs.push(format!("label=\"{}\"", escape(&label)));
+        }
+        if !is_blocking(&edge.kind) {
+            attrs.push("style=dashed".to_string());
+            attrs.push("arrowhead=none".to_string());
+        }
+        let attrs =
+            if attrs.is_empty() { String::new() } else { format!(" [{}]", attrs.join(", ")) };
+        let _ = writeln!(out, "  \"{}\" -> \"{}\"{};", edge.depends_on, edge.task_id, attrs);
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub fn to_mermaid(nodes: &[GraphNode], edges: &[GraphEdge]) -> String {
+    // Mermaid ids cannot contain dashes, so nodes get short positional ids
+    let ids: std::collections::HashMap<Uuid, String> =
+        nodes.iter().enumerate().map(|(i, node)| (node.id, format!("n{i}"))).collect();
+    let escape = |s: &str| s.replace('"', "#quot;");
+
+    let mut out = String::from("flowchart LR\n");
+    for node in nodes {
+        let _ = writeln!(
+            out,
+            "  {}[\"{}<br/>{} · {}\"]",
+            ids[&node.id],
+            escape(&node.title),
+            node.status,
+            node.priority,
+        );
+    }
+    for edge in edges {
+        let (Some(from), Some(to)) = (ids.get(&edge.depends_on), ids.get(&edge.task_id)) else {
+            continue;
+        };
+        let arrow = if is_blocking(&edge.kind) { "-->" } else { "-.-" };
+        match edge_label(edge) {
+            Some(label) => {
+                let _ = writeln!(out, "  {from} {arrow}|\"{}\"| {to}", escape(&label));
+            }
+            None => {
+                let _ = writeln!(out, "  {from} {arrow} {to}");
+            }
+        }
+    }
+    for node in nodes {
+        let (stroke, width) = priority_stroke(&node.priority);
+        let _ = writeln!(
+            out,
+            "  style {} fill:{},stroke:{},stroke-width:{}px",
+            ids[&node.id],
+            status_fill(&node.status),
+            stroke,
+            width,
+        );
+    }
+    out
+}
+
+// https://jsongraphformat.info (v2)
+pub fn to_json_graph(nodes: &[GraphNode], edges: &[GraphEdge]) -> Value {
+    let nodes: Map<String, Value> = nodes
+        .iter()
+        .map(|node| {
+            let (stroke, _) = priority_stroke(&node.priority);
+            let value = json!({
+                "label": node.title,
+                "metadata": {
+                    "status": node.status,
+                    "priority": node.priority,
+                    "fill": status_fill(&node.status),
+                    "stroke": stroke,
+                },
+            });
+            (node.id.to_string(), value)
+        })
+        .collect();
+    let edges: Vec<Value> = edges
+        .iter()
+        .map(|edge| {
+            json!({
+                "source": edge.depends_on,
+                "target": edge.task_id,
+                "relation": edge.kind,
+                "directed": is_blocking(&edge.kind),
+                "metadata": { "lag_minutes": edge.lag_minutes },
+            })
+        })
+        .collect();
+
+    json!({
+        "graph": {
+            "directed": true,
+            "type": "task dependencies",
+            "nodes": nodes,
+            "edges": edges,
+        }
+    })
+}
@@ -0,0 +1,80 @@
+// Writing iCalendar (RFC 5545) text: CRLF line endings, lines folded at 75 octets,
+// TEXT values escaped and times in UTC.
+use chrono::{DateTime, Utc};
+
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Default)]
+pub struct ICalWriter {
+    out: String,
+}
+
+impl ICalWriter {
+    pub fn begin(&mut self, component: &str) -> &mut Self {
+        self.line(&format!("BEGIN:{}", component))
+    }
+
+    pub fn end(&mut self, component: &str) -> &mut Self {
+        self.line(&format!("END:{}", component))
+    }
+
+    // A property whose value needs no escaping (numbers, codes, durations)
+    pub fn property(&mut self, name: &str, value: &str) -> &mut Self {
+        self.line(&format!("{}:{}", name, value))
+    }
+
+    // A TEXT property
+    pub fn text(&mut self, name: &str, value: &str) -> &mut Self {
+        self.property(name, &escape_text(value))
+    }
+
+    // A DATE-TIME property, in UTC
+    pub fn time(&mut self, name: &str, at: DateTime<Utc>) -> &mut Self {
+        self.property(name, &format_time(at))
+    }
+
+    // A multi-valued TEXT property such as CATEGORIES
+    pub fn list(&mut self, name: &str, values: &[String]) -> &mut Self {
+        let values: Vec<String> = values.iter().map(|value| escape_text(value)).collect();
+        self.property(name, &values.join(","))
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn line(&mut self, line: &str) -> &mut Self {
+        let mut octets = 0;
+        for c in line.chars() {
+            // Continuation lines start with a space, which counts towards the limit
+            if octets + c.len_utf8() > MAX_LINE_OCTETS {
+                self.out.push_str("\r\n ");
+                octets = 1;
+            }
+            self.out.push(c);
+            octets += c.len_utf8();
+        }
+        self.out.push_str("\r\n");
+        self
+    }
+}
+
+pub fn format_time(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1 +1,12 @@
+pub mod csv;
+pub mod etag;
+pub mod graph_export;
+pub mod ical;
 pub mod jwt;
+pub mod outbound;
+pub mod presence;
+pub mod ready;
+pub mod rrule;
+pub mod schedule;
+pub mod token;
+pub mod webhook_signature;
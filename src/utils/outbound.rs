@@ -0,0 +1,130 @@
+use reqwest::{
+    Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+};
+use std::net::IpAddr;
+
+use crate::error::{AppError, Result};
+
+// Requests to user-given URLs (webhooks) must not reach the server's own network.
+// Addresses are checked when a URL is saved and again on every request, by the
+// resolver below, so a name that later resolves elsewhere is still refused.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // "This network" and carrier-grade NAT
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10)
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+// The address in the URL, when its host is one rather than a name
+pub fn literal_ip(url: &Url) -> Option<IpAddr> {
+    let host = url.host_str()?;
+    // IPv6 hosts come in brackets
+    host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host).parse().ok()
+}
+
+// Resolve the URL's host and refuse it if any of its addresses is not public
+pub async fn check_public_url(url: &Url) -> Result<()> {
+    let refused = |ip: IpAddr| {
+        AppError::BadRequest(format!("URL points to a private or local address ({})", ip))
+    };
+    if let Some(ip) = literal_ip(url) {
+        return if is_public(ip) { Ok(()) } else { Err(refused(ip)) };
+    }
+
+    let host = url.host_str().ok_or_else(|| AppError::BadRequest("URL has no host".to_string()))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Cannot resolve '{}': {}", host, e)))?;
+    for addr in addrs {
+        if !is_public(addr.ip()) {
+            return Err(refused(addr.ip()));
+        }
+    }
+    Ok(())
+}
+
+// DNS resolver for the HTTP client that only hands out public addresses
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+                return Err(format!(
+                    "{} resolves to a private or local address ({})",
+                    host,
+                    addr.ip()
+                )
+                .into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_local_and_private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should be refused", ip);
+        }
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for ip in ["93.184.216.34", "8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be allowed", ip);
+        }
+    }
+
+    #[test]
+    fn finds_literal_hosts() {
+        let url = Url::parse("http://[::1]:8080/hook").unwrap();
+        assert_eq!(literal_ip(&url), Some("::1".parse().unwrap()));
+        let url = Url::parse("https://example.com/hook").unwrap();
+        assert_eq!(literal_ip(&url), None);
+    }
+}
@@ -0,0 +1,84 @@
+// Who is viewing which task, and the short-lived messages between them (presence
+// changes, typing indicators). Kept in memory: it is per API instance and gone on
+// restart, unlike task events.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+const ROOM_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum RoomMessage {
+    Presence { task_id: Uuid, viewers: Vec<Uuid> },
+    Typing { task_id: Uuid, user_id: Uuid, connection_id: u64 },
+}
+
+pub struct PresenceHub {
+    // task -> connection -> user
+    viewers: Mutex<HashMap<Uuid, HashMap<u64, Uuid>>>,
+    next_connection_id: AtomicU64,
+    messages: broadcast::Sender<RoomMessage>,
+}
+
+impl PresenceHub {
+    pub fn new() -> Self {
+        let (messages, _) = broadcast::channel(ROOM_CHANNEL_CAPACITY);
+        Self {
+            viewers: Mutex::new(HashMap::new()),
+            next_connection_id: AtomicU64::new(1),
+            messages,
+        }
+    }
+
+    pub fn connection_id(&self) -> u64 {
+        self.next_connection_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RoomMessage> {
+        self.messages.subscribe()
+    }
+
+    // Returns the task's viewers after joining
+    pub fn join(&self, task_id: Uuid, connection_id: u64, user_id: Uuid) -> Vec<Uuid> {
+        let viewers = {
+            let mut rooms = self.viewers.lock().unwrap_or_else(|e| e.into_inner());
+            let room = rooms.entry(task_id).or_default();
+            room.insert(connection_id, user_id);
+            distinct_users(room)
+        };
+        let _ = self.messages.send(RoomMessage::Presence { task_id, viewers: viewers.clone() });
+        viewers
+    }
+
+    pub fn leave(&self, task_id: Uuid, connection_id: u64) {
+        let viewers = {
+            let mut rooms = self.viewers.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(room) = rooms.get_mut(&task_id) else {
+                return;
+            };
+            if room.remove(&connection_id).is_none() {
+                return;
+            }
+            let viewers = distinct_users(room);
+            if room.is_empty() {
+                rooms.remove(&task_id);
+            }
+            viewers
+        };
+        let _ = self.messages.send(RoomMessage::Presence { task_id, viewers });
+    }
+
+    pub fn typing(&self, task_id: Uuid, connection_id: u64, user_id: Uuid) {
+        let _ = self.messages.send(RoomMessage::Typing { task_id, user_id, connection_id });
+    }
+}
+
+// A user with two tabs open is one viewer
+fn distinct_users(room: &HashMap<u64, Uuid>) -> Vec<Uuid> {
+    let mut users: Vec<Uuid> = room.values().copied().collect();
+    users.sort();
+    users.dedup();
+    users
+}
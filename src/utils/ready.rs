@@ -0,0 +1,103 @@
+// Ready queue ordering.
+//
+// Ready tasks can still wait for each other: a start_to_start successor is ready once
+// its predecessor has started, and both are open. The queue is a topological order of
+// the ready tasks that follows their rank (score, then due date, then age) wherever the
+// links leave a choice: each step takes the best ranked task whose ready predecessors
+// have all been placed already.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use uuid::Uuid;
+
+use crate::models::ReadyTask;
+
+// `ranked` comes best first; `waits_for` of each task holds the ready tasks upstream
+pub fn order(ranked: Vec<ReadyTask>, limit: usize) -> Vec<ReadyTask> {
+    let ids: Vec<Uuid> = ranked.iter().map(|ready| ready.task.id).collect();
+    let waits: Vec<&[Uuid]> = ranked.iter().map(|ready| ready.waits_for.as_slice()).collect();
+    let positions = topological(&ids, &waits);
+
+    let mut slots: Vec<Option<ReadyTask>> = ranked.into_iter().map(Some).collect();
+    positions.into_iter().take(limit).filter_map(|i| slots[i].take()).collect()
+}
+
+// Indices into `ids`, with every task after the ones it waits for and otherwise the
+// lowest index first. Waits on tasks outside `ids` are ignored.
+fn topological(ids: &[Uuid], waits: &[&[Uuid]]) -> Vec<usize> {
+    let index: HashMap<Uuid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut pending = vec![0usize; ids.len()];
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); ids.len()];
+    for (i, upstream) in waits.iter().enumerate() {
+        for id in upstream.iter() {
+            if let Some(&j) = index.get(id).filter(|&&j| j != i) {
+                pending[i] += 1;
+                successors[j].push(i);
+            }
+        }
+    }
+
+    let mut available: BinaryHeap<Reverse<usize>> =
+        (0..ids.len()).filter(|&i| pending[i] == 0).map(Reverse).collect();
+    let mut positions = Vec::with_capacity(ids.len());
+    while let Some(Reverse(i)) = available.pop() {
+        positions.push(i);
+        for &next in &successors[i] {
+            pending[next] -= 1;
+            if pending[next] == 0 {
+                available.push(Reverse(next));
+            }
+        }
+    }
+
+    // Links among ready tasks cannot form a cycle; should one appear anyway, the
+    // tasks on it keep their rank order at the end rather than disappearing
+    if positions.len() < ids.len() {
+        let stuck: Vec<usize> = (0..ids.len()).filter(|&i| pending[i] > 0).collect();
+        positions.extend(stuck);
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn unlinked_tasks_keep_their_rank() {
+        let ids = [id(1), id(2), id(3)];
+        assert_eq!(topological(&ids, &[&[], &[], &[]]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn predecessors_come_first_whatever_their_rank() {
+        // 1 (best ranked) waits for 3, which waits for 2
+        let ids = [id(1), id(2), id(3)];
+        let waits: [&[Uuid]; 3] = [&[id(3)], &[], &[id(2)]];
+        assert_eq!(topological(&ids, &waits), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn rank_decides_between_available_tasks() {
+        // 2 waits for 4, so it drops behind it; the others keep their rank
+        let ids = [id(1), id(2), id(3), id(4)];
+        let waits: [&[Uuid]; 4] = [&[], &[id(4)], &[], &[]];
+        assert_eq!(topological(&ids, &waits), vec![0, 2, 3, 1]);
+
+        // A diamond: 1 waits for 2 and 3, both wait for 4
+        let waits: [&[Uuid]; 4] = [&[id(2), id(3)], &[id(4)], &[id(4)], &[]];
+        assert_eq!(topological(&ids, &waits), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn waits_outside_the_queue_are_ignored() {
+        let ids = [id(1), id(2)];
+        let waits: [&[Uuid]; 2] = [&[id(9)], &[id(1)]];
+        assert_eq!(topological(&ids, &waits), vec![0, 1]);
+    }
+}
@@ -0,0 +1,458 @@
+// Recurrence rules: the subset of RFC 5545 RRULE that recurring tasks support.
+//
+//   FREQ=DAILY|WEEKLY|MONTHLY   required
+//   INTERVAL=n                  every n days/weeks/months (default 1)
+//   BYDAY=MO,WE,FR              weekdays; MONTHLY also takes 1MO, -1FR (first Monday,
+//                               last Friday of the month)
+//   UNTIL=20251231[T170000Z]    last possible occurrence (a date means the whole day)
+//   COUNT=n                     number of occurrences, not together with UNTIL
+//
+// Occurrences are computed in UTC from the first one (DTSTART, the due date of the
+// task the series starts from), at its time of day. As in RFC 5545, DTSTART is
+// always the first occurrence and counts towards COUNT. Weeks start on Monday, and
+// month days that do not exist (the 31st in April) are skipped.
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use crate::error::{AppError, Result};
+
+const MAX_INTERVAL: u32 = 1000;
+const MAX_COUNT: u32 = 1000;
+// Periods in a row without an occurrence before giving up (DAILY;INTERVAL=7;BYDAY=TU
+// from a Monday never matches)
+const MAX_EMPTY_PERIODS: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// A BYDAY entry; `ordinal` is only allowed for MONTHLY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub until: Option<DateTime<Utc>>,
+    pub count: Option<u32>,
+}
+
+impl RRule {
+    // Every occurrence, in order, starting with `dtstart` itself
+    pub fn occurrences(&self, dtstart: DateTime<Utc>) -> Occurrences<'_> {
+        Occurrences {
+            rule: self,
+            dtstart,
+            next_period: 0,
+            pending: VecDeque::from([dtstart]),
+            emitted: 0,
+            empty_periods: 0,
+        }
+    }
+
+    // The occurrence at `index` (0 is `dtstart`), if the rule gets that far
+    pub fn nth(&self, dtstart: DateTime<Utc>, index: usize) -> Option<DateTime<Utc>> {
+        self.occurrences(dtstart).nth(index)
+    }
+
+    // The same rule with `count` occurrences taken off, for a series that continues
+    // another one. None when nothing is left.
+    pub fn skip_count(&self, taken: u32) -> Option<Self> {
+        let mut rule = self.clone();
+        if let Some(count) = self.count {
+            rule.count = Some(count.checked_sub(taken).filter(|left| *left > 0)?);
+        }
+        Some(rule)
+    }
+
+    // Days of one period that match the rule, before the time of day is applied
+    fn period_days(&self, dtstart: NaiveDate, period: u32) -> Vec<NaiveDate> {
+        let step = i64::from(period) * i64::from(self.interval);
+        let mut days = match self.freq {
+            Frequency::Daily => {
+                let day = dtstart + Duration::days(step);
+                if self.by_day.is_empty() || self.by_day.iter().any(|d| d.weekday == day.weekday())
+                {
+                    vec![day]
+                } else {
+                    vec![]
+                }
+            }
+            Frequency::Weekly => {
+                let monday = dtstart
+                    - Duration::days(i64::from(dtstart.weekday().num_days_from_monday()))
+                    + Duration::weeks(step);
+                if self.by_day.is_empty() {
+                    vec![
+                        monday
+                            + Duration::days(i64::from(dtstart.weekday().num_days_from_monday())),
+                    ]
+                } else {
+                    self.by_day
+                        .iter()
+                        .map(|d| {
+                            monday + Duration::days(i64::from(d.weekday.num_days_from_monday()))
+                        })
+                        .collect()
+                }
+            }
+            Frequency::Monthly => {
+                let months = i64::from(dtstart.month0()) + step;
+                let year = dtstart.year() + (months / 12) as i32;
+                let month = (months % 12) as u32 + 1;
+                let month_days: Vec<NaiveDate> =
+                    (1..=31).filter_map(|day| NaiveDate::from_ymd_opt(year, month, day)).collect();
+                if self.by_day.is_empty() {
+                    month_days.into_iter().filter(|day| day.day() == dtstart.day()).collect()
+                } else {
+                    self.by_day
+                        .iter()
+                        .flat_map(|by_day| {
+                            let matching: Vec<NaiveDate> = month_days
+                                .iter()
+                                .copied()
+                                .filter(|day| day.weekday() == by_day.weekday)
+                                .collect();
+                            match by_day.ordinal {
+                                None => matching,
+                                Some(n) if n > 0 => {
+                                    matching.get(n as usize - 1).copied().into_iter().collect()
+                                }
+                                Some(n) => matching
+                                    .len()
+                                    .checked_sub(n.unsigned_abs() as usize)
+                                    .and_then(|i| matching.get(i).copied())
+                                    .into_iter()
+                                    .collect(),
+                            }
+                        })
+                        .collect()
+                }
+            }
+        };
+        days.sort();
+        days.dedup();
+        days
+    }
+}
+
+pub struct Occurrences<'a> {
+    rule: &'a RRule,
+    dtstart: DateTime<Utc>,
+    // The first period is the one of `dtstart`: a weekly rule from a Monday with
+    // BYDAY=MO,TH has its second occurrence that Thursday
+    next_period: u32,
+    pending: VecDeque<DateTime<Utc>>,
+    emitted: u32,
+    empty_periods: u32,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<DateTime<Utc>> {
+        if self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+
+        while self.pending.is_empty() {
+            if self.empty_periods >= MAX_EMPTY_PERIODS {
+                return None;
+            }
+            let period = self.next_period;
+            self.next_period += 1;
+            let time = self.dtstart.time();
+            let found: Vec<DateTime<Utc>> = self
+                .rule
+                .period_days(self.dtstart.date_naive(), period)
+                .into_iter()
+                .map(|day| Utc.from_utc_datetime(&day.and_time(time)))
+                .filter(|at| *at > self.dtstart)
+                .collect();
+            if found.is_empty() {
+                self.empty_periods += 1;
+            } else {
+                self.empty_periods = 0;
+                self.pending.extend(found);
+            }
+        }
+
+        let next = self.pending.pop_front()?;
+        if self.rule.until.is_some_and(|until| next > until) {
+            self.pending.clear();
+            self.empty_periods = MAX_EMPTY_PERIODS;
+            return None;
+        }
+        self.emitted += 1;
+        Some(next)
+    }
+}
+
+impl FromStr for RRule {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut freq = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut until = None;
+        let mut count = None;
+
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) =
+                part.split_once('=').ok_or_else(|| bad(format!("'{}' is not NAME=VALUE", part)))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => {
+                            return Err(bad(format!(
+                                "FREQ={} is not supported: expected DAILY, WEEKLY or MONTHLY",
+                                other
+                            )));
+                        }
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|n| (1..=MAX_INTERVAL).contains(n))
+                        .ok_or_else(|| {
+                            bad(format!("INTERVAL must be between 1 and {}", MAX_INTERVAL))
+                        })?
+                }
+                "BYDAY" => {
+                    by_day = value.split(',').map(parse_by_day).collect::<Result<_>>()?;
+                }
+                "UNTIL" => until = Some(parse_until(value)?),
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|n| (1..=MAX_COUNT).contains(n))
+                            .ok_or_else(|| {
+                                bad(format!("COUNT must be between 1 and {}", MAX_COUNT))
+                            })?,
+                    )
+                }
+                other => return Err(bad(format!("{} is not supported", other))),
+            }
+        }
+
+        let freq = freq.ok_or_else(|| bad("FREQ is required".to_string()))?;
+        if until.is_some() && count.is_some() {
+            return Err(bad("UNTIL and COUNT cannot be used together".to_string()));
+        }
+        if freq != Frequency::Monthly && by_day.iter().any(|d: &ByDay| d.ordinal.is_some()) {
+            return Err(bad(
+                "BYDAY ordinals like 1MO are only allowed with FREQ=MONTHLY".to_string()
+            ));
+        }
+
+        Ok(Self { freq, interval, by_day, until, count })
+    }
+}
+
+// The canonical form, as stored
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let freq = match self.freq {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|d| {
+                    let code = WEEKDAY_CODES
+                        .iter()
+                        .find(|(_, weekday)| *weekday == d.weekday)
+                        .map_or("", |(code, _)| code);
+                    match d.ordinal {
+                        Some(n) => format!("{}{}", n, code),
+                        None => code.to_string(),
+                    }
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        Ok(())
+    }
+}
+
+const WEEKDAY_CODES: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn parse_by_day(value: &str) -> Result<ByDay> {
+    let value = value.trim().to_ascii_uppercase();
+    let (ordinal, weekday) = WEEKDAY_CODES
+        .iter()
+        .find_map(|(code, weekday)| Some((value.strip_suffix(code)?, *weekday)))
+        .ok_or_else(|| bad(format!("BYDAY '{}' is not a weekday like MO or -1FR", value)))?;
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(
+            n.trim_start_matches('+')
+                .parse::<i8>()
+                .ok()
+                .filter(|n| *n != 0 && (-5..=5).contains(n))
+                .ok_or_else(|| {
+                    bad(format!("BYDAY '{}': the ordinal must be 1 to 5 or -1 to -5", value))
+                })?,
+        ),
+    };
+    Ok(ByDay { ordinal, weekday })
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(at) = chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(Utc.from_utc_datetime(&at));
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default();
+        return Ok(Utc.from_utc_datetime(&day.and_time(end_of_day)));
+    }
+    Err(bad(format!(
+        "UNTIL '{}' must be a date (20251231) or a UTC time (20251231T170000Z)",
+        value
+    )))
+}
+
+fn bad(message: String) -> AppError {
+    AppError::BadRequest(format!("Invalid RRULE: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn take(rule: &str, dtstart: &str, n: usize) -> Vec<String> {
+        let rule: RRule = rule.parse().unwrap();
+        rule.occurrences(at(dtstart))
+            .take(n)
+            .map(|at| at.format("%Y-%m-%d %a").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn count_includes_dtstart() {
+        let days = take("FREQ=DAILY;INTERVAL=2;COUNT=3", "2025-01-01T09:00:00Z", 10);
+        assert_eq!(days, ["2025-01-01 Wed", "2025-01-03 Fri", "2025-01-05 Sun"]);
+    }
+
+    #[test]
+    fn until_date_covers_the_whole_day() {
+        let days = take("FREQ=DAILY;UNTIL=20250103", "2025-01-01T17:00:00Z", 10);
+        assert_eq!(days, ["2025-01-01 Wed", "2025-01-02 Thu", "2025-01-03 Fri"]);
+
+        let days = take("FREQ=DAILY;UNTIL=20250103T120000Z", "2025-01-01T17:00:00Z", 10);
+        assert_eq!(days, ["2025-01-01 Wed", "2025-01-02 Thu"]);
+    }
+
+    #[test]
+    fn weekly_by_day_starts_in_the_week_of_dtstart() {
+        let days = take("FREQ=WEEKLY;BYDAY=MO,TH", "2025-01-06T09:00:00Z", 4);
+        assert_eq!(days, ["2025-01-06 Mon", "2025-01-09 Thu", "2025-01-13 Mon", "2025-01-16 Thu"]);
+    }
+
+    #[test]
+    fn monthly_by_day_ordinals() {
+        let days = take("FREQ=MONTHLY;BYDAY=1MO", "2025-01-06T09:00:00Z", 3);
+        assert_eq!(days, ["2025-01-06 Mon", "2025-02-03 Mon", "2025-03-03 Mon"]);
+
+        let days = take("FREQ=MONTHLY;BYDAY=-1FR", "2025-01-31T09:00:00Z", 3);
+        assert_eq!(days, ["2025-01-31 Fri", "2025-02-28 Fri", "2025-03-28 Fri"]);
+    }
+
+    // There is no BYMONTHDAY: the day of the month comes from DTSTART, and months
+    // without it are skipped
+    #[test]
+    fn monthly_on_the_31st_skips_short_months() {
+        let days = take("FREQ=MONTHLY", "2025-01-31T09:00:00Z", 4);
+        assert_eq!(days, ["2025-01-31 Fri", "2025-03-31 Mon", "2025-05-31 Sat", "2025-07-31 Thu"]);
+    }
+
+    #[test]
+    fn split_continues_the_same_dates() {
+        let rule: RRule = "FREQ=WEEKLY;BYDAY=MO,TH;COUNT=6".parse().unwrap();
+        let dtstart = at("2025-01-06T09:00:00Z");
+        let all: Vec<_> = rule.occurrences(dtstart).collect();
+
+        // Splitting at the third occurrence: the new series starts there, with the
+        // two before it taken off the count
+        let taken = 2;
+        let rest = rule.skip_count(taken).unwrap();
+        let split_start = rule.nth(dtstart, taken as usize).unwrap();
+        let continued: Vec<_> = rest.occurrences(split_start).collect();
+        assert_eq!(continued, all[taken as usize..]);
+
+        assert_eq!(rule.skip_count(6), None);
+    }
+
+    #[test]
+    fn display_is_canonical() {
+        let rule: RRule = "RRULE:freq=monthly;byday=+1mo,-1fr;interval=2;count=4".parse().unwrap();
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;INTERVAL=2;BYDAY=1MO,-1FR;COUNT=4");
+        assert_eq!(rule.to_string().parse::<RRule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "INTERVAL=2",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20250101",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=MONTHLY;BYDAY=MO€",
+            "FREQ=MONTHLY;BYDAY=€MO",
+            "FREQ=WEEKLY;BYDAY=€",
+            "FREQ=WEEKLY;BYDAY=",
+        ] {
+            assert!(
+                matches!(rule.parse::<RRule>(), Err(AppError::BadRequest(_))),
+                "{} should be rejected",
+                rule
+            );
+        }
+    }
+}
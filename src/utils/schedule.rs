@@ -0,0 +1,328 @@
+// Critical path method (CPM) over a dependency DAG.
+//
+// Times are minutes relative to `now`. Work that already happened keeps its real
+// times: a started task's start is fixed, a completed task's finish too. Open tasks
+// are scheduled from now on, as early as their links allow.
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::{ScheduleLink, ScheduleResponse, ScheduleTaskRow, ScheduledTask},
+};
+
+struct Node {
+    duration: i64,
+    // Start/finish are fixed by what already happened
+    fixed_start: Option<i64>,
+    fixed_finish: Option<i64>,
+    es: i64,
+    ef: i64,
+    ls: i64,
+    lf: i64,
+}
+
+pub fn compute(
+    task_id: Uuid,
+    now: DateTime<Utc>,
+    tasks: Vec<ScheduleTaskRow>,
+    links: Vec<ScheduleLink>,
+) -> Result<ScheduleResponse> {
+    let offset = |at: DateTime<Utc>| (at - now).num_minutes();
+
+    let mut nodes: HashMap<Uuid, Node> = HashMap::new();
+    for task in &tasks {
+        let estimate = i64::from(task.estimated_minutes.unwrap_or(0));
+        let node = match task.status.as_str() {
+            "completed" => {
+                let finish = offset(task.completed_at.unwrap_or(now));
+                let start = task.started_at.map(offset).unwrap_or(finish).min(finish);
+                Node::fixed(start, Some(finish))
+            }
+            _ => match task.started_at {
+                // Still running: whatever is left of the estimate, from now on
+                Some(started_at) => {
+                    let start = offset(started_at);
+                    Node::fixed(start, None).with_duration((start + estimate).max(0) - start)
+                }
+                None => Node::open(estimate),
+            },
+        };
+        nodes.insert(task.id, node);
+    }
+
+    // Predecessor links per task and successor links per task
+    let mut preds: HashMap<Uuid, Vec<&ScheduleLink>> = HashMap::new();
+    let mut succs: HashMap<Uuid, Vec<&ScheduleLink>> = HashMap::new();
+    for link in &links {
+        preds.entry(link.task_id).or_default().push(link);
+        succs.entry(link.depends_on).or_default().push(link);
+    }
+
+    let order = topological_order(&tasks, &preds, &succs)?;
+
+    // Forward pass: earliest start and finish
+    for id in &order {
+        let duration = nodes[id].duration;
+        let mut es = 0;
+        for link in preds.get(id).into_iter().flatten() {
+            let pred = &nodes[&link.depends_on];
+            let lag = i64::from(link.lag_minutes);
+            es = es.max(match link.kind.as_str() {
+                "start_to_start" => pred.es + lag,
+                "finish_to_finish" => pred.ef + lag - duration,
+                _ => pred.ef + lag,
+            });
+        }
+
+        let node = nodes.get_mut(id).expect("ordered task is in the graph");
+        node.es = node.fixed_start.unwrap_or(es);
+        node.ef = node.fixed_finish.unwrap_or(node.es + duration);
+    }
+
+    let finish = nodes.values().map(|node| node.ef).max().unwrap_or(0);
+
+    // Backward pass: latest start and finish that do not push out the overall finish
+    for id in order.iter().rev() {
+        let duration = nodes[id].duration;
+        let mut lf = finish;
+        for link in succs.get(id).into_iter().flatten() {
+            let succ = &nodes[&link.task_id];
+            let lag = i64::from(link.lag_minutes);
+            lf = lf.min(match link.kind.as_str() {
+                "start_to_start" => succ.ls - lag + duration,
+                "finish_to_finish" => succ.lf - lag,
+                _ => succ.ls - lag,
+            });
+        }
+
+        let node = nodes.get_mut(id).expect("ordered task is in the graph");
+        node.lf = node.fixed_finish.unwrap_or(lf);
+        node.ls = node.fixed_start.unwrap_or(node.lf - duration);
+    }
+
+    let by_id: HashMap<Uuid, &ScheduleTaskRow> = tasks.iter().map(|t| (t.id, t)).collect();
+    let at = |minutes: i64| now + Duration::minutes(minutes);
+
+    let mut scheduled = Vec::with_capacity(order.len());
+    for id in &order {
+        let task = by_id[id];
+        let node = &nodes[id];
+        let open = task.status != "completed";
+        let slack = node.lf - node.ef;
+        let earliest_finish = at(node.ef);
+
+        scheduled.push(ScheduledTask {
+            task_id: task.id,
+            title: task.title.clone(),
+            status: task.status.clone(),
+            duration_minutes: node.ef - node.es,
+            earliest_start: at(node.es),
+            earliest_finish,
+            latest_start: at(node.ls),
+            latest_finish: at(node.lf),
+            slack_minutes: slack,
+            critical: open && slack == 0,
+            due_date: task.due_date,
+            due_date_infeasible: open && task.due_date.is_some_and(|due| earliest_finish > due),
+        });
+    }
+
+    let mut critical: Vec<&ScheduledTask> = scheduled.iter().filter(|t| t.critical).collect();
+    critical.sort_by_key(|t| (t.earliest_start, t.earliest_finish));
+
+    Ok(ScheduleResponse {
+        task_id,
+        computed_at: now,
+        finish: at(finish),
+        critical_path: critical.iter().map(|t| t.task_id).collect(),
+        infeasible_due_dates: scheduled
+            .iter()
+            .filter(|t| t.due_date_infeasible)
+            .map(|t| t.task_id)
+            .collect(),
+        unestimated: tasks
+            .iter()
+            .filter(|t| t.status != "completed" && t.estimated_minutes.is_none())
+            .map(|t| t.id)
+            .collect(),
+        tasks: scheduled,
+    })
+}
+
+impl Node {
+    fn open(duration: i64) -> Self {
+        Self { duration, fixed_start: None, fixed_finish: None, es: 0, ef: 0, ls: 0, lf: 0 }
+    }
+
+    fn fixed(start: i64, finish: Option<i64>) -> Self {
+        let duration = finish.map_or(0, |finish| finish - start);
+        Self { fixed_start: Some(start), fixed_finish: finish, ..Self::open(duration) }
+    }
+
+    fn with_duration(self, duration: i64) -> Self {
+        Self { duration, ..self }
+    }
+}
+
+// Kahn's algorithm; blocking links never form cycles, but a schedule must not loop
+// forever if the data says otherwise
+fn topological_order(
+    tasks: &[ScheduleTaskRow],
+    preds: &HashMap<Uuid, Vec<&ScheduleLink>>,
+    succs: &HashMap<Uuid, Vec<&ScheduleLink>>,
+) -> Result<Vec<Uuid>> {
+    let mut waiting: HashMap<Uuid, usize> =
+        tasks.iter().map(|t| (t.id, preds.get(&t.id).map_or(0, Vec::len))).collect();
+
+    let mut ready: Vec<Uuid> =
+        waiting.iter().filter(|&(_, &n)| n == 0).map(|(&id, _)| id).collect();
+    ready.sort();
+    let mut queue = VecDeque::from(ready);
+
+    let mut order = Vec::with_capacity(tasks.len());
+    while let Some(id) = queue.pop_front() {
+        order.push(id);
+        for link in succs.get(&id).into_iter().flatten() {
+            let count = waiting.get_mut(&link.task_id).expect("linked task is in the graph");
+            *count -= 1;
+            if *count == 0 {
+                queue.push_back(link.task_id);
+            }
+        }
+    }
+
+    if order.len() != tasks.len() {
+        return Err(AppError::Conflict("Dependency graph contains a cycle".to_string()));
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn task(n: u128, minutes: i32) -> ScheduleTaskRow {
+        ScheduleTaskRow {
+            id: id(n),
+            title: format!("task {}", n),
+            status: "pending".to_string(),
+            started_at: None,
+            completed_at: None,
+            due_date: None,
+            estimated_minutes: Some(minutes),
+        }
+    }
+
+    fn link(task: u128, depends_on: u128, kind: &str, lag_minutes: i32) -> ScheduleLink {
+        ScheduleLink {
+            task_id: id(task),
+            depends_on: id(depends_on),
+            kind: kind.to_string(),
+            lag_minutes,
+        }
+    }
+
+    fn schedule(tasks: Vec<ScheduleTaskRow>, links: Vec<ScheduleLink>) -> ScheduleResponse {
+        compute(id(1), Utc::now(), tasks, links).unwrap()
+    }
+
+    // Earliest start, earliest finish and slack of each task, in minutes from now
+    fn times(schedule: &ScheduleResponse) -> HashMap<Uuid, (i64, i64, i64)> {
+        let now = schedule.computed_at;
+        schedule
+            .tasks
+            .iter()
+            .map(|t| {
+                let es = (t.earliest_start - now).num_minutes();
+                let ef = (t.earliest_finish - now).num_minutes();
+                (t.task_id, (es, ef, t.slack_minutes))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finish_to_start_waits_for_the_finish_plus_lag() {
+        let schedule =
+            schedule(vec![task(1, 60), task(2, 30)], vec![link(2, 1, "finish_to_start", 15)]);
+        let times = times(&schedule);
+        assert_eq!(times[&id(1)], (0, 60, 0));
+        assert_eq!(times[&id(2)], (75, 105, 0));
+        assert_eq!(schedule.finish, schedule.computed_at + Duration::minutes(105));
+        assert_eq!(schedule.critical_path, [id(1), id(2)]);
+    }
+
+    #[test]
+    fn start_to_start_waits_for_the_start_plus_lag() {
+        let schedule =
+            schedule(vec![task(1, 60), task(2, 30)], vec![link(2, 1, "start_to_start", 10)]);
+        let times = times(&schedule);
+        assert_eq!(times[&id(1)], (0, 60, 0));
+        assert_eq!(times[&id(2)], (10, 40, 20));
+        assert_eq!(schedule.critical_path, [id(1)]);
+    }
+
+    #[test]
+    fn finish_to_finish_starts_late_enough_to_finish_after() {
+        let schedule =
+            schedule(vec![task(1, 60), task(2, 30)], vec![link(2, 1, "finish_to_finish", 0)]);
+        let times = times(&schedule);
+        assert_eq!(times[&id(1)], (0, 60, 0));
+        assert_eq!(times[&id(2)], (30, 60, 0));
+        assert_eq!(schedule.critical_path, [id(1), id(2)]);
+    }
+
+    // 1 -> 2 -> 4 and 1 -> 3 -> 4: the longer branch is critical, the other has slack
+    #[test]
+    fn diamond_follows_the_longest_branch() {
+        let schedule = schedule(
+            vec![task(1, 10), task(2, 20), task(3, 50), task(4, 10)],
+            vec![
+                link(2, 1, "finish_to_start", 0),
+                link(3, 1, "finish_to_start", 0),
+                link(4, 2, "finish_to_start", 0),
+                link(4, 3, "finish_to_start", 0),
+            ],
+        );
+        let times = times(&schedule);
+        assert_eq!(times[&id(1)], (0, 10, 0));
+        assert_eq!(times[&id(2)], (10, 30, 30));
+        assert_eq!(times[&id(3)], (10, 60, 0));
+        assert_eq!(times[&id(4)], (60, 70, 0));
+        assert_eq!(schedule.critical_path, [id(1), id(3), id(4)]);
+        let order: Vec<Uuid> = schedule.tasks.iter().map(|t| t.task_id).collect();
+        assert_eq!(order.first(), Some(&id(1)));
+        assert_eq!(order.last(), Some(&id(4)));
+    }
+
+    #[test]
+    fn started_task_keeps_its_start_and_finishes_the_rest() {
+        let now = Utc::now();
+        let mut started = task(1, 60);
+        started.status = "in_progress".to_string();
+        started.started_at = Some(now - Duration::minutes(20));
+        let schedule =
+            compute(id(1), now, vec![started, task(2, 30)], vec![link(2, 1, "finish_to_start", 0)])
+                .unwrap();
+        let first = &schedule.tasks[0];
+        assert_eq!(first.earliest_start, now - Duration::minutes(20));
+        assert_eq!(first.earliest_finish, now + Duration::minutes(40));
+        assert_eq!(schedule.finish, now + Duration::minutes(70));
+    }
+
+    #[test]
+    fn cycle_is_refused() {
+        let result = compute(
+            id(1),
+            Utc::now(),
+            vec![task(1, 10), task(2, 10)],
+            vec![link(2, 1, "finish_to_start", 0), link(1, 2, "finish_to_start", 0)],
+        );
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+    }
+}
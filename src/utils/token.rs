@@ -0,0 +1,7 @@
+// Random token for secrets and capability URLs: 256 bits from the OS random source,
+// hex-encoded after a prefix that tells what the token is for
+pub fn random_token(prefix: &str) -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("OS random source is available");
+    format!("{}{}", prefix, hex::encode(bytes))
+}
@@ -0,0 +1,14 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// `sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">`. Signing the timestamp lets
+// receivers reject replayed requests.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}